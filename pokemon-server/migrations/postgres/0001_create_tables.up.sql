@@ -0,0 +1,129 @@
+-- Tables of the releases before versioned migrations. Every statement
+-- tolerates a database created by those releases, which adopts version 1
+-- as it is and gets the columns and values added since then.
+
+DO $$ BEGIN
+    CREATE TYPE side AS ENUM('Buy', 'Sell');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE card AS ENUM('Pikachu', 'Bulbasaur', 'Charmander', 'Squirtle');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE orderstatus AS ENUM('Confirmed', 'Filled', 'Dropped', 'Cancelled', 'PartiallyFilled', 'Expired', 'Killed');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+ALTER TYPE orderstatus ADD VALUE IF NOT EXISTS 'Cancelled';
+ALTER TYPE orderstatus ADD VALUE IF NOT EXISTS 'PartiallyFilled';
+ALTER TYPE orderstatus ADD VALUE IF NOT EXISTS 'Expired';
+ALTER TYPE orderstatus ADD VALUE IF NOT EXISTS 'Killed';
+
+DO $$ BEGIN
+    CREATE TYPE timeinforce AS ENUM('GTC', 'IOC', 'FOK');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE ordertype AS ENUM('Limit', 'Market');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE ledgerkind AS ENUM('Deposit', 'Withdrawal', 'Mint', 'Burn');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+create table if not exists request_table(
+    uuid UUID,
+    tm timestamptz,
+    side Side,
+    order_px BIGINT,
+    vol INTEGER,
+    card Card,
+    trader_id INTEGER,
+    time_in_force TimeInForce default 'GTC',
+    order_type OrderType default 'Limit'
+);
+alter table request_table add column if not exists time_in_force TimeInForce default 'GTC';
+alter table request_table add column if not exists order_type OrderType default 'Limit';
+
+create table if not exists status_table(
+    uuid UUID,
+    status OrderStatus
+);
+
+-- trades of former releases are timed by the upgrade
+create table if not exists trade_table(
+    buy_uuid UUID,
+    sell_uuid UUID,
+    buy_side_id INTEGER,
+    sell_side_id INTEGER,
+    tx_price BIGINT,
+    tx_vol INTEGER,
+    card Card,
+    tm timestamptz default now()
+);
+alter table trade_table add column if not exists tm timestamptz default now();
+
+create table if not exists amend_table(
+    uuid UUID,
+    tm timestamptz,
+    order_px BIGINT,
+    vol INTEGER,
+    keep_priority BOOLEAN
+);
+
+-- a trader owns a single key pair at a time
+create table if not exists trader_table(
+    trader_id INTEGER primary key,
+    api_key TEXT unique not null,
+    secret TEXT not null,
+    created_at timestamptz default now()
+);
+
+-- cash of a trader in cents
+create table if not exists account_table(
+    trader_id INTEGER primary key,
+    cash BIGINT not null default 0,
+    reserved_cash BIGINT not null default 0
+);
+
+create table if not exists inventory_table(
+    trader_id INTEGER,
+    card Card,
+    qty INTEGER not null default 0,
+    reserved_qty INTEGER not null default 0,
+    primary key (trader_id, card)
+);
+
+-- entries are only appended and never changed
+create table if not exists ledger_table(
+    seq BIGSERIAL primary key,
+    tm timestamptz not null,
+    trader_id INTEGER not null,
+    operator_id INTEGER not null,
+    kind LedgerKind not null,
+    card Card,
+    amount BIGINT not null default 0,
+    qty INTEGER not null default 0,
+    reason TEXT not null
+);
+create or replace rule ledger_table_no_update as on update to ledger_table do instead nothing;
+create or replace rule ledger_table_no_delete as on delete to ledger_table do instead nothing;
+
+-- prices of former releases were stored as FLOAT8 in dollars, convert them into cents
+DO $$ BEGIN
+    IF (select data_type from information_schema.columns where table_name = 'request_table' and column_name = 'order_px') = 'double precision' THEN
+        alter table request_table alter column order_px type BIGINT using round(order_px * 100)::BIGINT;
+    END IF;
+    IF (select data_type from information_schema.columns where table_name = 'trade_table' and column_name = 'tx_price') = 'double precision' THEN
+        alter table trade_table alter column tx_price type BIGINT using round(tx_price * 100)::BIGINT;
+    END IF;
+    IF (select data_type from information_schema.columns where table_name = 'amend_table' and column_name = 'order_px') = 'double precision' THEN
+        alter table amend_table alter column order_px type BIGINT using round(order_px * 100)::BIGINT;
+    END IF;
+END $$;
@@ -0,0 +1,17 @@
+-- an order is requested and given a status once, amendments and trades refer to it
+alter table request_table add primary key (uuid);
+alter table status_table add primary key (uuid);
+
+-- histories of a trader by date and the resting orders of today
+create index request_table_trader_id_tm on request_table (trader_id, tm);
+create index request_table_tm on request_table (tm);
+create index amend_table_uuid_tm on amend_table (uuid, tm);
+
+-- fills of an order, trades of a trader and candles of a card
+create index trade_table_buy_uuid on trade_table (buy_uuid);
+create index trade_table_sell_uuid on trade_table (sell_uuid);
+create index trade_table_buy_side_id_tm on trade_table (buy_side_id, tm);
+create index trade_table_sell_side_id_tm on trade_table (sell_side_id, tm);
+create index trade_table_card_tm on trade_table (card, tm);
+
+create index ledger_table_trader_id on ledger_table (trader_id);
@@ -0,0 +1,5 @@
+-- every start of the server is numbered, so the feed of a run numbers its events past the former runs
+create table if not exists run_table(
+    run BIGSERIAL primary key,
+    started_at timestamptz not null default now()
+);
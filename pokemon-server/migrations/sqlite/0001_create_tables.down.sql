@@ -0,0 +1,8 @@
+drop table if exists ledger_table;
+drop table if exists inventory_table;
+drop table if exists account_table;
+drop table if exists trader_table;
+drop table if exists amend_table;
+drop table if exists trade_table;
+drop table if exists status_table;
+drop table if exists request_table;
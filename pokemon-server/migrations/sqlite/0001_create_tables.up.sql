@@ -0,0 +1,75 @@
+-- Tables of the Postgres schema, where the enum types become TEXT columns
+-- checked against their values, prices are counted in cents and times are
+-- kept as RFC 3339 text in UTC.
+
+create table if not exists request_table(
+    uuid TEXT,
+    tm TEXT,
+    side TEXT check (side in ('Buy', 'Sell')),
+    order_px INTEGER,
+    vol INTEGER,
+    card TEXT check (card in ('Pikachu', 'Bulbasaur', 'Charmander', 'Squirtle')),
+    trader_id INTEGER,
+    time_in_force TEXT default 'GTC' check (time_in_force in ('GTC', 'IOC', 'FOK')),
+    order_type TEXT default 'Limit' check (order_type in ('Limit', 'Market'))
+);
+
+create table if not exists status_table(
+    uuid TEXT,
+    status TEXT check (status in ('Confirmed', 'Filled', 'Dropped', 'Cancelled', 'PartiallyFilled', 'Expired', 'Killed'))
+);
+
+create table if not exists trade_table(
+    buy_uuid TEXT,
+    sell_uuid TEXT,
+    buy_side_id INTEGER,
+    sell_side_id INTEGER,
+    tx_price INTEGER,
+    tx_vol INTEGER,
+    card TEXT check (card in ('Pikachu', 'Bulbasaur', 'Charmander', 'Squirtle')),
+    tm TEXT
+);
+
+create table if not exists amend_table(
+    uuid TEXT,
+    tm TEXT,
+    order_px INTEGER,
+    vol INTEGER,
+    keep_priority INTEGER
+);
+
+create table if not exists trader_table(
+    trader_id INTEGER primary key,
+    api_key TEXT unique not null,
+    secret TEXT not null,
+    created_at TEXT default current_timestamp
+);
+
+create table if not exists account_table(
+    trader_id INTEGER primary key,
+    cash INTEGER not null default 0,
+    reserved_cash INTEGER not null default 0
+);
+
+create table if not exists inventory_table(
+    trader_id INTEGER,
+    card TEXT check (card in ('Pikachu', 'Bulbasaur', 'Charmander', 'Squirtle')),
+    qty INTEGER not null default 0,
+    reserved_qty INTEGER not null default 0,
+    primary key (trader_id, card)
+);
+
+create table if not exists ledger_table(
+    seq INTEGER primary key autoincrement,
+    tm TEXT not null,
+    trader_id INTEGER not null,
+    operator_id INTEGER not null,
+    kind TEXT not null check (kind in ('Deposit', 'Withdrawal', 'Mint', 'Burn')),
+    card TEXT check (card in ('Pikachu', 'Bulbasaur', 'Charmander', 'Squirtle')),
+    amount INTEGER not null default 0,
+    qty INTEGER not null default 0,
+    reason TEXT not null
+);
+-- entries are only appended and never changed
+create trigger if not exists ledger_table_no_update before update on ledger_table begin select raise(ignore); end;
+create trigger if not exists ledger_table_no_delete before delete on ledger_table begin select raise(ignore); end;
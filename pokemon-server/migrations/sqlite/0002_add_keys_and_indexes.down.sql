@@ -0,0 +1,14 @@
+drop index if exists ledger_table_trader_id;
+
+drop index if exists trade_table_card_tm;
+drop index if exists trade_table_sell_side_id_tm;
+drop index if exists trade_table_buy_side_id_tm;
+drop index if exists trade_table_sell_uuid;
+drop index if exists trade_table_buy_uuid;
+
+drop index if exists amend_table_uuid_tm;
+drop index if exists request_table_tm;
+drop index if exists request_table_trader_id_tm;
+
+drop index if exists status_table_uuid;
+drop index if exists request_table_uuid;
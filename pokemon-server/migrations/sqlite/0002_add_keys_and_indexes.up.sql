@@ -0,0 +1,17 @@
+-- SQLite cannot add a primary key to a table, a unique index on uuid keys it alike
+create unique index request_table_uuid on request_table (uuid);
+create unique index status_table_uuid on status_table (uuid);
+
+-- histories of a trader by date and the resting orders of today
+create index request_table_trader_id_tm on request_table (trader_id, tm);
+create index request_table_tm on request_table (tm);
+create index amend_table_uuid_tm on amend_table (uuid, tm);
+
+-- fills of an order, trades of a trader and candles of a card
+create index trade_table_buy_uuid on trade_table (buy_uuid);
+create index trade_table_sell_uuid on trade_table (sell_uuid);
+create index trade_table_buy_side_id_tm on trade_table (buy_side_id, tm);
+create index trade_table_sell_side_id_tm on trade_table (sell_side_id, tm);
+create index trade_table_card_tm on trade_table (card, tm);
+
+create index ledger_table_trader_id on ledger_table (trader_id);
@@ -0,0 +1 @@
+drop table if exists run_table;
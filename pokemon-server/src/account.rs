@@ -0,0 +1,490 @@
+use crate::data_type::{Card, LedgerEntry, Price, RejectReason, RequestOrder, Side};
+use crate::store::{Store, Write};
+use schemars::JsonSchema;
+use std::cmp;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tide::prelude::Serialize;
+
+// cards of a kind held by a trader, the reserved ones are promised to resting sell orders
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct Holding {
+    qty: i32,
+    reserved: i32,
+}
+
+impl Holding {
+    pub fn get_qty(&self) -> &i32 {
+        &self.qty
+    }
+
+    pub fn get_reserved(&self) -> &i32 {
+        &self.reserved
+    }
+
+    pub fn get_available(&self) -> i32 {
+        self.qty - self.reserved
+    }
+}
+
+/**
+ * Cash and cards of a trader. Reserved cash backs the resting buy
+ * orders and reserved cards the resting sell orders, so only the
+ * available part could be committed to a new order
+ **/
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct Account {
+    trader_id: i32,
+    cash: Price,
+    reserved_cash: Price,
+    cards: HashMap<Card, Holding>,
+}
+
+impl Account {
+    fn new(trader_id: i32) -> Self {
+        Self { trader_id, ..Self::default() }
+    }
+
+    pub fn get_trader_id(&self) -> &i32 {
+        &self.trader_id
+    }
+
+    pub fn get_cash(&self) -> &Price {
+        &self.cash
+    }
+
+    pub fn get_reserved_cash(&self) -> &Price {
+        &self.reserved_cash
+    }
+
+    pub fn get_available_cash(&self) -> Price {
+        Price::from_cents(self.cash.get_cents() - self.reserved_cash.get_cents())
+    }
+
+    pub fn get_cards(&self) -> &HashMap<Card, Holding> {
+        &self.cards
+    }
+
+    pub fn get_holding(&self, card: &Card) -> Holding {
+        self.cards.get(card).copied().unwrap_or_default()
+    }
+
+    // cash for a buy order, cards for a sell order
+    fn reserve(&mut self, side: Side, card: &Card, px: &Price, vol: i32) -> Result<(), RejectReason> {
+        match side {
+            Side::Buy => {
+                let amount = px.times(vol);
+                if self.get_available_cash() < amount {
+                    return Err(RejectReason::InsufficientCash);
+                }
+                self.reserved_cash = Price::from_cents(self.reserved_cash.get_cents() + amount.get_cents());
+            }
+            Side::Sell => {
+                let holding = self.cards.entry(*card).or_default();
+                if holding.get_available() < vol {
+                    return Err(RejectReason::InsufficientCards);
+                }
+                holding.reserved += vol;
+            }
+        }
+        Ok(())
+    }
+
+    fn release(&mut self, side: Side, card: &Card, px: &Price, vol: i32) {
+        match side {
+            Side::Buy => {
+                let amount = cmp::min(px.times(vol), self.reserved_cash);
+                self.reserved_cash = Price::from_cents(self.reserved_cash.get_cents() - amount.get_cents());
+            }
+            Side::Sell => {
+                let holding = self.cards.entry(*card).or_default();
+                holding.reserved -= vol.min(holding.reserved);
+            }
+        }
+    }
+
+    fn add_cash(&mut self, amount: &Price) -> Result<(), RejectReason> {
+        if self.get_available_cash().get_cents() + amount.get_cents() < 0 {
+            return Err(RejectReason::InsufficientCash);
+        }
+        self.cash = Price::from_cents(self.cash.get_cents() + amount.get_cents());
+        Ok(())
+    }
+
+    fn add_cards(&mut self, card: &Card, qty: i32) -> Result<(), RejectReason> {
+        let holding = self.cards.entry(*card).or_default();
+        if holding.get_available() + qty < 0 {
+            return Err(RejectReason::InsufficientCards);
+        }
+        holding.qty += qty;
+        Ok(())
+    }
+
+    fn get_writes(&self) -> Vec<Write> {
+        let mut writes = vec![Write::Account(self.trader_id, self.cash, self.reserved_cash)];
+        for (card, holding) in self.cards.iter() {
+            writes.push(Write::Inventory(self.trader_id, *card, holding.qty, holding.reserved));
+        }
+        writes
+    }
+}
+
+// why a ledger entry is not booked, in which case no balance is changed either
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookingError {
+    Rejected(RejectReason),
+    StoreFailed(String),
+}
+
+/**
+ * A balance of a trader differing from the one implied by the ledger and
+ * the trades, counted in cents for cash (no card) or in units of the card
+ **/
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Reconciliation {
+    trader_id: i32,
+    card: Option<Card>,
+    booked: i64,
+    expected: i64,
+}
+
+impl Reconciliation {
+    pub fn get_trader_id(&self) -> &i32 {
+        &self.trader_id
+    }
+
+    pub fn get_card(&self) -> &Option<Card> {
+        &self.card
+    }
+
+    pub fn get_booked(&self) -> &i64 {
+        &self.booked
+    }
+
+    pub fn get_expected(&self) -> &i64 {
+        &self.expected
+    }
+}
+
+/**
+ * Accounts of all traders, cached in memory and written through to
+ * account_table and inventory_table. The handle is shared by the
+ * matching engines; a change is stored while the cache is locked so
+ * that engines never interleave their writes of a trader. Disabled
+ * accounts let every order through without any bookkeeping
+ **/
+#[derive(Debug, Clone, Default)]
+pub struct Accounts {
+    enabled: bool,
+    accounts: Arc<Mutex<HashMap<i32, Account>>>,
+}
+
+impl Accounts {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            accounts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> &bool {
+        &self.enabled
+    }
+
+    // fill the cache with the balances stored in the database
+    pub fn load(&self, db: &mut dyn Store) {
+        let mut accounts = self.accounts.lock().unwrap();
+        for (trader_id, cash, reserved_cash) in db.get_accounts() {
+            let account = accounts.entry(trader_id).or_insert_with_key(|id| Account::new(*id));
+            account.cash = cash;
+            account.reserved_cash = reserved_cash;
+        }
+        for (trader_id, card, qty, reserved) in db.get_inventories() {
+            let account = accounts.entry(trader_id).or_insert_with_key(|id| Account::new(*id));
+            account.cards.insert(card, Holding { qty, reserved });
+        }
+    }
+
+    pub fn get_account(&self, trader_id: &i32) -> Account {
+        self.accounts.lock().unwrap().get(trader_id).cloned().unwrap_or_else(|| Account::new(*trader_id))
+    }
+
+    // whether the trader could afford the order at px, nothing is reserved yet
+    pub fn check(&self, req: &RequestOrder, px: &Price) -> Result<(), RejectReason> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.get_account(&req.get_trade_id()).reserve(req.get_side(), &req.get_card(), px, req.get_vol())
+    }
+
+    /**
+     * Stage the changes of an order. The cache stays locked until they are
+     * applied or dropped, so no other engine reads or stores the balances
+     * of a trader while the writes of the order are in flight
+     **/
+    pub fn begin(&self) -> AccountChanges<'_> {
+        AccountChanges {
+            accounts: if self.enabled { Some(self.accounts.lock().unwrap()) } else { None },
+            changed: HashMap::new(),
+        }
+    }
+
+    /**
+     * Apply a deposit, withdrawal, mint or burn of an operator and append
+     * it to the ledger. The balance and the entry are stored in one
+     * transaction, and the cache takes the change only once they are
+     * stored. The account after the change is returned
+     **/
+    pub fn book(&self, db: &mut dyn Store, entry: &LedgerEntry) -> Result<Account, BookingError> {
+        let mut changes = self.begin();
+        let credited = match entry.get_card() {
+            Some(card) => changes.credit_cards(entry.get_trader_id(), card, *entry.get_qty()),
+            None => changes.credit_cash(entry.get_trader_id(), entry.get_amount()),
+        };
+        credited.map_err(BookingError::Rejected)?;
+
+        let mut writes = changes.get_writes();
+        writes.push(Write::Ledger(entry.clone()));
+        db.write(&writes).map_err(BookingError::StoreFailed)?;
+        changes.apply();
+        Ok(self.get_account(entry.get_trader_id()))
+    }
+
+    pub fn get_ledger(&self, db: &mut dyn Store, trader_id: &i32) -> Vec<LedgerEntry> {
+        db.get_ledger(trader_id)
+    }
+
+    /**
+     * Compare the cash and cards of every trader with the ones implied by
+     * the ledger and the trades of trade_table. Only differences are listed,
+     * so it comes back empty as long as balances are kept from the start
+     **/
+    pub fn reconcile(&self, db: &mut dyn Store) -> Vec<Reconciliation> {
+        let mut expected: HashMap<(i32, Option<Card>), i64> = HashMap::new();
+        for (trader_id, cash) in db.get_expected_cash() {
+            expected.insert((trader_id, None), cash);
+        }
+        for (trader_id, card, qty) in db.get_expected_cards() {
+            expected.insert((trader_id, Some(card)), qty as i64);
+        }
+
+        let mut booked: HashMap<(i32, Option<Card>), i64> = HashMap::new();
+        for (trader_id, account) in self.accounts.lock().unwrap().iter() {
+            booked.insert((*trader_id, None), account.cash.get_cents());
+            for (card, holding) in account.cards.iter() {
+                booked.insert((*trader_id, Some(*card)), holding.qty as i64);
+            }
+        }
+
+        let mut keys: Vec<(i32, Option<Card>)> = expected.keys().chain(booked.keys()).copied().collect();
+        keys.sort_by_key(|(trader_id, card)| (*trader_id, card.map(|card| card as u8)));
+        keys.dedup();
+        keys.into_iter()
+            .map(|key| Reconciliation {
+                trader_id: key.0,
+                card: key.1,
+                booked: booked.get(&key).copied().unwrap_or(0),
+                expected: expected.get(&key).copied().unwrap_or(0),
+            })
+            .filter(|reconciliation| reconciliation.booked != reconciliation.expected)
+            .collect()
+    }
+}
+
+/**
+ * Balances changed by an order, taken by the cache of the accounts only
+ * once the writes of the order are stored. Dropping the changes leaves
+ * the cache as it was, as if the order had never been processed
+ **/
+pub struct AccountChanges<'a> {
+    accounts: Option<MutexGuard<'a, HashMap<i32, Account>>>,
+    changed: HashMap<i32, Account>,
+}
+
+impl AccountChanges<'_> {
+    fn update<F>(&mut self, trader_id: &i32, change: F) -> Result<(), RejectReason>
+    where
+        F: FnOnce(&mut Account) -> Result<(), RejectReason>,
+    {
+        let accounts = match &self.accounts {
+            Some(accounts) => accounts,
+            None => return Ok(()),
+        };
+
+        let mut account = self.changed.get(trader_id).or_else(|| accounts.get(trader_id)).cloned().unwrap_or_else(|| Account::new(*trader_id));
+        change(&mut account)?;
+        self.changed.insert(*trader_id, account);
+        Ok(())
+    }
+
+    // reserve cash of vol units at px for a buy order, or vol cards for a sell order
+    pub fn reserve(&mut self, req: &RequestOrder, px: &Price, vol: i32) -> Result<(), RejectReason> {
+        self.update(&req.get_trade_id(), |account| account.reserve(req.get_side(), &req.get_card(), px, vol))
+    }
+
+    pub fn release(&mut self, req: &RequestOrder, px: &Price, vol: i32) {
+        let _ = self.update(&req.get_trade_id(), |account| {
+            account.release(req.get_side(), &req.get_card(), px, vol);
+            Ok(())
+        });
+    }
+
+    // deposit, or withdraw by a negative amount out of the available cash
+    pub fn credit_cash(&mut self, trader_id: &i32, amount: &Price) -> Result<(), RejectReason> {
+        self.update(trader_id, |account| account.add_cash(amount))
+    }
+
+    // add cards, or remove them by a negative qty out of the available ones
+    pub fn credit_cards(&mut self, trader_id: &i32, card: &Card, qty: i32) -> Result<(), RejectReason> {
+        self.update(trader_id, |account| account.add_cards(card, qty))
+    }
+
+    // move the reservation of a resting order to its amended terms, kept as it was if unaffordable
+    pub fn adjust(&mut self, req: &RequestOrder, from: (Price, i32), to: (Price, i32)) -> Result<(), RejectReason> {
+        self.update(&req.get_trade_id(), |account| {
+            account.release(req.get_side(), &req.get_card(), &from.0, from.1);
+            account.reserve(req.get_side(), &req.get_card(), &to.0, to.1)
+        })
+    }
+
+    /**
+     * Settle a fill of the incoming order against a resting order of the
+     * counterparty. The buyer pays the traded price out of the cash it
+     * reserved at reserved_px, and the seller delivers reserved cards
+     **/
+    pub fn transfer(&mut self, req: &RequestOrder, counterparty: &i32, reserved_px: &Price, traded_px: &Price, qty: i32) {
+        let card = req.get_card();
+        let (buyer_id, seller_id, buyer_px) = match req.get_side() {
+            Side::Buy => (req.get_trade_id(), *counterparty, *reserved_px),
+            // a resting buy order trades at its own price
+            Side::Sell => (*counterparty, req.get_trade_id(), *traded_px),
+        };
+        let amount = traded_px.times(qty);
+
+        let _ = self.update(&buyer_id, |buyer| {
+            buyer.release(Side::Buy, &card, &buyer_px, qty);
+            buyer.cash = Price::from_cents(buyer.cash.get_cents() - amount.get_cents());
+            buyer.cards.entry(card).or_default().qty += qty;
+            Ok(())
+        });
+        let _ = self.update(&seller_id, |seller| {
+            seller.release(Side::Sell, &card, traded_px, qty);
+            seller.cards.entry(card).or_default().qty -= qty;
+            seller.cash = Price::from_cents(seller.cash.get_cents() + amount.get_cents());
+            Ok(())
+        });
+    }
+
+    // writes storing the changed balances along with the rest of the order
+    pub fn get_writes(&self) -> Vec<Write> {
+        self.changed.values().flat_map(|account| account.get_writes()).collect()
+    }
+
+    // the cache takes the changes once their writes are stored
+    pub fn apply(mut self) {
+        if let Some(accounts) = self.accounts.as_mut() {
+            accounts.extend(self.changed.drain());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::{AccountChanges, Accounts, BookingError};
+    use crate::data_type::{CardTransfer, CashTransfer, Card, LedgerEntry, LedgerKind, Price, ProcessResult, RejectReason, RequestOrder, Side};
+    use crate::scheduler::Scheduler;
+    use crate::store::{self, Store};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    // stage changes of an order and store them as the scheduler does
+    fn settle<F>(accounts: &Accounts, db: &mut dyn Store, change: F) -> Result<(), RejectReason>
+    where
+        F: FnOnce(&mut AccountChanges) -> Result<(), RejectReason>,
+    {
+        let mut changes = accounts.begin();
+        change(&mut changes)?;
+        db.write(&changes.get_writes()).unwrap();
+        changes.apply();
+        Ok(())
+    }
+
+    #[test]
+    fn given_funded_accounts_when_orders_reserved_and_filled_then_balances_moved_and_stored() {
+        let mut db = store::open();
+        let accounts = Accounts::new(true);
+        accounts.book(&mut *db, &LedgerEntry::for_cash(101, 0, &CashTransfer::new(10.00, "seed"))).unwrap();
+        accounts.book(&mut *db, &LedgerEntry::for_cards(102, 0, &CardTransfer::new(Card::Pikachu, 3, "seed"))).unwrap();
+
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.00, 3, Card::Pikachu, 101);
+        let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 2.50, 2, Card::Pikachu, 102);
+        assert_eq!(Ok(()), settle(&accounts, &mut *db, |changes| changes.reserve(&buy, &Price::from(3.00), 3)));
+        assert_eq!(Err(RejectReason::InsufficientCash), accounts.check(&buy, &Price::from(3.00)));
+        assert_eq!(Ok(()), settle(&accounts, &mut *db, |changes| changes.reserve(&sell, &Price::from(2.50), 2)));
+        assert_eq!(Err(RejectReason::InsufficientCards), settle(&accounts, &mut *db, |changes| changes.reserve(&sell, &Price::from(2.50), 2)));
+
+        // the buy order rests at 3.00 and trades with the sell order at 3.00
+        settle(&accounts, &mut *db, |changes| {
+            changes.transfer(&sell, &101, &Price::from(2.50), &Price::from(3.00), 2);
+            Ok(())
+        }).unwrap();
+        let (buyer, seller) = (accounts.get_account(&101), accounts.get_account(&102));
+        assert_eq!((&Price::from(4.00), &Price::from(3.00)), (buyer.get_cash(), buyer.get_reserved_cash()));
+        assert_eq!((&2, &0), (buyer.get_holding(&Card::Pikachu).get_qty(), buyer.get_holding(&Card::Pikachu).get_reserved()));
+        assert_eq!((&Price::from(6.00), 1), (seller.get_cash(), seller.get_holding(&Card::Pikachu).get_available()));
+
+        // reserved cash could not be withdrawn until released
+        assert_eq!(Err(RejectReason::InsufficientCash), settle(&accounts, &mut *db, |changes| changes.credit_cash(&101, &Price::from(-2.00))));
+        settle(&accounts, &mut *db, |changes| {
+            changes.release(&buy, &Price::from(3.00), 1);
+            Ok(())
+        }).unwrap();
+        assert_eq!(Ok(()), settle(&accounts, &mut *db, |changes| changes.credit_cash(&101, &Price::from(-2.00))));
+
+        // changes dropped before their writes are stored never reach the cache
+        let before = accounts.get_account(&101);
+        let mut changes = accounts.begin();
+        assert_eq!(Ok(()), changes.reserve(&buy, &Price::from(1.00), 2));
+        drop(changes);
+        assert_eq!(before, accounts.get_account(&101));
+
+        let stored = Accounts::new(true);
+        stored.load(&mut *db);
+        assert_eq!(accounts.get_account(&101), stored.get_account(&101));
+        assert_eq!(accounts.get_account(&102).get_holding(&Card::Pikachu), stored.get_account(&102).get_holding(&Card::Pikachu));
+
+        let disabled = Accounts::new(false);
+        assert_eq!(Ok(()), settle(&disabled, &mut *db, |changes| changes.reserve(&buy, &Price::from(3.00), 1000)));
+    }
+
+    #[test]
+    fn given_balances_booked_by_operators_when_traded_then_ledger_listed_and_reconciled() {
+        let (buyer, seller) = (121, 122);
+        let mut scheduler = Scheduler::for_cards(&[Card::Squirtle]);
+        let accounts = Accounts::new(true);
+        scheduler.set_accounts(accounts.clone());
+
+        let deposit = LedgerEntry::for_cash(buyer, 1, &CashTransfer::new(20.00, "seed"));
+        let withdrawal = LedgerEntry::for_cash(buyer, 1, &CashTransfer::new(-30.00, "payout"));
+        let mint = LedgerEntry::for_cards(seller, 1, &CardTransfer::new(Card::Squirtle, 3, "airdrop"));
+        assert_eq!(Ok(Price::from(20.00)), accounts.book(&mut *scheduler.db, &deposit).map(|account| *account.get_cash()));
+        assert_eq!(Err(BookingError::Rejected(RejectReason::InsufficientCash)), accounts.book(&mut *scheduler.db, &withdrawal));
+        assert_eq!(Ok(3), accounts.book(&mut *scheduler.db, &mint).map(|account| *account.get_holding(&Card::Squirtle).get_qty()));
+
+        let ledger = accounts.get_ledger(&mut *scheduler.db, &buyer);
+        assert_eq!(vec![(LedgerKind::Deposit, Price::from(20.00), String::from("seed"))], ledger.iter().map(|entry| (*entry.get_kind(), *entry.get_amount(), entry.get_reason().to_owned())).collect::<Vec<_>>());
+        assert!(ledger[0].get_seq() > &0);
+
+        let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 4.00, 2, Card::Squirtle, seller);
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 4.00, 2, Card::Squirtle, buyer);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&sell));
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&buy));
+        assert!(accounts.reconcile(&mut *scheduler.db).is_empty());
+
+        // a balance changed outside the ledger is reported
+        settle(&accounts, &mut *scheduler.db, |changes| changes.credit_cash(&seller, &Price::from(1.00))).unwrap();
+        let differences: Vec<_> = accounts.reconcile(&mut *scheduler.db).into_iter().filter(|r| r.get_trader_id() == &seller).collect();
+        assert_eq!(1, differences.len());
+        assert_eq!((&None, &900, &800), (differences[0].get_card(), differences[0].get_booked(), differences[0].get_expected()));
+    }
+}
@@ -0,0 +1,383 @@
+use crate::data_type::{ProcessStatus, Rsp};
+use crate::store::Store;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tide::{Body, Middleware, Next, Request, Response, StatusCode};
+
+pub const API_KEY: &str = "X-Api-Key";
+pub const TIMESTAMP: &str = "X-Timestamp";
+pub const NONCE: &str = "X-Nonce";
+pub const SIGNATURE: &str = "X-Signature";
+pub const TOKEN: &str = "token";
+
+// the longest a stream token is valid for
+const MAX_TOKEN_MS: i64 = 3_600_000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AuthError {
+    MissingHeaders,
+    UnknownKey,
+    Expired,
+    Replayed,
+    BadSignature,
+}
+
+// the trader a request is signed by, kept in the extensions of the request
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Identity {
+    trader_id: i32,
+}
+
+impl Identity {
+    pub fn get_trader_id(&self) -> &i32 {
+        &self.trader_id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    trader_id: i32,
+    api_key: String,
+    secret: String,
+}
+
+impl Credentials {
+    pub fn get_trader_id(&self) -> &i32 {
+        &self.trader_id
+    }
+
+    pub fn get_api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    pub fn get_secret(&self) -> &str {
+        &self.secret
+    }
+}
+
+// headers of a signed request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    api_key: String,
+    timestamp: i64,
+    nonce: String,
+    signature: String,
+}
+
+impl Signature {
+    pub fn new(api_key: &str, timestamp: i64, nonce: &str, signature: &str) -> Self {
+        Self {
+            api_key: api_key.to_owned(),
+            timestamp,
+            nonce: nonce.to_owned(),
+            signature: signature.to_owned(),
+        }
+    }
+}
+
+/**
+ * A signature is the HMAC-SHA256 in lower-case hex of the payload
+ *   {timestamp}\n{nonce}\n{METHOD}\n{path[?query]}\n{body}
+ * where the timestamp is given in unix milliseconds
+ **/
+fn get_mac(secret: &str, timestamp: i64, nonce: &str, method: &str, path: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}\n{}\n{}\n", timestamp, nonce, method.to_uppercase(), path).as_bytes());
+    mac.update(body);
+    mac
+}
+
+/**
+ * A stream token {api_key}.{expires}.{signature} is sent as the token
+ * query by clients which cannot set headers, e.g. EventSource of the
+ * browsers. Its signature is the HMAC-SHA256 in lower-case hex of
+ *   {expires}\nGET\n{path}
+ * where expires is given in unix milliseconds, so the reconnections of
+ * a stream reuse the token until it expires
+ **/
+fn get_token_mac(secret: &str, expires: i64, path: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\nGET\n{}", expires, path).as_bytes());
+    mac
+}
+
+fn get_random_hex(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    hex::encode((0..len).map(|_| rng.gen::<u8>()).collect::<Vec<u8>>())
+}
+
+/**
+ * Authenticates traders by the API keys kept in trader_table. A request
+ * carries the key, a timestamp, a nonce and its signature in headers;
+ * it is accepted once signed by the secret of the key within max_skew_ms
+ * of the server clock, and with a nonce never seen in that window
+ **/
+#[derive(Clone)]
+pub struct Authenticator {
+    db: Arc<Mutex<Box<dyn Store>>>,
+    nonces: Arc<Mutex<HashMap<(i32, String), i64>>>,
+    max_skew_ms: i64,
+    optional: bool,
+    operators: Option<Arc<Vec<i32>>>,
+    tokens: bool,
+}
+
+impl Authenticator {
+    pub fn new(db: Box<dyn Store>, max_skew_ms: i64) -> Self {
+        Self {
+            db: Arc::new(Mutex::new(db)),
+            nonces: Arc::new(Mutex::new(HashMap::new())),
+            max_skew_ms,
+            optional: false,
+            operators: None,
+            tokens: false,
+        }
+    }
+
+    // the middleware letting unsigned requests through anonymously, signed ones are still verified
+    pub fn optional(&self) -> Self {
+        Self { optional: true, ..self.clone() }
+    }
+
+    // the middleware serving the given operators only, who may act on any trader
+    pub fn operators(&self, operators: &[i32]) -> Self {
+        Self { optional: false, operators: Some(Arc::new(operators.to_vec())), ..self.clone() }
+    }
+
+    // the middleware of the streams, taking a stream token in place of the signature headers
+    pub fn streams(&self) -> Self {
+        Self { tokens: true, ..self.clone() }
+    }
+
+    // issue a new key pair of a trader, the former one is revoked
+    pub fn register(&self, trader_id: i32) -> Credentials {
+        let credentials = Credentials {
+            trader_id,
+            api_key: get_random_hex(16),
+            secret: get_random_hex(32),
+        };
+        self.db.lock().unwrap().upsert_trader(&credentials.trader_id, &credentials.api_key, &credentials.secret);
+        credentials
+    }
+
+    pub fn verify(&self, signed: &Signature, method: &str, path: &str, body: &[u8]) -> Result<i32, AuthError> {
+        let now = Utc::now().timestamp_millis();
+        if (now - signed.timestamp).abs() > self.max_skew_ms {
+            return Err(AuthError::Expired);
+        }
+
+        let (trader_id, secret): (i32, String) = match self.db.lock().unwrap().get_trader(&signed.api_key) {
+            Some(trader) => trader,
+            None => return Err(AuthError::UnknownKey),
+        };
+        let mac = get_mac(&secret, signed.timestamp, &signed.nonce, method, path, body);
+        match hex::decode(&signed.signature) {
+            Ok(signature) if mac.verify_slice(&signature).is_ok() => {},
+            _ => return Err(AuthError::BadSignature),
+        }
+
+        // only a verified signature spends its nonce, nonces out of the window are forgotten
+        let mut nonces = self.nonces.lock().unwrap();
+        nonces.retain(|_, tm| now - *tm <= self.max_skew_ms);
+        if nonces.insert((trader_id, signed.nonce.clone()), signed.timestamp).is_some() {
+            return Err(AuthError::Replayed);
+        }
+        Ok(trader_id)
+    }
+
+    // a token has no nonce, it is valid up to its expiry which is at most MAX_TOKEN_MS away
+    pub fn verify_token(&self, token: &str, path: &str) -> Result<i32, AuthError> {
+        let (api_key, expires, signature) = match token.splitn(3, '.').collect::<Vec<&str>>()[..] {
+            [api_key, expires, signature] => match expires.parse::<i64>() {
+                Ok(expires) => (api_key, expires, signature),
+                Err(_) => return Err(AuthError::BadSignature),
+            },
+            _ => return Err(AuthError::BadSignature),
+        };
+        let now = Utc::now().timestamp_millis();
+        if expires <= now || expires - now > MAX_TOKEN_MS {
+            return Err(AuthError::Expired);
+        }
+
+        let (trader_id, secret): (i32, String) = match self.db.lock().unwrap().get_trader(api_key) {
+            Some(trader) => trader,
+            None => return Err(AuthError::UnknownKey),
+        };
+        match hex::decode(signature) {
+            Ok(signature) if get_token_mac(&secret, expires, path).verify_slice(&signature).is_ok() => Ok(trader_id),
+            _ => Err(AuthError::BadSignature),
+        }
+    }
+}
+
+// trader named by the :id parameter or the id query of a request, if any
+fn get_named_trader(req: &Request<()>) -> Option<i32> {
+    if let Ok(id) = req.param("id") {
+        return id.parse::<i32>().ok();
+    }
+    req.url().query_pairs().find(|(key, _)| key == "id").and_then(|(_, id)| id.parse::<i32>().ok())
+}
+
+fn reject(status: StatusCode, msg: String) -> tide::Result {
+    let mut res = Response::new(status);
+    let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}"));
+    res.set_body(Body::from_json(&rsp)?);
+    Ok(res)
+}
+
+/**
+ * Verify the signature, or the stream token of a stream, before the
+ * endpoint is reached. A request naming a trader by :id or ?id is only
+ * served to that very trader, unless the endpoint is reserved for operators
+ **/
+#[tide::utils::async_trait]
+impl Middleware<()> for Authenticator {
+    async fn handle(&self, mut req: Request<()>, next: Next<'_, ()>) -> tide::Result {
+        let header = |name: &str| req.header(name).map(|value| value.as_str().trim().to_owned());
+        let token = req.url().query_pairs().find(|(key, _)| key == TOKEN).map(|(_, token)| token.into_owned()).filter(|_| self.tokens);
+        let signed = match (header(API_KEY), header(TIMESTAMP).and_then(|tm| tm.parse::<i64>().ok()), header(NONCE), header(SIGNATURE)) {
+            (Some(api_key), Some(timestamp), Some(nonce), Some(signature)) => Some(Signature::new(&api_key, timestamp, &nonce, &signature)),
+            (None, None, None, None) if token.is_some() => None,
+            (None, None, None, None) if self.optional => return Ok(next.run(req).await),
+            _ => return reject(StatusCode::Unauthorized, format!("authentication failed: {:?}", AuthError::MissingHeaders)),
+        };
+
+        let body = req.body_bytes().await?;
+        let path = match req.url().query() {
+            Some(query) => format!("{}?{}", req.url().path(), query),
+            None => req.url().path().to_owned(),
+        };
+        let verified = match (&signed, &token) {
+            (Some(signed), _) => self.verify(signed, req.method().as_ref(), &path, &body),
+            (None, Some(token)) => self.verify_token(token, req.url().path()),
+            (None, None) => Err(AuthError::MissingHeaders),
+        };
+        let trader_id = match verified {
+            Ok(trader_id) => trader_id,
+            Err(e) => return reject(StatusCode::Unauthorized, format!("authentication failed: {:?}", e)),
+        };
+        match &self.operators {
+            Some(operators) if !operators.contains(&trader_id) => {
+                return reject(StatusCode::Forbidden, format!("trader {} is not an operator", trader_id));
+            }
+            Some(_) => {}
+            None => {
+                if let Some(named) = get_named_trader(&req).filter(|named| *named != trader_id) {
+                    return reject(StatusCode::Forbidden, format!("trader {} is not authorized to access trader {}", trader_id, named));
+                }
+            }
+        }
+
+        req.set_body(body);
+        req.set_ext(Identity { trader_id });
+        Ok(next.run(req).await)
+    }
+}
+
+// the authenticated trader, if the request is signed
+pub fn get_identity(req: &Request<()>) -> Option<i32> {
+    req.ext::<Identity>().map(|identity| *identity.get_trader_id())
+}
+
+// the authenticated trader of an endpoint behind the required middleware
+pub fn get_trader_id(req: &Request<()>) -> tide::Result<i32> {
+    get_identity(req).ok_or_else(|| tide::Error::from_str(StatusCode::Unauthorized, "the request is not signed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{get_identity, get_mac, get_token_mac, AuthError, Authenticator, Signature};
+    use crate::store;
+    use async_std::task;
+    use chrono::Utc;
+    use hmac::Mac;
+    use tide::http::{Method, Request, Url};
+    use tide::{Response, StatusCode};
+    use uuid::Uuid;
+
+    // signed as a client would do
+    fn sign(secret: &str, timestamp: i64, nonce: &str, method: &str, path: &str, body: &[u8]) -> String {
+        hex::encode(get_mac(secret, timestamp, nonce, method, path, body).finalize().into_bytes())
+    }
+
+    #[test]
+    fn given_a_registered_trader_when_requests_signed_then_verified_once_within_the_window() {
+        let auth = Authenticator::new(store::open(), 30000);
+        let credentials = auth.register(81);
+        let key = credentials.get_api_key();
+        let (method, path, body) = ("POST", "/api/pokemon/card", br#"{"vol": 1}"#);
+        let now = Utc::now().timestamp_millis();
+        let nonce = Uuid::new_v4().to_string();
+        let signed = Signature::new(key, now, &nonce, &sign(credentials.get_secret(), now, &nonce, method, path, body));
+
+        assert_eq!(Ok(81), auth.verify(&signed, method, path, body));
+        assert_eq!(Err(AuthError::Replayed), auth.verify(&signed, method, path, body));
+
+        let nonce = Uuid::new_v4().to_string();
+        let signed = Signature::new(key, now, &nonce, &sign(credentials.get_secret(), now, &nonce, method, path, body));
+        assert_eq!(Err(AuthError::BadSignature), auth.verify(&signed, method, path, br#"{"vol": 9}"#));
+        assert_eq!(Err(AuthError::BadSignature), auth.verify(&signed, "DELETE", path, body));
+        assert_eq!(Err(AuthError::UnknownKey), auth.verify(&Signature::new("unknown", now, &nonce, "00"), method, path, body));
+
+        let stale = now - 60000;
+        let signed = Signature::new(key, stale, &nonce, &sign(credentials.get_secret(), stale, &nonce, method, path, body));
+        assert_eq!(Err(AuthError::Expired), auth.verify(&signed, method, path, body));
+
+        // a new key pair revokes the former one
+        let renewed = auth.register(81);
+        let signed = Signature::new(key, now, &nonce, &sign(credentials.get_secret(), now, &nonce, method, path, body));
+        assert_eq!(Err(AuthError::UnknownKey), auth.verify(&signed, method, path, body));
+        let signed = Signature::new(renewed.get_api_key(), now, &nonce, &sign(renewed.get_secret(), now, &nonce, method, path, body));
+        assert_eq!(Ok(81), auth.verify(&signed, method, path, body));
+    }
+
+    // minted by a client holding the secret, e.g. before opening an EventSource
+    fn get_token(api_key: &str, secret: &str, expires: i64, path: &str) -> String {
+        format!("{}.{}.{}", api_key, expires, hex::encode(get_token_mac(secret, expires, path).finalize().into_bytes()))
+    }
+
+    #[test]
+    fn given_a_stream_token_when_verified_then_reused_until_it_expires() {
+        let auth = Authenticator::new(store::open(), 30000);
+        let credentials = auth.register(82);
+        let (key, secret, path) = (credentials.get_api_key(), credentials.get_secret(), "/api/pokemon/stream/orders/82");
+        let expires = Utc::now().timestamp_millis() + 60000;
+        let token = get_token(key, secret, expires, path);
+
+        // an EventSource reconnects with the very same url
+        assert_eq!(Ok(82), auth.verify_token(&token, path));
+        assert_eq!(Ok(82), auth.verify_token(&token, path));
+        assert_eq!(Err(AuthError::BadSignature), auth.verify_token(&token, "/api/pokemon/stream/orders/83"));
+        assert_eq!(Err(AuthError::BadSignature), auth.verify_token(key, path));
+        assert_eq!(Err(AuthError::UnknownKey), auth.verify_token(&get_token("unknown", secret, expires, path), path));
+
+        let (stale, lasting) = (Utc::now().timestamp_millis() - 1, Utc::now().timestamp_millis() + 7200000);
+        assert_eq!(Err(AuthError::Expired), auth.verify_token(&get_token(key, secret, stale, path), path));
+        assert_eq!(Err(AuthError::Expired), auth.verify_token(&get_token(key, secret, lasting, path), path));
+    }
+
+    #[test]
+    fn given_a_stream_behind_the_middleware_when_requested_with_a_token_then_served_to_its_trader_only() {
+        let auth = Authenticator::new(store::open(), 30000);
+        let credentials = auth.register(84);
+        let mut server = tide::new();
+        server.at("/api/pokemon/stream/orders/:id").with(auth.streams()).get(|req: tide::Request<()>| async move { Ok(format!("{:?}", get_identity(&req))) });
+        server.at("/api/pokemon/order/:id").with(auth.clone()).get(|req: tide::Request<()>| async move { Ok(format!("{:?}", get_identity(&req))) });
+
+        let expires = Utc::now().timestamp_millis() + 60000;
+        let get = |path: &str, token: &str| {
+            let url = Url::parse(&format!("http://localhost{}?token={}", path, get_token(credentials.get_api_key(), credentials.get_secret(), expires, token))).unwrap();
+            let res: Response = task::block_on(server.respond(Request::new(Method::Get, url))).unwrap();
+            res.status()
+        };
+        assert_eq!(StatusCode::Ok, get("/api/pokemon/stream/orders/84", "/api/pokemon/stream/orders/84"));
+        assert_eq!(StatusCode::Unauthorized, get("/api/pokemon/stream/orders/85", "/api/pokemon/stream/orders/84"));
+        assert_eq!(StatusCode::Forbidden, get("/api/pokemon/stream/orders/85", "/api/pokemon/stream/orders/85"));
+        // endpoints other than the streams keep requiring the signature headers
+        assert_eq!(StatusCode::Unauthorized, get("/api/pokemon/order/84", "/api/pokemon/order/84"));
+    }
+}
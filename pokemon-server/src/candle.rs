@@ -0,0 +1,148 @@
+use crate::data_type::{Card, Interval, Price};
+use crate::trade_board::Trade;
+use chrono::{DateTime, TimeZone, Utc};
+use schemars::JsonSchema;
+use std::collections::HashMap;
+use tide::prelude::Serialize;
+
+const INTERVALS: [Interval; 4] = [Interval::OneMinute, Interval::FiveMinutes, Interval::OneHour, Interval::OneDay];
+
+// open/high/low/close prices and traded volume of a card over an interval
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Candle {
+    start: DateTime<Utc>,
+    open: Price,
+    high: Price,
+    low: Price,
+    close: Price,
+    volume: i32,
+}
+
+impl Candle {
+    pub fn new(start: DateTime<Utc>, open: Price, high: Price, low: Price, close: Price, volume: i32) -> Self {
+        Self { start, open, high, low, close, volume }
+    }
+
+    pub fn get_start(&self) -> &DateTime<Utc> {
+        &self.start
+    }
+
+    pub fn get_open(&self) -> &Price {
+        &self.open
+    }
+
+    pub fn get_high(&self) -> &Price {
+        &self.high
+    }
+
+    pub fn get_low(&self) -> &Price {
+        &self.low
+    }
+
+    pub fn get_close(&self) -> &Price {
+        &self.close
+    }
+
+    pub fn get_volume(&self) -> &i32 {
+        &self.volume
+    }
+
+    fn add(&mut self, px: &Price, vol: i32) {
+        self.high = self.high.max(*px);
+        self.low = self.low.min(*px);
+        self.close = *px;
+        self.volume += vol;
+    }
+}
+
+/**
+ * Rolling aggregator of the latest candle of each card and interval.
+ * A trade falling after the latest candle starts a new one, so the
+ * current candle is always live while closed ones are read from
+ * trade_table
+ **/
+#[derive(Debug, Clone, Default)]
+pub struct CandleBoard {
+    latest: HashMap<(Card, Interval), Candle>,
+}
+
+impl CandleBoard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_trade(&mut self, card: &Card, trade: &Trade) {
+        for interval in INTERVALS.iter() {
+            let start = interval.get_start(trade.get_tx_time());
+            let (px, vol) = (trade.get_tx_price(), *trade.get_tx_vol());
+            match self.latest.get_mut(&(*card, *interval)) {
+                Some(candle) if candle.start == start => candle.add(px, vol),
+                Some(candle) if candle.start > start => {}
+                _ => {
+                    self.latest.insert((*card, *interval), Candle::new(start, *px, *px, *px, *px, vol));
+                }
+            }
+        }
+    }
+
+    // restore the latest candle, e.g. the current one kept in trade_table before a restart
+    pub fn set_latest(&mut self, card: &Card, interval: &Interval, candle: Candle) {
+        self.latest.insert((*card, *interval), candle);
+    }
+
+    pub fn get_latest(&self, card: &Card, interval: &Interval) -> Option<&Candle> {
+        self.latest.get(&(*card, *interval))
+    }
+
+    // the latest candle, if it is still the current one at the time
+    pub fn get_current(&self, card: &Card, interval: &Interval, now: &DateTime<Utc>) -> Option<&Candle> {
+        self.get_latest(card, interval).filter(|candle| candle.start == interval.get_start(now))
+    }
+}
+
+pub fn get_intervals() -> &'static [Interval] {
+    &INTERVALS
+}
+
+// candles of the trades given in time order, bucketed by secs since the epoch
+pub fn aggregate<'a>(trades: impl Iterator<Item = &'a Trade>, secs: i64) -> Vec<Candle> {
+    let mut candles: Vec<Candle> = Vec::new();
+    for trade in trades {
+        let tm = trade.get_tx_time().timestamp();
+        let start = Utc.timestamp(tm - tm.rem_euclid(secs), 0);
+        let (px, vol) = (trade.get_tx_price(), *trade.get_tx_vol());
+        match candles.last_mut() {
+            Some(candle) if candle.start == start => candle.add(px, vol),
+            _ => candles.push(Candle::new(start, *px, *px, *px, *px, vol)),
+        }
+    }
+    candles
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::candle::{Candle, CandleBoard};
+    use crate::data_type::{Card, Interval, Price};
+    use crate::trade_board::Trade;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn given_trades_when_aggregated_then_candles_rolled_per_interval() {
+        let mut board = CandleBoard::new();
+        let start = Utc.ymd(2024, 3, 1).and_hms(10, 0, 0);
+        for (secs, px, vol) in [(5, 3.00, 1), (20, 3.50, 2), (40, 2.75, 1), (59, 3.25, 3), (61, 3.30, 1)].iter() {
+            board.add_trade(&Card::Pikachu, &Trade::new(start + Duration::seconds(*secs), 1, 2, *px, *vol));
+        }
+
+        let minute = Candle::new(start + Duration::minutes(1), Price::from(3.30), Price::from(3.30), Price::from(3.30), Price::from(3.30), 1);
+        let hour = Candle::new(start, Price::from(3.00), Price::from(3.50), Price::from(2.75), Price::from(3.30), 8);
+        assert_eq!(Some(&minute), board.get_latest(&Card::Pikachu, &Interval::OneMinute));
+        assert_eq!(Some(&hour), board.get_latest(&Card::Pikachu, &Interval::OneHour));
+        assert_eq!(Some(&Utc.ymd(2024, 3, 1).and_hms(0, 0, 0)), board.get_latest(&Card::Pikachu, &Interval::OneDay).map(|candle| *candle.get_start()).as_ref());
+        assert_eq!(None, board.get_latest(&Card::Squirtle, &Interval::OneMinute));
+
+        // a candle is current only within its interval
+        assert!(board.get_current(&Card::Pikachu, &Interval::FiveMinutes, &(start + Duration::minutes(4))).is_some());
+        assert!(board.get_current(&Card::Pikachu, &Interval::FiveMinutes, &(start + Duration::minutes(5))).is_none());
+    }
+}
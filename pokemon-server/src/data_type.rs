@@ -1,25 +1,75 @@
-use chrono::{DateTime, Utc};
-use postgres_types::{FromSql, ToSql};
+use bytes::BytesMut;
+use chrono::{DateTime, TimeZone, Utc};
+use postgres_types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserializer, Serializer};
+use std::fmt;
 use tide::prelude::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub enum ProcessStatus {
     Success,
     Failed,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub enum ProcessResult {
     TxConfirmed,
     TxFilled,
+    TxPartiallyFilled,
     TxBoardUpdateFail,
     TxSelfTraded,
+    TxCancelled,
+    TxNotFound,
+    TxNotOwner,
+    TxNotCancellable,
+    TxAmended,
+    TxNotAmendable,
+    TxAmendRejected,
+    TxExpired,
+    TxKilled,
+    TxRejected(RejectReason),
+    // the writes of the order could not be stored, the boards are left as they were
+    TxStoreFailed,
     UnknownCard,
     // add other status here based on real conditions
 }
 
-#[derive(Debug, ToSql, FromSql, Clone, PartialEq, Deserialize, Serialize)]
+impl ProcessResult {
+    // status an incoming order ends up with, none if it never entered matching
+    pub fn to_order_status(&self) -> Option<OrderStatus> {
+        match self {
+            ProcessResult::TxConfirmed => Some(OrderStatus::Confirmed),
+            ProcessResult::TxFilled => Some(OrderStatus::Filled),
+            ProcessResult::TxPartiallyFilled => Some(OrderStatus::PartiallyFilled),
+            ProcessResult::TxSelfTraded => Some(OrderStatus::Dropped),
+            ProcessResult::TxExpired => Some(OrderStatus::Expired),
+            ProcessResult::TxKilled => Some(OrderStatus::Killed),
+            _ => None,
+        }
+    }
+}
+
+// machine-readable code of an order refused before matching
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RejectReason {
+    InvalidPrice,
+    PxOutOfRange,
+    OffTick,
+    InvalidVolume,
+    InvalidTraderId,
+    TimestampSkew,
+    DuplicateUuid,
+    TooManyOpenOrders,
+    InsufficientCash,
+    InsufficientCards,
+}
+
+#[derive(Debug, ToSql, FromSql, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[postgres(name = "orderstatus")]
 pub enum OrderStatus {
     #[postgres(name = "Confirmed")]
@@ -28,9 +78,17 @@ pub enum OrderStatus {
     Filled,
     #[postgres(name = "Dropped")]
     Dropped,
+    #[postgres(name = "Cancelled")]
+    Cancelled,
+    #[postgres(name = "PartiallyFilled")]
+    PartiallyFilled,
+    #[postgres(name = "Expired")]
+    Expired,
+    #[postgres(name = "Killed")]
+    Killed,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, ToSql, FromSql)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, ToSql, FromSql, JsonSchema)]
 #[postgres(name = "side")]
 pub enum Side {
     #[postgres(name = "Buy")]
@@ -39,7 +97,36 @@ pub enum Side {
     Sell,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, ToSql, FromSql)]
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, PartialEq, ToSql, FromSql, JsonSchema)]
+#[postgres(name = "timeinforce")]
+pub enum TimeInForce {
+    // rest on tx_board until filled or cancelled
+    #[default]
+    #[serde(rename = "GTC")]
+    #[postgres(name = "GTC")]
+    Gtc,
+    // fill as much as possible and drop the rest
+    #[serde(rename = "IOC")]
+    #[postgres(name = "IOC")]
+    Ioc,
+    // fill completely or drop as a whole
+    #[serde(rename = "FOK")]
+    #[postgres(name = "FOK")]
+    Fok,
+}
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, PartialEq, ToSql, FromSql, JsonSchema)]
+#[postgres(name = "ordertype")]
+pub enum OrderType {
+    #[default]
+    #[postgres(name = "Limit")]
+    Limit,
+    // trade at the best prices of the opposite side regardless of order_px
+    #[postgres(name = "Market")]
+    Market,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, ToSql, FromSql, JsonSchema)]
 #[postgres(name = "card")]
 pub enum Card {
     #[postgres(name = "Pikachu")]
@@ -52,15 +139,147 @@ pub enum Card {
     Squirtle,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+/**
+ * Fixed-point price counted in cents, so that price levels could be
+ * compared and hashed exactly. It goes over the wire as a decimal
+ * number (e.g. 3.75) and is stored as BIGINT in the database. It is kept
+ * in millionths of a dollar, so that a price sent finer than a cent
+ * reaches the validator as it is and is rejected there
+ **/
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Price(i64);
+
+// millionths of a dollar in a cent
+const UNITS_PER_CENT: i64 = 10_000;
+
+impl Price {
+    pub fn from_cents(cents: i64) -> Self {
+        Self(cents * UNITS_PER_CENT)
+    }
+
+    // truncated for a price finer than a cent, which the validator rejects beforehand
+    pub fn get_cents(&self) -> i64 {
+        self.0 / UNITS_PER_CENT
+    }
+
+    // None if the decimal carries digits finer than a cent
+    pub fn parse(px: f64) -> Option<Self> {
+        let cents = (px * 100.0).round();
+        if (px * 100.0 - cents).abs() > 1e-6 || !cents.is_finite() {
+            return None;
+        }
+        Some(Self::from_cents(cents as i64))
+    }
+
+    // a price finer than a cent stays off the cents even past the millionths it is kept in
+    fn parse_sub_cent(px: f64) -> Self {
+        let units = (px * 100.0 * UNITS_PER_CENT as f64).round() as i64;
+        match units % UNITS_PER_CENT {
+            0 if (units as f64) < px * 100.0 * UNITS_PER_CENT as f64 => Self(units + 1),
+            0 => Self(units - 1),
+            _ => Self(units),
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / (100 * UNITS_PER_CENT) as f64
+    }
+
+    pub fn is_whole_cents(&self) -> bool {
+        self.0 % UNITS_PER_CENT == 0
+    }
+
+    pub fn is_multiple_of(&self, tick: &Price) -> bool {
+        tick.0 > 0 && self.0 % tick.0 == 0
+    }
+
+    // amount of qty units at the price
+    pub fn times(&self, qty: i32) -> Price {
+        Self(self.0 * qty as i64)
+    }
+}
+
+// rounded to the nearest cent
+impl From<f64> for Price {
+    fn from(px: f64) -> Self {
+        Self::from_cents((px * 100.0).round() as i64)
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.abs() / UNITS_PER_CENT;
+        match self.0.abs() % UNITS_PER_CENT {
+            0 => write!(f, "{}{}.{:02}", sign, cents / 100, cents % 100),
+            units => {
+                let digits = format!("{:02}{:04}", cents % 100, units);
+                write!(f, "{}{}.{}", sign, cents / 100, digits.trim_end_matches('0'))
+            }
+        }
+    }
+}
+
+impl ToSql for Price {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        self.get_cents().to_sql(ty, out)
+    }
+
+    accepts!(INT8);
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for Price {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        i64::from_sql(ty, raw).map(Price::from_cents)
+    }
+
+    accepts!(INT8);
+}
+
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+// documented as a number of dollars in cents precision
+impl JsonSchema for Price {
+    fn schema_name() -> String {
+        String::from("Price")
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = f64::json_schema(gen).into_object();
+        schema.number().multiple_of = Some(0.01);
+        schema.into()
+    }
+}
+
+// a price finer than a cent is taken as well, and rejected by the validator with InvalidPrice
+impl<'de> Deserialize<'de> for Price {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let px = f64::deserialize(deserializer)?;
+        Ok(Price::parse(px).unwrap_or_else(|| Price::parse_sub_cent(px)))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RequestOrder {
     uuid: Uuid,
     tm: DateTime<Utc>,
     side: Side,
-    order_px: f64,
+    order_px: Price,
     vol: i32,
     card: Card,
+    // derived from the authenticated trader, whatever the client claims
+    #[serde(default)]
     trader_id: i32,
+    #[serde(default)]
+    time_in_force: TimeInForce,
+    #[serde(default)]
+    order_type: OrderType,
 }
 
 impl RequestOrder {
@@ -68,7 +287,7 @@ impl RequestOrder {
         uuid: Uuid,
         tm: DateTime<Utc>,
         side: Side,
-        order_px: f64,
+        order_px: impl Into<Price>,
         vol: i32,
         card: Card,
         trade_id: i32,
@@ -77,13 +296,30 @@ impl RequestOrder {
             uuid: uuid,
             tm: tm,
             side: side,
-            order_px: order_px,
+            order_px: order_px.into(),
             vol: vol,
             card: card,
             trader_id: trade_id,
+            time_in_force: TimeInForce::Gtc,
+            order_type: OrderType::Limit,
         }
     }
 
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    pub fn with_order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
+    pub fn with_trader_id(mut self, trader_id: i32) -> Self {
+        self.trader_id = trader_id;
+        self
+    }
+
     pub fn get_uuid(&self) -> Uuid {
         self.uuid
     }
@@ -96,7 +332,7 @@ impl RequestOrder {
         self.side
     }
 
-    pub fn get_order_px(&self) -> f64 {
+    pub fn get_order_px(&self) -> Price {
         self.order_px
     }
 
@@ -112,9 +348,22 @@ impl RequestOrder {
         self.trader_id
     }
 
+    pub fn get_time_in_force(&self) -> TimeInForce {
+        self.time_in_force
+    }
+
+    pub fn get_order_type(&self) -> OrderType {
+        self.order_type
+    }
+
+    // market orders never rest on tx_board since they carry no price limit
+    pub fn is_resting(&self) -> bool {
+        self.order_type == OrderType::Limit && self.time_in_force == TimeInForce::Gtc
+    }
+
     pub fn to_str(&self) -> String {
         let mut res = String::from("");
-        let fmt = format!(" uuid: {}, tm: {}, side: {:?}, order_px: {}, vol: {}, card: {:?}, trade_id: {} ", self.uuid, self.tm, self.side, self.order_px, self.vol, self.card, self.trader_id);
+        let fmt = format!(" uuid: {}, tm: {}, side: {:?}, order_px: {}, vol: {}, card: {:?}, trade_id: {}, time_in_force: {:?}, order_type: {:?} ", self.uuid, self.tm, self.side, self.order_px, self.vol, self.card, self.trader_id, self.time_in_force, self.order_type);
         res.push('{');
         res.push_str(&fmt);
         res.push('}');
@@ -122,9 +371,147 @@ impl RequestOrder {
     }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Fill {
+    px: Price,
+    vol: i32,
+}
+
+impl Fill {
+    pub fn new(px: Price, vol: i32) -> Self {
+        Self { px, vol }
+    }
+
+    pub fn get_px(&self) -> &Price {
+        &self.px
+    }
+
+    pub fn get_vol(&self) -> &i32 {
+        &self.vol
+    }
+}
+
+/**
+ * Outcome of matching an order, replied to the trader who placed it.
+ * Fills leave out the counterparties; avg_px is rounded to the cent
+ **/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct OrderAck {
+    uuid: Uuid,
+    result: ProcessResult,
+    status: Option<OrderStatus>,
+    filled_vol: i32,
+    resting_vol: i32,
+    avg_px: Option<Price>,
+    fills: Vec<Fill>,
+    // sequence number given by the matching engine of the card
+    seq: u64,
+}
+
+impl OrderAck {
+    pub fn new(req: &RequestOrder, result: ProcessResult, fills: Vec<Fill>) -> Self {
+        let filled_vol: i32 = fills.iter().map(|fill| fill.vol).sum();
+        let notional: i64 = fills.iter().map(|fill| fill.px.get_cents() * fill.vol as i64).sum();
+        let avg_px = if filled_vol > 0 {
+            Some(Price::from_cents((notional as f64 / filled_vol as f64).round() as i64))
+        } else {
+            None
+        };
+        let status = result.to_order_status();
+        let resting_vol = match status {
+            Some(OrderStatus::Confirmed) | Some(OrderStatus::PartiallyFilled) => req.get_vol() - filled_vol,
+            _ => 0,
+        };
+        Self {
+            uuid: req.get_uuid(),
+            result,
+            status,
+            filled_vol,
+            resting_vol,
+            avg_px,
+            fills,
+            seq: 0,
+        }
+    }
+
+    pub fn with_seq(mut self, seq: u64) -> Self {
+        self.seq = seq;
+        self
+    }
+
+    pub fn get_uuid(&self) -> &Uuid {
+        &self.uuid
+    }
+
+    pub fn get_result(&self) -> &ProcessResult {
+        &self.result
+    }
+
+    pub fn get_status(&self) -> &Option<OrderStatus> {
+        &self.status
+    }
+
+    pub fn get_filled_vol(&self) -> &i32 {
+        &self.filled_vol
+    }
+
+    pub fn get_resting_vol(&self) -> &i32 {
+        &self.resting_vol
+    }
+
+    pub fn get_avg_px(&self) -> &Option<Price> {
+        &self.avg_px
+    }
+
+    pub fn get_fills(&self) -> &Vec<Fill> {
+        &self.fills
+    }
+
+    pub fn get_seq(&self) -> &u64 {
+        &self.seq
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AmendOrder {
+    #[serde(default)]
+    trader_id: i32,
+    order_px: Price,
+    vol: i32,
+}
+
+impl AmendOrder {
+    pub fn new(trader_id: i32, order_px: impl Into<Price>, vol: i32) -> Self {
+        Self {
+            trader_id,
+            order_px: order_px.into(),
+            vol,
+        }
+    }
+
+    pub fn with_trader_id(mut self, trader_id: i32) -> Self {
+        self.trader_id = trader_id;
+        self
+    }
+
+    pub fn get_trade_id(&self) -> i32 {
+        self.trader_id
+    }
+
+    pub fn get_order_px(&self) -> Price {
+        self.order_px
+    }
+
+    pub fn get_vol(&self) -> i32 {
+        self.vol
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
 pub struct Rsp<T> {
     code: ProcessStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<RejectReason>,
     msg: String,
     data: T,
 }
@@ -133,13 +520,19 @@ impl<T> Rsp<T> {
     pub fn new(code: ProcessStatus, msg: String, data: T) -> Self {
         Self {
             code, 
+            reason: None,
             msg, 
             data,
         }
     }
+
+    pub fn with_reason(mut self, reason: RejectReason) -> Self {
+        self.reason = Some(reason);
+        self
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct HistoryParam {
     id: i32,
     date: String,
@@ -155,7 +548,232 @@ impl HistoryParam {
     }
 }
 
-#[derive(Serialize,Deserialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, ToSql, FromSql, JsonSchema)]
+#[postgres(name = "ledgerkind")]
+pub enum LedgerKind {
+    #[postgres(name = "Deposit")]
+    Deposit,
+    #[postgres(name = "Withdrawal")]
+    Withdrawal,
+    #[postgres(name = "Mint")]
+    Mint,
+    #[postgres(name = "Burn")]
+    Burn,
+}
+
+// cash credited to a trader by an operator, a negative amount withdraws it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CashTransfer {
+    amount: Price,
+    reason: String,
+}
+
+impl CashTransfer {
+    pub fn new(amount: impl Into<Price>, reason: &str) -> Self {
+        Self {
+            amount: amount.into(),
+            reason: reason.to_owned(),
+        }
+    }
+
+    pub fn get_amount(&self) -> &Price {
+        &self.amount
+    }
+
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+// cards minted for a trader by an operator, a negative qty burns them
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CardTransfer {
+    card: Card,
+    qty: i32,
+    reason: String,
+}
+
+impl CardTransfer {
+    pub fn new(card: Card, qty: i32, reason: &str) -> Self {
+        Self {
+            card,
+            qty,
+            reason: reason.to_owned(),
+        }
+    }
+
+    pub fn get_card(&self) -> &Card {
+        &self.card
+    }
+
+    pub fn get_qty(&self) -> &i32 {
+        &self.qty
+    }
+
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+/**
+ * An entry of the append-only ledger, i.e. a balance change booked by
+ * an operator. Cash entries carry no card, card entries no amount;
+ * trades are not booked here but taken from trade_table
+ **/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LedgerEntry {
+    seq: i64,
+    tm: DateTime<Utc>,
+    trader_id: i32,
+    operator_id: i32,
+    kind: LedgerKind,
+    card: Option<Card>,
+    amount: Price,
+    qty: i32,
+    reason: String,
+}
+
+impl LedgerEntry {
+    pub fn for_cash(trader_id: i32, operator_id: i32, transfer: &CashTransfer) -> Self {
+        let kind = if transfer.amount.get_cents() < 0 { LedgerKind::Withdrawal } else { LedgerKind::Deposit };
+        Self {
+            seq: 0,
+            tm: Utc::now(),
+            trader_id,
+            operator_id,
+            kind,
+            card: None,
+            amount: transfer.amount,
+            qty: 0,
+            reason: transfer.reason.clone(),
+        }
+    }
+
+    pub fn for_cards(trader_id: i32, operator_id: i32, transfer: &CardTransfer) -> Self {
+        let kind = if transfer.qty < 0 { LedgerKind::Burn } else { LedgerKind::Mint };
+        Self {
+            seq: 0,
+            tm: Utc::now(),
+            trader_id,
+            operator_id,
+            kind,
+            card: Some(transfer.card),
+            amount: Price::default(),
+            qty: transfer.qty,
+            reason: transfer.reason.clone(),
+        }
+    }
+
+    // the sequence number and the time the entry is stored with
+    pub fn with_seq(mut self, seq: i64, tm: DateTime<Utc>) -> Self {
+        self.seq = seq;
+        self.tm = tm;
+        self
+    }
+
+    pub fn get_seq(&self) -> &i64 {
+        &self.seq
+    }
+
+    pub fn get_tm(&self) -> &DateTime<Utc> {
+        &self.tm
+    }
+
+    pub fn get_trader_id(&self) -> &i32 {
+        &self.trader_id
+    }
+
+    pub fn get_operator_id(&self) -> &i32 {
+        &self.operator_id
+    }
+
+    pub fn get_kind(&self) -> &LedgerKind {
+        &self.kind
+    }
+
+    pub fn get_card(&self) -> &Option<Card> {
+        &self.card
+    }
+
+    pub fn get_amount(&self) -> &Price {
+        &self.amount
+    }
+
+    pub fn get_qty(&self) -> &i32 {
+        &self.qty
+    }
+
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+// length of a candle
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Interval {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl Interval {
+    pub fn get_secs(&self) -> i64 {
+        match self {
+            Interval::OneMinute => 60,
+            Interval::FiveMinutes => 300,
+            Interval::OneHour => 3600,
+            Interval::OneDay => 86400,
+        }
+    }
+
+    // start of the candle the time falls in, days start at midnight UTC
+    pub fn get_start(&self, tm: &DateTime<Utc>) -> DateTime<Utc> {
+        let secs = tm.timestamp();
+        Utc.timestamp(secs - secs.rem_euclid(self.get_secs()), 0)
+    }
+}
+
+// candles starting from from (inclusive) to to (exclusive), the latest 100 ones by default
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CandleParam {
+    interval: Interval,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+impl CandleParam {
+    pub fn get_interval(&self) -> &Interval {
+        &self.interval
+    }
+
+    pub fn get_to(&self) -> DateTime<Utc> {
+        self.to.unwrap_or_else(Utc::now)
+    }
+
+    pub fn get_from(&self) -> DateTime<Utc> {
+        let from = self.from.unwrap_or_else(|| self.get_to() - chrono::Duration::seconds(self.interval.get_secs() * 100));
+        self.interval.get_start(&from)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BookParam {
+    depth: Option<usize>,
+}
+
+impl BookParam {
+    // 10 levels of each side unless specified
+    pub fn get_depth(&self) -> usize {
+        self.depth.unwrap_or(10)
+    }
+}
+
+#[derive(Serialize,Deserialize, JsonSchema)]
 pub struct StatusParam {
     uuid: Uuid,
 }
@@ -164,4 +782,5 @@ impl StatusParam {
     pub fn get_uuid(&self) -> &Uuid {
         &self.uuid
     }
-}
\ No newline at end of file
+}
+
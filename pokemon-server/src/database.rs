@@ -1,7 +1,13 @@
-use crate::data_type::{Card, OrderStatus, RequestOrder, Side};
+use crate::candle::Candle;
+use crate::data_type::{CardTransfer, CashTransfer, Card, LedgerEntry, OrderStatus, Price, RequestOrder, Side};
+use crate::migration::{self, Migration, Migrator, POSTGRES};
 use crate::settings::Settings;
+use crate::status_board::Status;
+use crate::store::{Store, Write};
+use crate::trade_board::{Trade, TradeHistory};
 use contracts::*;
-use postgres::{Client, NoTls, Row};
+use chrono::{DateTime, Utc};
+use postgres::{Client, GenericClient, NoTls, Row};
 use uuid::Uuid;
 use std::env;
 
@@ -12,6 +18,14 @@ pub struct Database {
 impl Database {
     #[ensures(ret.is_connected() == true, "database is connected")]
     pub fn new() -> Self {
+        let mut db = Database::connect();
+        db.init_tables();
+        db
+    }
+
+    // connected without migrating the schema, as the migrate command does
+    #[ensures(ret.is_connected(), "database is connected")]
+    pub fn connect() -> Self {
         // Obtain config file path
         let mut args = env::args();
         let database = match args.nth(1) {
@@ -24,16 +38,23 @@ impl Database {
             }
         };
 
-        let mut db = Database {
-            client: Client::connect(
-                database.as_str(),
-                NoTls,
-            )
-            .unwrap(),
-        };
+        Database::open(&database)
+    }
 
-        db.init_tables();
-        db
+    // connected to the given url without migrating the schema, e.g. to a database of a test
+    #[ensures(ret.is_connected(), "database is connected")]
+    pub fn open(url: &str) -> Self {
+        let mut client = Client::connect(url, NoTls).unwrap();
+        client
+            .batch_execute(
+                "create table if not exists schema_version(
+                    version INTEGER primary key,
+                    name TEXT not null,
+                    applied_at timestamptz not null default now()
+                );",
+            )
+            .unwrap();
+        Database { client }
     }
 }
 
@@ -42,72 +63,21 @@ impl Database {
     #[ensures(self.enum_type_exist("side"), "enum Side should be created after the database initialization")]
     #[ensures(self.enum_type_exist("card"), "enum Card should be created after the database initialization")]
     #[ensures(self.enum_type_exist("orderstatus"), "enum OrderStatus should be created after the database initialization")]
+    #[ensures(self.enum_type_exist("timeinforce"), "enum TimeInForce should be created after the database initialization")]
+    #[ensures(self.enum_type_exist("ordertype"), "enum OrderType should be created after the database initialization")]
+    #[ensures(self.enum_type_exist("ledgerkind"), "enum LedgerKind should be created after the database initialization")]
     #[ensures(self.table_exist("public", "request_table"), "request_table should be created after the database initialization")]
     #[ensures(self.table_exist("public", "status_table"), "status_table should be created after the database initialization")]
     #[ensures(self.table_exist("public", "trade_table"), "trade_table should be created after the database initialization")]
+    #[ensures(self.table_exist("public", "amend_table"), "amend_table should be created after the database initialization")]
+    #[ensures(self.table_exist("public", "trader_table"), "trader_table should be created after the database initialization")]
+    #[ensures(self.table_exist("public", "account_table"), "account_table should be created after the database initialization")]
+    #[ensures(self.table_exist("public", "inventory_table"), "inventory_table should be created after the database initialization")]
+    #[ensures(self.table_exist("public", "ledger_table"), "ledger_table should be created after the database initialization")]
+    #[ensures(self.get_schema_version().len() == POSTGRES.len(), "every migration should be applied after the database initialization")]
     #[invariant(true)]
     pub fn init_tables(&mut self) {
-        // create enum 'Side'
-        if !self.enum_type_exist("side") {
-            self.client
-                .batch_execute("CREATE TYPE side AS ENUM('Buy', 'Sell');")
-                .unwrap();
-        }
-
-        // create enum 'Card'
-        if !self.enum_type_exist("card") {
-            self.client
-                .batch_execute(
-                    "CREATE TYPE card AS ENUM('Pikachu', 'Bulbasaur', 'Charmander', 'Squirtle');",
-                )
-                .unwrap();
-        }
-
-        // create enum 'OrderStatus'
-        if !self.enum_type_exist("orderstatus") {
-            self.client
-                .batch_execute("CREATE TYPE orderstatus AS ENUM('Confirmed', 'Filled', 'Dropped');")
-                .unwrap();
-        }
-
-        // create table 'request_table'
-        self.client
-            .batch_execute(
-                "create table if not exists request_table(
-                    uuid UUID,
-                    tm timestamptz,
-                    side Side,
-                    order_px FLOAT8,
-                    vol INTEGER,
-                    card Card,
-                    trader_id INTEGER);",
-            )
-            .unwrap();
-
-        // create table 'status_table'
-        self.client
-            .batch_execute(
-                "create table if not exists status_table(
-                    uuid UUID,
-                    status OrderStatus
-                );",
-            )
-            .unwrap();
-
-        // create table 'trade_table'
-        self.client
-            .batch_execute(
-                "create table if not exists trade_table(
-                    buy_uuid UUID,
-                    sell_uuid UUID,
-                    buy_side_id INTEGER,
-                    sell_side_id INTEGER,
-                    tx_price FLOAT8,
-                    tx_vol INTEGER,
-                    card Card
-                );",
-            )
-            .unwrap();
+        migration::up(self);
     }
 
     pub fn is_connected(&self) -> bool {
@@ -137,27 +107,121 @@ impl Database {
         res.get("exists")
     }
 
-    #[requires(self.is_connected(), "database should be connected before checking whether a request exists")]
-    #[requires(self.table_exist("public", "request_table"), "request_table should be created in the database")]
-    #[ensures(self.request_exist(&req.get_uuid()), "the request should be inserted into request_table")]
+    #[requires(self.is_connected(), "database should be connected before registering a trader")]
+    #[requires(self.table_exist("public", "trader_table"), "trader_table should be created in the database")]
+    #[ensures(self.get_trader(api_key).is_some(), "the key pair should be stored in trader_table")]
     #[invariant(true)]
-    pub fn insert_request_table(&mut self, req: &RequestOrder) {
+    pub fn upsert_trader(&mut self, trader_id: &i32, api_key: &str, secret: &str) {
         match self.client.execute(
-            "INSERT INTO request_table(uuid, tm, side, order_px, vol, card, trader_id) VALUES ($1, $2, $3, $4, $5, $6, $7)",
-            &[
-                &req.get_uuid(),
-                &req.get_tm(),
-                &req.get_side(),
-                &req.get_order_px(),
-                &req.get_vol(),
-                &req.get_card(),
-                &req.get_trade_id()
-            ],
+            "insert into trader_table(trader_id, api_key, secret) values($1, $2, $3)
+             on conflict (trader_id) do update set api_key = excluded.api_key, secret = excluded.secret, created_at = now()",
+            &[&trader_id, &api_key, &secret],
         ) {
             Ok(_) => {}
             Err(e) => {
-                panic!("[Database][insert_request_table] Error: {}", e);
+                panic!("[Database][upsert_trader] {}", e);
             }
+        };
+    }
+
+    #[requires(self.is_connected(), "database should be connected before getting a trader")]
+    #[requires(self.table_exist("public", "trader_table"), "trader_table should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_trader(&mut self, api_key: &str) -> Option<Row> {
+        self.client.query_opt("select trader_id, api_key, secret from trader_table where api_key = $1", &[&api_key]).unwrap()
+    }
+
+    #[requires(self.is_connected(), "database should be connected before updating an account")]
+    #[requires(self.table_exist("public", "account_table"), "account_table should be created in the database")]
+    #[requires(cash >= reserved_cash, "reserved cash should be covered by the cash")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn upsert_account(&mut self, trader_id: &i32, cash: &Price, reserved_cash: &Price) {
+        if let Err(e) = execute_write(&mut self.client, &Write::Account(*trader_id, *cash, *reserved_cash)) {
+            panic!("[Database][upsert_account] {}", e);
+        }
+    }
+
+    #[requires(self.is_connected(), "database should be connected before updating an inventory")]
+    #[requires(self.table_exist("public", "inventory_table"), "inventory_table should be created in the database")]
+    #[requires(qty >= reserved_qty, "reserved cards should be covered by the holding")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn upsert_inventory(&mut self, trader_id: &i32, card: &Card, qty: &i32, reserved_qty: &i32) {
+        if let Err(e) = execute_write(&mut self.client, &Write::Inventory(*trader_id, *card, *qty, *reserved_qty)) {
+            panic!("[Database][upsert_inventory] {}", e);
+        }
+    }
+
+    #[requires(self.is_connected(), "database should be connected before getting accounts")]
+    #[requires(self.table_exist("public", "account_table"), "account_table should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_accounts(&mut self) -> Vec<Row> {
+        self.client.query("select trader_id, cash, reserved_cash from account_table", &[]).unwrap()
+    }
+
+    #[requires(self.is_connected(), "database should be connected before getting inventories")]
+    #[requires(self.table_exist("public", "inventory_table"), "inventory_table should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_inventories(&mut self) -> Vec<Row> {
+        self.client.query("select trader_id, card, qty, reserved_qty from inventory_table", &[]).unwrap()
+    }
+
+    #[requires(self.is_connected(), "database should be connected before getting the ledger")]
+    #[requires(self.table_exist("public", "ledger_table"), "ledger_table should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_ledger(&mut self, trader_id: &i32) -> Vec<Row> {
+        self.client
+            .query("select seq, tm, trader_id, operator_id, kind, card, amount, qty, reason from ledger_table where trader_id = $1 order by seq", &[&trader_id])
+            .unwrap()
+    }
+
+    /**
+     * Cash of each trader implied by the ledger and the trades, i.e. the
+     * booked amounts plus the proceeds of sales minus the cost of purchases
+     **/
+    #[requires(self.is_connected(), "database should be connected")]
+    #[requires(self.table_exist("public", "ledger_table"), "ledger_table should be created in the database")]
+    #[requires(self.table_exist("public", "trade_table"), "trade_table should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_expected_cash(&mut self) -> Vec<Row> {
+        self.client.query("select trader_id, sum(amount)::BIGINT as cash
+                           from ( select trader_id, amount from ledger_table where card is null
+                                  union all
+                                  select sell_side_id, tx_price * tx_vol from trade_table
+                                  union all
+                                  select buy_side_id, -tx_price * tx_vol from trade_table ) t
+                           group by trader_id", &[]).unwrap()
+    }
+
+    // cards of each trader implied by the ledger and the trades
+    #[requires(self.is_connected(), "database should be connected")]
+    #[requires(self.table_exist("public", "ledger_table"), "ledger_table should be created in the database")]
+    #[requires(self.table_exist("public", "trade_table"), "trade_table should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_expected_cards(&mut self) -> Vec<Row> {
+        self.client.query("select trader_id, card, sum(qty)::INTEGER as qty
+                           from ( select trader_id, card, qty from ledger_table where card is not null
+                                  union all
+                                  select buy_side_id, card, tx_vol from trade_table
+                                  union all
+                                  select sell_side_id, card, -tx_vol from trade_table ) t
+                           group by trader_id, card", &[]).unwrap()
+    }
+
+    #[requires(self.is_connected(), "database should be connected before checking whether a request exists")]
+    #[requires(self.table_exist("public", "request_table"), "request_table should be created in the database")]
+    #[ensures(self.request_exist(&req.get_uuid()), "the request should be inserted into request_table")]
+    #[invariant(true)]
+    pub fn insert_request_table(&mut self, req: &RequestOrder) {
+        if let Err(e) = execute_write(&mut self.client, &Write::Request(*req)) {
+            panic!("[Database][insert_request_table] {}", e);
         }
     }
 
@@ -173,20 +237,39 @@ impl Database {
         res
     }
 
+    #[requires(self.is_connected(), "database should be connected before getting a request")]
+    #[requires(self.table_exist("public", "request_table"), "request_table should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_request(&mut self, uuid: &Uuid) -> Option<Row> {
+        // price and volume reflect the latest amendment of the order
+        self.client.query_opt("select rt.uuid, rt.tm, rt.side, coalesce(am.order_px, rt.order_px) as order_px, coalesce(am.vol, rt.vol) as vol, rt.card, rt.trader_id
+                               from request_table rt left join lateral ( select at.order_px, at.vol
+                                                                         from amend_table at
+                                                                         where at.uuid = rt.uuid
+                                                                         order by at.tm desc limit 1 ) am on true
+                               where rt.uuid = $1", &[&uuid]).unwrap()
+    }
+
+    #[requires(self.is_connected(), "database should be connected before amending a request")]
+    #[requires(self.table_exist("public", "amend_table"), "amend_table should be created in the database")]
+    #[requires(self.request_exist(uuid), "the amended request should have been existing in the request_table")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn insert_amend_table(&mut self, uuid: &Uuid, tm: &DateTime<Utc>, order_px: &Price, vol: &i32, keep_priority: &bool) {
+        if let Err(e) = execute_write(&mut self.client, &Write::Amend(*uuid, *tm, *order_px, *vol, *keep_priority)) {
+            panic!("[Database][insert_amend_table] {}", e);
+        }
+    }
+
     #[requires(self.is_connected(), "database should be connected before inserting status of an order")]
     #[requires(self.table_exist("public", "status_table"), "status_table should be created in the database")]
     #[ensures(self.order_status_exist(uuid), "the status of the order has been inserted")]
     #[invariant(true)]
     pub fn insert_order_status(&mut self, uuid: &Uuid, status: &OrderStatus) {
-        match self.client.execute(
-            "insert into status_table(uuid, status) values($1, $2)",
-            &[&uuid, &status],
-        ) {
-            Ok(n) => {}
-            Err(e) => {
-                panic!("[Database][insert_order_status] {}", e);
-            }
-        };
+        if let Err(e) = execute_write(&mut self.client, &Write::InsertStatus(*uuid, status.clone())) {
+            panic!("[Database][insert_order_status] {}", e);
+        }
     }
 
     #[requires(self.is_connected(), "database should be connected before updating status of an order exists")]
@@ -195,15 +278,9 @@ impl Database {
     #[ensures(*status == self.get_order_status(uuid), "status should be the same after updated")]
     #[invariant(true)]
     pub fn update_order_status(&mut self, uuid: &Uuid, status: &OrderStatus) {
-        match self.client.execute(
-            "update status_table set status = $1 where uuid = $2",
-            &[&status, &uuid],
-        ) {
-            Ok(_) => {}
-            Err(e) => {
-                panic!("[Database][update_order_status] {}", e);
-            }
-        };
+        if let Err(e) = execute_write(&mut self.client, &Write::UpdateStatus(*uuid, status.clone())) {
+            panic!("[Database][update_order_status] {}", e);
+        }
     }
 
     #[requires(self.is_connected(), "database should be connected before checking whether status of an order exists")]
@@ -234,24 +311,61 @@ impl Database {
         res.get("status")
     }
 
+    // a lost connection fails the query, so it is answered as an error instead of being required
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn find_order(&mut self, uuid: &Uuid) -> Result<Option<Row>, postgres::Error> {
+        self.client.query_opt("select rt.uuid, rt.tm, rt.side, coalesce(am.order_px, rt.order_px) as order_px, coalesce(am.vol, rt.vol) as vol, rt.card, rt.trader_id, st.status
+                               from request_table rt left join lateral ( select at.order_px, at.vol
+                                                                         from amend_table at
+                                                                         where at.uuid = rt.uuid
+                                                                         order by at.tm desc limit 1 ) am on true
+                                                     left join status_table st on st.uuid = rt.uuid
+                               where rt.uuid = $1", &[&uuid])
+    }
+
     #[requires(self.is_connected(), "database should be connected before checking whether a request exists")]
     #[requires(self.table_exist("public", "trade_table"), "request_table should be created in the database")]
     #[ensures(self.trade_exist(&buy_side_uuid, &sell_side_uuid), "the trade should be inserted into trade_table")]
     #[invariant(true)]
-    pub fn insert_trade_table(
-        &mut self,
-        buy_side_uuid: &Uuid,
-        sell_side_uuid: &Uuid,
-        buy_side_id: &i32,
-        sell_side_id: &i32,
-        tx_price: &f64,
-        tx_vol: &i32,
-        card: &Card,
-    ) {
-        match self.client.execute("insert into trade_table(buy_uuid, sell_uuid, buy_side_id, sell_side_id, tx_price, tx_vol, card) values($1, $2, $3, $4, $5, $6, $7)", &[&buy_side_uuid, &sell_side_uuid, &buy_side_id, &sell_side_id, &tx_price, &tx_vol, &card]){
-            Ok(_) => {},
-            Err(e) => { panic!("[Dtabase][insert_table_table] {}", e); }
-        };
+    pub fn insert_trade_table(&mut self, buy_side_uuid: &Uuid, sell_side_uuid: &Uuid, card: &Card, trade: &Trade) {
+        if let Err(e) = execute_write(&mut self.client, &Write::Trade(*buy_side_uuid, *sell_side_uuid, *card, trade.clone())) {
+            panic!("[Database][insert_trade_table] {}", e);
+        }
+    }
+
+    #[requires(self.is_connected(), "database should be connected before getting trades")]
+    #[requires(self.table_exist("public", "trade_table"), "trade_table should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_trades(&mut self, trader_id: &i32) -> Vec<Row> {
+        // every trade of the trader in time order
+        self.client
+            .query("select buy_side_id, sell_side_id, tx_price, tx_vol, card, tm from trade_table where buy_side_id = $1 or sell_side_id = $1 order by tm", &[&trader_id])
+            .unwrap()
+    }
+
+    #[requires(self.is_connected(), "database should be connected before getting candles")]
+    #[requires(self.table_exist("public", "trade_table"), "trade_table should be created in the database")]
+    #[requires(*secs > 0, "interval of candles should be positive")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_candles(&mut self, card: &Card, secs: &i64, from: &DateTime<Utc>, to: &DateTime<Utc>) -> Vec<Row> {
+        // trades of the card in [from, to) bucketed by the interval since the epoch
+        match self.client.query(
+            "select to_timestamp((floor(extract(epoch from tm) / $2::bigint) * $2::bigint)::float8) as start,
+                    (array_agg(tx_price order by tm))[1] as open,
+                    max(tx_price) as high,
+                    min(tx_price) as low,
+                    (array_agg(tx_price order by tm desc))[1] as close,
+                    sum(tx_vol)::integer as volume
+             from trade_table where card = $1 and tm >= $3 and tm < $4
+             group by start order by start",
+            &[&card, &secs, &from, &to],
+        ) {
+            Ok(rows) => rows,
+            Err(e) => { panic!("[Database][get_candles] {}", e); }
+        }
     }
 
     #[requires(self.is_connected(), "database should be connected before checking whether a trade exists")]
@@ -259,11 +373,12 @@ impl Database {
     #[ensures(true)]
     #[invariant(true)]
     pub fn trade_exist(&mut self, buy_side_uuid: &Uuid, sell_side_uuid: &Uuid) -> bool {
-        let res = match self.client.query_one(
+        // the same orders could fill each other more than once, each fill alike kept as a trade of its own
+        let res = match self.client.query(
             "select * FROM trade_table where buy_uuid = $1 and sell_uuid = $2",
             &[&buy_side_uuid, &sell_side_uuid],
         ) {
-            Ok(_) => true,
+            Ok(rows) => !rows.is_empty(),
             Err(_) => false,
         };
         res
@@ -275,14 +390,24 @@ impl Database {
     #[ensures(true)]
     #[invariant(true)]    
     pub fn get_realtime_tx_info(&mut self, side: &Side, card: &Card) -> Vec<Row> {
-        let res: Vec<Row> = self.client.query(" select rt.uuid, rt.tm, rt.side, rt.order_px, rt.vol, rt.card, rt.trader_id 
+        // amended orders are restored with their latest terms; re-priced or enlarged ones queue from the amendment time
+        let res: Vec<Row> = self.client.query(" select rt.uuid, rt.tm, rt.side, coalesce(am.order_px, rt.order_px) as order_px, rt.card, rt.trader_id,
+                                                       (coalesce(am.vol, rt.vol) - coalesce((select sum(tt.tx_vol)
+                                                                                             from trade_table tt
+                                                                                             where tt.buy_uuid = rt.uuid or tt.sell_uuid = rt.uuid), 0))::INTEGER as vol
                                                 from request_table rt inner join status_table st
-                                                on (st.status = 'Confirmed' and 
+                                                on (st.status in ('Confirmed', 'PartiallyFilled') and 
                                                     rt.uuid = st.uuid and 
                                                     date(rt.tm) = current_date and 
                                                     rt.side = $1 and 
                                                     rt.card = $2)
-                                                order by rt.tm;", &[&side, &card]).unwrap();
+                                                left join lateral ( select at.order_px, at.vol
+                                                                    from amend_table at
+                                                                    where at.uuid = rt.uuid
+                                                                    order by at.tm desc limit 1 ) am on true
+                                                order by coalesce((select max(at.tm)
+                                                                   from amend_table at
+                                                                   where at.uuid = rt.uuid and not at.keep_priority), rt.tm);", &[&side, &card]).unwrap();
         res
     }
 
@@ -293,15 +418,13 @@ impl Database {
     #[ensures(true)]
     #[invariant(true)] 
     pub fn get_trade_history(&mut self, id: &i32, date: &str) -> Vec<Row> {
+        // a single select keeps fills alike apart and a trade of a trader with itself once
         let res = self.client.query("select tt.buy_side_id, tt.sell_side_id, tt.tx_price, tt.tx_vol, tt.card
                                      from trade_table tt
                                      where tt.buy_uuid in ( select rt.uuid 
                                                             from request_table rt
                                                             where rt.trader_id = $1 and to_char(rt.tm, 'YYYY-MM-DD') like $2 )
-                                     union
-                                     select tt.buy_side_id, tt.sell_side_id, tt.tx_price, tt.tx_vol, tt.card
-                                     from trade_table tt
-                                     where tt.sell_uuid in ( select rt.uuid 
+                                        or tt.sell_uuid in ( select rt.uuid 
                                                              from request_table rt
                                                              where rt.trader_id = $1 and to_char(rt.tm, 'YYYY-MM-DD') like $2 )", &[&id, &date]).unwrap();
         res
@@ -313,9 +436,15 @@ impl Database {
     #[ensures(true)]
     #[invariant(true)] 
     pub fn get_request_history(&mut self, id: &i32, date: &str) -> Vec<Row> {
-        let res = self.client.query("select * 
+        // amendments share the uuid of the original request so the chain could be followed in time order
+        let res = self.client.query("select rt.uuid, rt.tm, rt.side, rt.order_px, rt.vol, rt.card, rt.trader_id, rt.time_in_force, rt.order_type
                                      from request_table rt
-                                     where rt.trader_id = $1 and to_char(rt.tm, 'YYYY-MM-DD') like $2", &[&id, &date]).unwrap();
+                                     where rt.trader_id = $1 and to_char(rt.tm, 'YYYY-MM-DD') like $2
+                                     union all
+                                     select at.uuid, at.tm, rt.side, at.order_px, at.vol, rt.card, rt.trader_id, rt.time_in_force, rt.order_type
+                                     from amend_table at inner join request_table rt on at.uuid = rt.uuid
+                                     where rt.trader_id = $1 and to_char(rt.tm, 'YYYY-MM-DD') like $2
+                                     order by tm", &[&id, &date]).unwrap();
         res
     }
 
@@ -331,4 +460,274 @@ impl Database {
         res
     }
 
+
+    #[requires(self.is_connected(), "database should be connected before numbering a run")]
+    #[requires(self.table_exist("public", "run_table"), "run_table should be created in the database")]
+    #[ensures(ret > 0, "the run should be given a number")]
+    #[invariant(true)]
+    pub fn insert_run_table(&mut self) -> i64 {
+        match self.client.query_one("insert into run_table default values returning run", &[]) {
+            Ok(row) => row.get("run"),
+            Err(e) => {
+                panic!("[Database][insert_run_table] {}", e);
+            }
+        }
+    }
+
+    // a lost connection fails the transaction, so it is answered as an error instead of being required
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn write(&mut self, writes: &[Write]) -> Result<(), postgres::Error> {
+        // dropping the transaction without committing rolls it back
+        let mut tx = self.client.transaction()?;
+        for write in writes.iter() {
+            execute_write(&mut tx, write)?;
+        }
+        tx.commit()
+    }
+
+    #[requires(self.is_connected(), "database should be connected before getting the schema version")]
+    #[requires(self.table_exist("public", "schema_version"), "schema_version should be created in the database")]
+    #[ensures(true)]
+    #[invariant(true)]
+    pub fn get_schema_version(&mut self) -> Vec<Row> {
+        self.client.query("select version, name, applied_at from schema_version order by version", &[]).unwrap()
+    }
+
+    #[requires(self.is_connected(), "database should be connected before applying a migration")]
+    #[requires(self.table_exist("public", "schema_version"), "schema_version should be created in the database")]
+    #[ensures(self.get_schema_version().iter().any(|row| row.get::<_, i32>("version") == *migration.get_version()), "the version should be recorded in schema_version")]
+    #[invariant(true)]
+    pub fn apply_migration(&mut self, migration: &Migration) {
+        let mut tx = self.client.transaction().unwrap();
+        let applied = tx
+            .batch_execute(migration.get_up())
+            .and_then(|_| tx.execute("insert into schema_version(version, name) values($1, $2)", &[migration.get_version(), &migration.get_name()]))
+            .and_then(|_| tx.commit());
+        if let Err(e) = applied {
+            panic!("[Database][apply_migration] migration {} {}: {}", migration.get_version(), migration.get_name(), e);
+        }
+    }
+
+    #[requires(self.is_connected(), "database should be connected before reverting a migration")]
+    #[requires(self.table_exist("public", "schema_version"), "schema_version should be created in the database")]
+    #[ensures(self.get_schema_version().iter().all(|row| row.get::<_, i32>("version") != *migration.get_version()), "the version should be removed from schema_version")]
+    #[invariant(true)]
+    pub fn revert_migration(&mut self, migration: &Migration) {
+        let mut tx = self.client.transaction().unwrap();
+        let reverted = tx
+            .batch_execute(migration.get_down())
+            .and_then(|_| tx.execute("delete from schema_version where version = $1", &[migration.get_version()]))
+            .and_then(|_| tx.commit());
+        if let Err(e) = reverted {
+            panic!("[Database][revert_migration] migration {} {}: {}", migration.get_version(), migration.get_name(), e);
+        }
+    }
+}
+
+// a write executed on the client, or within a transaction of it
+fn execute_write(client: &mut impl GenericClient, write: &Write) -> Result<u64, postgres::Error> {
+    match write {
+        Write::Request(req) => client.execute(
+            "INSERT INTO request_table(uuid, tm, side, order_px, vol, card, trader_id, time_in_force, order_type) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            &[
+                &req.get_uuid(),
+                &req.get_tm(),
+                &req.get_side(),
+                &req.get_order_px(),
+                &req.get_vol(),
+                &req.get_card(),
+                &req.get_trade_id(),
+                &req.get_time_in_force(),
+                &req.get_order_type()
+            ],
+        ),
+        Write::Amend(uuid, tm, order_px, vol, keep_priority) => client.execute(
+            "insert into amend_table(uuid, tm, order_px, vol, keep_priority) values($1, $2, $3, $4, $5)",
+            &[uuid, tm, order_px, vol, keep_priority],
+        ),
+        Write::InsertStatus(uuid, status) => client.execute("insert into status_table(uuid, status) values($1, $2)", &[uuid, status]),
+        Write::UpdateStatus(uuid, status) => client.execute("update status_table set status = $1 where uuid = $2", &[status, uuid]),
+        Write::Trade(buy_side_uuid, sell_side_uuid, card, trade) => client.execute(
+            "insert into trade_table(buy_uuid, sell_uuid, buy_side_id, sell_side_id, tx_price, tx_vol, card, tm) values($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[buy_side_uuid, sell_side_uuid, trade.get_buy_side_id(), trade.get_sell_side_id(), trade.get_tx_price(), trade.get_tx_vol(), card, trade.get_tx_time()],
+        ),
+        Write::Account(trader_id, cash, reserved_cash) => client.execute(
+            "insert into account_table(trader_id, cash, reserved_cash) values($1, $2, $3)
+             on conflict (trader_id) do update set cash = excluded.cash, reserved_cash = excluded.reserved_cash",
+            &[trader_id, cash, reserved_cash],
+        ),
+        Write::Inventory(trader_id, card, qty, reserved_qty) => client.execute(
+            "insert into inventory_table(trader_id, card, qty, reserved_qty) values($1, $2, $3, $4)
+             on conflict (trader_id, card) do update set qty = excluded.qty, reserved_qty = excluded.reserved_qty",
+            &[trader_id, card, qty, reserved_qty],
+        ),
+        Write::Ledger(entry) => client.execute(
+            "insert into ledger_table(tm, trader_id, operator_id, kind, card, amount, qty, reason) values($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[entry.get_tm(), entry.get_trader_id(), entry.get_operator_id(), entry.get_kind(), entry.get_card(), entry.get_amount(), entry.get_qty(), &entry.get_reason()],
+        ),
+    }
+}
+
+impl Store for Database {
+    fn upsert_trader(&mut self, trader_id: &i32, api_key: &str, secret: &str) {
+        Database::upsert_trader(self, trader_id, api_key, secret)
+    }
+
+    fn get_trader(&mut self, api_key: &str) -> Option<(i32, String)> {
+        Database::get_trader(self, api_key).map(|row| (row.get("trader_id"), row.get("secret")))
+    }
+
+    fn upsert_account(&mut self, trader_id: &i32, cash: &Price, reserved_cash: &Price) {
+        Database::upsert_account(self, trader_id, cash, reserved_cash)
+    }
+
+    fn upsert_inventory(&mut self, trader_id: &i32, card: &Card, qty: &i32, reserved_qty: &i32) {
+        Database::upsert_inventory(self, trader_id, card, qty, reserved_qty)
+    }
+
+    fn get_accounts(&mut self) -> Vec<(i32, Price, Price)> {
+        Database::get_accounts(self).iter().map(|row| (row.get("trader_id"), row.get("cash"), row.get("reserved_cash"))).collect()
+    }
+
+    fn get_inventories(&mut self) -> Vec<(i32, Card, i32, i32)> {
+        Database::get_inventories(self).iter().map(|row| (row.get("trader_id"), row.get("card"), row.get("qty"), row.get("reserved_qty"))).collect()
+    }
+
+    fn get_ledger(&mut self, trader_id: &i32) -> Vec<LedgerEntry> {
+        Database::get_ledger(self, trader_id)
+            .iter()
+            .map(|row| {
+                let (trader_id, operator_id, reason): (i32, i32, String) = (row.get("trader_id"), row.get("operator_id"), row.get("reason"));
+                let entry = match row.get::<_, Option<Card>>("card") {
+                    Some(card) => LedgerEntry::for_cards(trader_id, operator_id, &CardTransfer::new(card, row.get("qty"), &reason)),
+                    None => LedgerEntry::for_cash(trader_id, operator_id, &CashTransfer::new(row.get::<_, Price>("amount"), &reason)),
+                };
+                entry.with_seq(row.get("seq"), row.get("tm"))
+            })
+            .collect()
+    }
+
+    fn get_expected_cash(&mut self) -> Vec<(i32, i64)> {
+        Database::get_expected_cash(self).iter().map(|row| (row.get("trader_id"), row.get("cash"))).collect()
+    }
+
+    fn get_expected_cards(&mut self) -> Vec<(i32, Card, i32)> {
+        Database::get_expected_cards(self).iter().map(|row| (row.get("trader_id"), row.get("card"), row.get("qty"))).collect()
+    }
+
+    fn insert_request_table(&mut self, req: &RequestOrder) {
+        Database::insert_request_table(self, req)
+    }
+
+    fn request_exist(&mut self, uuid: &Uuid) -> bool {
+        Database::request_exist(self, uuid)
+    }
+
+    fn get_request(&mut self, uuid: &Uuid) -> Option<RequestOrder> {
+        Database::get_request(self, uuid).map(|row| RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get::<_, Price>("order_px"), row.get("vol"), row.get("card"), row.get("trader_id")))
+    }
+
+    fn insert_amend_table(&mut self, uuid: &Uuid, tm: &DateTime<Utc>, order_px: &Price, vol: &i32, keep_priority: &bool) {
+        Database::insert_amend_table(self, uuid, tm, order_px, vol, keep_priority)
+    }
+
+    fn insert_order_status(&mut self, uuid: &Uuid, status: &OrderStatus) {
+        Database::insert_order_status(self, uuid, status)
+    }
+
+    fn update_order_status(&mut self, uuid: &Uuid, status: &OrderStatus) {
+        Database::update_order_status(self, uuid, status)
+    }
+
+    fn order_status_exist(&mut self, uuid: &Uuid) -> bool {
+        Database::order_status_exist(self, uuid)
+    }
+
+    fn get_order_status(&mut self, uuid: &Uuid) -> OrderStatus {
+        Database::get_order_status(self, uuid)
+    }
+
+    fn find_order(&mut self, uuid: &Uuid) -> Result<Option<(RequestOrder, Option<OrderStatus>)>, String> {
+        Database::find_order(self, uuid)
+            .map(|row| row.map(|row| (RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get::<_, Price>("order_px"), row.get("vol"), row.get("card"), row.get("trader_id")), row.get("status"))))
+            .map_err(|e| e.to_string())
+    }
+
+    fn insert_trade_table(&mut self, buy_side_uuid: &Uuid, sell_side_uuid: &Uuid, card: &Card, trade: &Trade) {
+        Database::insert_trade_table(self, buy_side_uuid, sell_side_uuid, card, trade)
+    }
+
+    fn get_trades(&mut self, trader_id: &i32) -> Vec<(Card, Trade)> {
+        Database::get_trades(self, trader_id)
+            .iter()
+            .map(|row| (row.get("card"), Trade::new(row.get("tm"), row.get("buy_side_id"), row.get("sell_side_id"), row.get::<_, Price>("tx_price"), row.get("tx_vol"))))
+            .collect()
+    }
+
+    fn get_candles(&mut self, card: &Card, secs: &i64, from: &DateTime<Utc>, to: &DateTime<Utc>) -> Vec<Candle> {
+        Database::get_candles(self, card, secs, from, to)
+            .iter()
+            .map(|row| Candle::new(row.get("start"), row.get("open"), row.get("high"), row.get("low"), row.get("close"), row.get("volume")))
+            .collect()
+    }
+
+    fn get_realtime_tx_info(&mut self, side: &Side, card: &Card) -> Vec<RequestOrder> {
+        Database::get_realtime_tx_info(self, side, card)
+            .iter()
+            .map(|row| RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get::<_, Price>("order_px"), row.get("vol"), row.get("card"), row.get("trader_id")))
+            .collect()
+    }
+
+    fn get_trade_history(&mut self, id: &i32, date: &str) -> Vec<TradeHistory> {
+        Database::get_trade_history(self, id, date)
+            .iter()
+            .map(|row| TradeHistory::new(row.get("buy_side_id"), row.get("sell_side_id"), row.get::<_, Price>("tx_price"), row.get("tx_vol"), row.get("card")))
+            .collect()
+    }
+
+    fn get_request_history(&mut self, id: &i32, date: &str) -> Vec<RequestOrder> {
+        Database::get_request_history(self, id, date)
+            .iter()
+            .map(|row| {
+                RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get::<_, Price>("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"))
+                    .with_time_in_force(row.get("time_in_force"))
+                    .with_order_type(row.get("order_type"))
+            })
+            .collect()
+    }
+
+    fn get_status_history(&mut self, uuid: &Uuid) -> Vec<Status> {
+        Database::get_status_history(self, uuid).iter().map(|row| Status::new(row.get("uuid"), row.get("status"))).collect()
+    }
+
+    fn insert_run_table(&mut self) -> i64 {
+        Database::insert_run_table(self)
+    }
+
+    fn write(&mut self, writes: &[Write]) -> Result<(), String> {
+        Database::write(self, writes).map_err(|e| e.to_string())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.client.is_closed()
+    }
+}
+
+impl Migrator for Database {
+    fn get_migrations(&self) -> &'static [Migration] {
+        &POSTGRES
+    }
+
+    fn get_applied(&mut self) -> Vec<(i32, DateTime<Utc>)> {
+        Database::get_schema_version(self).iter().map(|row| (row.get("version"), row.get("applied_at"))).collect()
+    }
+
+    fn apply(&mut self, migration: &Migration) {
+        Database::apply_migration(self, migration)
+    }
+
+    fn revert(&mut self, migration: &Migration) {
+        Database::revert_migration(self, migration)
+    }
 }
@@ -0,0 +1,496 @@
+use crate::account::Accounts;
+use crate::candle::Candle;
+use crate::data_type::{CandleParam, Card, OrderAck, OrderStatus, ProcessResult, RequestOrder, Side};
+use crate::market_data::MarketData;
+use crate::order_queue::{OpenOrders, OrderQueue, PendingOrder, QueueDepth, QueueError};
+use crate::pool::StorePool;
+use crate::portfolio::Portfolio;
+use crate::scheduler::Scheduler;
+use crate::status_board::Stats;
+use async_std::channel;
+use chrono::Utc;
+use std::collections::{HashMap, LinkedList};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use uuid::Uuid;
+
+/**
+ * Matching worker of a single card. It owns the scheduler of the card,
+ * i.e. its CardBoard, latest trades and sequence counter, and consumes
+ * the order queue of the card on a thread of its own
+ **/
+#[derive(Clone)]
+pub struct Engine {
+    card: Card,
+    scheduler: Arc<Mutex<Scheduler>>,
+    queue: OrderQueue,
+    open_orders: OpenOrders,
+}
+
+impl Engine {
+    // the engine without its worker, the receiver has to be consumed by the caller
+    pub fn new(card: Card, capacity: usize) -> (Self, Receiver<PendingOrder>) {
+        let (queue, receiver) = OrderQueue::new(capacity);
+        let open_orders = OpenOrders::new();
+        let mut scheduler = Scheduler::for_cards(&[card]);
+        scheduler.set_open_orders(open_orders.clone());
+        let engine = Self {
+            card,
+            scheduler: Arc::new(Mutex::new(scheduler)),
+            queue,
+            open_orders,
+        };
+        (engine, receiver)
+    }
+
+    // recover the board of the card and start matching its orders
+    pub fn spawn(card: Card, capacity: usize, feed: &MarketData, accounts: &Accounts, open_orders: &OpenOrders) -> Self {
+        let (mut engine, receiver) = Self::new(card, capacity);
+        engine.open_orders = open_orders.clone();
+        {
+            let mut scheduler = engine.scheduler.lock().unwrap();
+            scheduler.set_open_orders(open_orders.clone());
+            scheduler.recover();
+            scheduler.set_feed(feed.clone());
+            scheduler.set_accounts(accounts.clone());
+        }
+
+        let worker = engine.clone();
+        thread::spawn(move || worker.run(receiver));
+        engine
+    }
+
+    /**
+     * The scheduler of the card, ready to match. A panic while it was locked
+     * leaves its board in an unknown state, so the board is rebuilt from the
+     * store, and a lost connection is opened again. Until both succeed the
+     * card is answered as unavailable, and the next lock tries again
+     **/
+    pub fn lock(&self) -> Result<MutexGuard<'_, Scheduler>, String> {
+        let (mut scheduler, poisoned) = match self.scheduler.lock() {
+            Ok(scheduler) => (scheduler, false),
+            Err(err) => (err.into_inner(), true),
+        };
+        scheduler.reconnect()?;
+        if poisoned {
+            scheduler.rebuild()?;
+            self.scheduler.clear_poison();
+            eprintln!("[RECOVERED][{:?}] the board is rebuilt from the store", self.card);
+        }
+        Ok(scheduler)
+    }
+
+    // the worker sleeps on the channel until an order of the card arrives
+    pub fn run(&self, receiver: Receiver<PendingOrder>) {
+        for pending in receiver.iter() {
+            let ack = match self.lock() {
+                Ok(mut res) => res.execute(pending.get_order()),
+                // the order is answered as never stored, so it could be retried
+                Err(err) => {
+                    eprintln!("[ERROR][{:?}] {}", self.card, err);
+                    OrderAck::new(pending.get_order(), ProcessResult::TxStoreFailed, vec![])
+                }
+            };
+            self.queue.done(&pending.get_order().get_uuid());
+            // an order stays open only while it rests on the board
+            if !matches!(ack.get_status(), Some(OrderStatus::Confirmed) | Some(OrderStatus::PartiallyFilled)) {
+                self.open_orders.close(&pending.get_order().get_trade_id());
+            }
+            pending.acknowledge(ack);
+        }
+    }
+
+    pub fn get_card(&self) -> &Card {
+        &self.card
+    }
+
+    pub fn get_scheduler(&self) -> &Arc<Mutex<Scheduler>> {
+        &self.scheduler
+    }
+
+    pub fn get_queue(&self) -> &OrderQueue {
+        &self.queue
+    }
+
+    pub fn get_open_orders(&self) -> &OpenOrders {
+        &self.open_orders
+    }
+}
+
+/**
+ * Engines of all cards. Orders are routed by their cards, so cards are
+ * matched in parallel while orders of a card stay strictly sequential.
+ * Every engine matches on a store of its own, while whatever is only
+ * read from the database goes through the pool, so reading never waits
+ * for matching
+ **/
+#[derive(Clone)]
+pub struct Engines {
+    engines: HashMap<Card, Engine>,
+    pool: StorePool,
+    feed: MarketData,
+    accounts: Accounts,
+    open_orders: OpenOrders,
+    max_open_orders: usize,
+    // orders are admitted one at a time, so an uuid checked across cards stays unique until pushed
+    admission: Arc<Mutex<()>>,
+}
+
+impl Engines {
+    // balances of traders are loaded before any engine starts matching
+    pub fn spawn(capacity: usize, feed: MarketData, accounts: Accounts, pool: StorePool) -> Self {
+        let mut loader = Scheduler::for_cards(&[]);
+        accounts.load(&mut *loader.db);
+        feed.start_run(loader.db.insert_run_table());
+
+        let open_orders = OpenOrders::new();
+        let mut engines = HashMap::new();
+        for card in [Card::Pikachu, Card::Bulbasaur, Card::Charmander, Card::Squirtle].iter() {
+            engines.insert(*card, Engine::spawn(*card, capacity, &feed, &accounts, &open_orders));
+        }
+        Self {
+            engines,
+            max_open_orders: *loader.validator.get_max_open_orders(),
+            pool,
+            feed,
+            accounts,
+            open_orders,
+            admission: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn get_feed(&self) -> &MarketData {
+        &self.feed
+    }
+
+    pub fn get(&self, card: &Card) -> Option<&Engine> {
+        self.engines.get(card)
+    }
+
+    pub fn get_pool(&self) -> &StorePool {
+        &self.pool
+    }
+
+    pub fn get_accounts(&self) -> &Accounts {
+        &self.accounts
+    }
+
+    pub fn get_max_open_orders(&self) -> &usize {
+        &self.max_open_orders
+    }
+
+    pub fn push(&self, req: RequestOrder) -> Result<channel::Receiver<OrderAck>, QueueError> {
+        let engine = match self.engines.get(&req.get_card()) {
+            Some(engine) => engine,
+            None => return Err(QueueError::Closed),
+        };
+
+        let _admission = self.admission.lock().unwrap();
+        // an uuid must not be reused across cards either
+        if self.engines.values().any(|engine| engine.queue.contains(&req.get_uuid())) {
+            return Err(QueueError::Duplicate);
+        }
+        if !self.open_orders.open(&req.get_trade_id(), self.max_open_orders) {
+            return Err(QueueError::TooManyOpenOrders);
+        }
+        engine.queue.push(req).inspect_err(|_| self.open_orders.close(&req.get_trade_id()))
+    }
+
+    // orders of a trader resting on the boards or waiting in the queues of all cards
+    pub fn get_open_orders(&self, trader_id: &i32) -> usize {
+        self.open_orders.get(trader_id)
+    }
+
+    // the engine matching the order with the given uuid
+    pub async fn locate(&self, uuid: &Uuid) -> Result<Option<&Engine>, String> {
+        let uuid = *uuid;
+        let card = self.pool.read(move |db| db.get_request(&uuid).map(|req| req.get_card())).await?;
+        Ok(card.and_then(|card| self.engines.get(&card)))
+    }
+
+    pub fn get_depth(&self) -> QueueDepth {
+        self.engines.values().map(|engine| engine.queue.get_depth()).fold(QueueDepth::default(), |total, depth| total + depth)
+    }
+
+    /**
+     * Positions of a trader built from its trades in trade_table, marked at
+     * the last trade of each card and joined by its orders resting on the boards
+     **/
+    pub async fn get_portfolio(&self, trader_id: &i32) -> Result<Portfolio, String> {
+        let id = *trader_id;
+        let trades = self.pool.read(move |db| db.get_trades(&id)).await?;
+        let mut portfolio = Portfolio::new(*trader_id);
+        for (card, trade) in trades {
+            let side = if trade.get_buy_side_id() == trader_id { Side::Buy } else { Side::Sell };
+            portfolio.add_trade(&card, side, trade.get_tx_price(), *trade.get_tx_vol());
+        }
+
+        for card in [Card::Pikachu, Card::Bulbasaur, Card::Charmander, Card::Squirtle].iter() {
+            if let Some(engine) = self.engines.get(card) {
+                let scheduler = engine.lock()?;
+                if let Some(trade) = scheduler.trade_board.get_back_trade(card) {
+                    portfolio.mark(card, trade.get_tx_price());
+                }
+                for side in [Side::Buy, Side::Sell].iter() {
+                    let (vol, notional) = scheduler.tx_board.get_open_exposure(card, *side, trader_id);
+                    portfolio.add_open_orders(card, *side, vol, &notional);
+                }
+            }
+        }
+        Ok(portfolio)
+    }
+
+    /**
+     * Candles of a card in the range of the param. Closed candles are
+     * aggregated from trade_table, while the current one is taken live
+     * from the engine of the card
+     **/
+    pub async fn get_candles(&self, card: &Card, param: &CandleParam) -> Result<Vec<Candle>, String> {
+        let (interval, from, to) = (param.get_interval(), param.get_from(), param.get_to());
+        let now = Utc::now();
+        let current = interval.get_start(&now);
+        let (card, secs, closed) = (*card, interval.get_secs(), to.min(current));
+        let mut candles: Vec<Candle> = match from < closed {
+            true => self.pool.read(move |db| db.get_candles(&card, &secs, &from, &closed)).await?,
+            false => Vec::new(),
+        };
+
+        if from <= current && current < to {
+            if let Some(engine) = self.engines.get(&card) {
+                if let Some(candle) = engine.lock()?.trade_board.get_candles().get_current(&card, interval, &now) {
+                    candles.push(*candle);
+                }
+            }
+        }
+        Ok(candles)
+    }
+
+    // changes of the latest orders of a trader across cards after the given sequence number
+    pub fn get_changes_since(&self, id: &i32, seq: u64) -> Result<Vec<(u64, Stats)>, String> {
+        let mut changes = Vec::<(u64, Stats)>::new();
+        for engine in self.engines.values() {
+            changes.extend(engine.lock()?.status_board.get_changes_since(id, seq));
+        }
+        changes.sort_by_key(|(change, _)| *change);
+        Ok(changes)
+    }
+
+    // latest orders of a trader across cards, ordered by their time
+    pub fn get_latest_orders(&self, id: &i32) -> Result<Option<LinkedList<Stats>>, String> {
+        let mut stats = Vec::<Stats>::new();
+        let mut limit: usize = 0;
+        for engine in self.engines.values() {
+            let scheduler = engine.lock()?;
+            limit = *scheduler.status_board.get_limit();
+            if let Some(list) = scheduler.get_latest_orders(id) {
+                stats.extend(list);
+            }
+        }
+        if stats.is_empty() {
+            return Ok(None);
+        }
+
+        stats.sort_by_key(|stat| *stat.get_tm());
+        let skipped = stats.len().saturating_sub(limit);
+        Ok(Some(stats.into_iter().skip(skipped).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::Accounts;
+    use crate::data_type::{CandleParam, Card, Interval, OrderStatus, Price, ProcessResult, RequestOrder, Side};
+    use crate::engine::{Engine, Engines};
+    use crate::market_data::MarketData;
+    use crate::order_queue::QueueError;
+    use crate::pool::StorePool;
+    use crate::sqlite::SqliteStore;
+    use crate::store::MemoryStore;
+    use async_std::task;
+    use chrono::Utc;
+    use rusqlite::Connection;
+    use std::collections::HashMap;
+    use std::env;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use uuid::Uuid;
+
+    // engines of the card of a single engine, reading the tables it writes
+    fn get_engines(engine: &Engine, tables: &MemoryStore) -> Engines {
+        engine.get_scheduler().lock().unwrap().db = Box::new(tables.clone());
+        let tables = tables.clone();
+        Engines {
+            engines: [(*engine.get_card(), engine.clone())].iter().cloned().collect::<HashMap<Card, Engine>>(),
+            pool: StorePool::with_opener(1, move || Box::new(tables.clone())),
+            feed: MarketData::new(),
+            accounts: Accounts::default(),
+            open_orders: engine.get_open_orders().clone(),
+            max_open_orders: *engine.get_scheduler().lock().unwrap().validator.get_max_open_orders(),
+            admission: Arc::new(Mutex::new(())),
+        }
+    }
+
+    #[test]
+    fn given_engines_of_two_cards_when_orders_routed_then_matched_on_their_own_boards_and_sequences() {
+        let (pikachu, pikachu_receiver) = Engine::new(Card::Pikachu, 8);
+        let (squirtle, squirtle_receiver) = Engine::new(Card::Squirtle, 8);
+        assert!(pikachu.get_scheduler().lock().unwrap().tx_board.get_grid(&Card::Squirtle).is_none());
+
+        let (pikachu_worker, squirtle_worker) = (pikachu.clone(), squirtle.clone());
+        thread::spawn(move || pikachu_worker.run(pikachu_receiver));
+        thread::spawn(move || squirtle_worker.run(squirtle_receiver));
+
+        let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 7.05, 1, Card::Pikachu, 61);
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 7.05, 1, Card::Pikachu, 62);
+        let other = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 7.05, 1, Card::Squirtle, 62);
+
+        let mut acks = Vec::new();
+        for (engine, req) in [(&pikachu, sell), (&pikachu, buy), (&squirtle, other)].iter() {
+            match engine.get_queue().push(*req) {
+                Ok(receiver) => acks.push(async_std::task::block_on(receiver.recv()).unwrap()),
+                Err(e) => panic!("[ERROR] Test Failed: Order cannot be pushed: {:?}", e),
+            }
+        }
+
+        assert_eq!(&ProcessResult::TxConfirmed, acks[0].get_result());
+        assert_eq!(&ProcessResult::TxFilled, acks[1].get_result());
+        assert_eq!(&ProcessResult::TxConfirmed, acks[2].get_result());
+        // every card counts its own sequence
+        assert_eq!((&1, &2, &1), (acks[0].get_seq(), acks[1].get_seq(), acks[2].get_seq()));
+        assert_eq!(Some(OrderStatus::Confirmed), *acks[2].get_status());
+
+        // a card never accepts orders of other cards
+        let misrouted = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 7.05, 1, Card::Pikachu, 62);
+        assert_eq!(ProcessResult::UnknownCard, squirtle.get_scheduler().lock().unwrap().process(&misrouted));
+        assert_eq!(Some(1), pikachu.get_scheduler().lock().unwrap().get_latest_trades(&Card::Pikachu).map(|list| list.len()));
+        assert_eq!(Some(0), squirtle.get_scheduler().lock().unwrap().get_latest_trades(&Card::Squirtle).map(|list| list.len()));
+    }
+
+    #[test]
+    fn given_a_scheduler_poisoned_by_a_panic_when_orders_pushed_then_board_rebuilt_once_the_store_is_readable() {
+        let path = env::temp_dir().join(format!("pokemon-{}.db", Uuid::new_v4()));
+        let path = path.to_str().unwrap().to_string();
+        let (engine, receiver) = Engine::new(Card::Charmander, 8);
+        let resting = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 5.00, 1, Card::Charmander, 64);
+        {
+            let mut scheduler = engine.get_scheduler().lock().unwrap();
+            scheduler.db = Box::new(SqliteStore::new(&path));
+            assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&resting));
+        }
+        let poisoner = engine.clone();
+        assert!(thread::spawn(move || {
+            let _scheduler = poisoner.get_scheduler().lock().unwrap();
+            panic!("[Database][request_exist] connection closed");
+        })
+        .join()
+        .is_err());
+
+        let worker = engine.clone();
+        thread::spawn(move || worker.run(receiver));
+        let matched = |req: RequestOrder| async_std::task::block_on(engine.get_queue().push(req).unwrap().recv()).unwrap();
+
+        // the board cannot be rebuilt while the store is unreadable, so the order is answered as never stored
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch("alter table status_table rename to lost_table;").unwrap();
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 4.00, 1, Card::Charmander, 63);
+        assert_eq!(&ProcessResult::TxStoreFailed, matched(buy).get_result());
+        assert!(!engine.get_queue().contains(&buy.get_uuid()));
+        assert!(engine.lock().is_err());
+
+        // and matched on the rebuilt board once the store is back
+        conn.execute_batch("alter table lost_table rename to status_table;").unwrap();
+        assert_eq!(&ProcessResult::TxConfirmed, matched(buy).get_result());
+        let book = engine.lock().unwrap().get_book(&Card::Charmander, 10).unwrap();
+        assert_eq!((1, 1), (book.get_bids().len(), book.get_asks().len()));
+        assert_eq!(1, engine.get_open_orders().get(&64));
+        assert!(engine.get_scheduler().lock().is_ok());
+    }
+
+    #[test]
+    fn given_a_trader_with_max_open_orders_when_more_pushed_then_refused_until_some_closed() {
+        let (engine, receiver) = Engine::new(Card::Bulbasaur, 8);
+        {
+            let mut scheduler = engine.get_scheduler().lock().unwrap();
+            scheduler.validator = scheduler.validator.clone().with_max_open_orders(2);
+        }
+        let engines = get_engines(&engine, &MemoryStore::new());
+        let worker = engine.clone();
+        thread::spawn(move || worker.run(receiver));
+
+        let order = |side: Side, trader_id: i32| RequestOrder::new(Uuid::new_v4(), Utc::now(), side, 6.10, 1, Card::Bulbasaur, trader_id);
+        let matched = |req: RequestOrder| async_std::task::block_on(engines.push(req).unwrap().recv()).unwrap();
+        let (first, second) = (order(Side::Buy, 91), order(Side::Buy, 91));
+        assert_eq!(&ProcessResult::TxConfirmed, matched(first).get_result());
+        assert_eq!(&ProcessResult::TxConfirmed, matched(second).get_result());
+
+        // resting orders count as well as queued ones, checked while the scheduler is busy matching
+        let matching = engine.get_scheduler().lock().unwrap();
+        assert_eq!(2, engines.get_open_orders(&91));
+        assert_eq!(Some(QueueError::TooManyOpenOrders), engines.push(order(Side::Buy, 91)).err());
+        drop(matching);
+
+        // a filled order closes along with the resting one it fills
+        assert_eq!(&ProcessResult::TxFilled, matched(order(Side::Sell, 92)).get_result());
+        assert_eq!((1, 0), (engines.get_open_orders(&91), engines.get_open_orders(&92)));
+        assert_eq!(&ProcessResult::TxConfirmed, matched(order(Side::Buy, 91)).get_result());
+        assert_eq!(Some(QueueError::TooManyOpenOrders), engines.push(order(Side::Buy, 91)).err());
+
+        assert_eq!(ProcessResult::TxCancelled, engine.get_scheduler().lock().unwrap().cancel(&second.get_uuid(), &91));
+        assert_eq!(1, engines.get_open_orders(&91));
+        assert!(engines.push(order(Side::Buy, 91)).is_ok());
+    }
+
+    #[test]
+    fn given_trades_and_resting_orders_when_portfolio_requested_then_positions_marked_with_exposure() {
+        let (buyer, seller) = (31, 32);
+        let (engine, _receiver) = Engine::new(Card::Pikachu, 8);
+        let engines = get_engines(&engine, &MemoryStore::new());
+
+        {
+            let mut scheduler = engine.get_scheduler().lock().unwrap();
+            assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 3.00, 2, Card::Pikachu, seller)));
+            assert_eq!(ProcessResult::TxFilled, scheduler.process(&RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.00, 1, Card::Pikachu, buyer)));
+        }
+        let resting = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 2, Card::Pikachu, buyer);
+        assert_eq!(ProcessResult::TxConfirmed, engine.get_scheduler().lock().unwrap().process(&resting));
+
+        assert_eq!(Some(&Card::Pikachu), task::block_on(engines.locate(&resting.get_uuid())).unwrap().map(|engine| engine.get_card()));
+        assert!(task::block_on(engines.locate(&Uuid::new_v4())).unwrap().is_none());
+        let portfolio = task::block_on(engines.get_portfolio(&buyer)).unwrap();
+        let pikachu = portfolio.get_position(&Card::Pikachu).unwrap();
+        assert_eq!((&1, &Some(Price::from(3.00)), &Some(Price::from(3.00))), (pikachu.get_qty(), pikachu.get_avg_cost(), pikachu.get_mark_px()));
+        assert_eq!((&2, &Price::from(4.00)), (pikachu.get_open_buy_vol(), pikachu.get_open_buy_notional()));
+
+        let portfolio = task::block_on(engines.get_portfolio(&seller)).unwrap();
+        let pikachu = portfolio.get_position(&Card::Pikachu).unwrap();
+        assert_eq!((&-1, &1, &Price::from(3.00)), (pikachu.get_qty(), pikachu.get_open_sell_vol(), pikachu.get_open_sell_notional()));
+        assert!(task::block_on(engines.get_portfolio(&(seller + 1))).unwrap().get_positions().is_empty());
+    }
+
+    #[test]
+    fn given_trades_on_a_card_when_candles_requested_then_current_candle_taken_live() {
+        let (engine, _receiver) = Engine::new(Card::Squirtle, 8);
+        let engines = get_engines(&engine, &MemoryStore::new());
+
+        {
+            let mut scheduler = engine.get_scheduler().lock().unwrap();
+            for (side, px, vol, trader) in [(Side::Sell, 4.00, 3, 71), (Side::Buy, 4.00, 1, 72), (Side::Sell, 3.50, 1, 72), (Side::Buy, 4.50, 2, 73)].iter() {
+                scheduler.process(&RequestOrder::new(Uuid::new_v4(), Utc::now(), *side, *px, *vol, Card::Squirtle, *trader));
+            }
+        }
+
+        // only trades of today are stored, so the live candle is the latest one
+        let param: CandleParam = serde_json::from_value(serde_json::json!({ "interval": "1d" })).unwrap();
+        let candles = task::block_on(engines.get_candles(&Card::Squirtle, &param)).unwrap();
+        let today = candles.last().unwrap();
+        assert_eq!(&Interval::OneDay.get_start(&Utc::now()), today.get_start());
+        assert_eq!((&Price::from(4.00), &Price::from(4.00), &Price::from(3.50), &Price::from(4.00)), (today.get_open(), today.get_high(), today.get_low(), today.get_close()));
+        assert_eq!(&3, today.get_volume());
+        assert!(candles.windows(2).all(|pair| pair[0].get_start() < pair[1].get_start()));
+
+        let to = Interval::OneDay.get_start(&Utc::now());
+        let param: CandleParam = serde_json::from_value(serde_json::json!({ "interval": "1d", "to": to })).unwrap();
+        assert!(task::block_on(engines.get_candles(&Card::Squirtle, &param)).unwrap().iter().all(|candle| candle.get_start() < &to));
+    }
+}
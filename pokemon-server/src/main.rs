@@ -2,16 +2,30 @@
 extern crate ini;
 
 use std::sync::atomic::{AtomicBool, Ordering};
-use data_type::{Card, RequestOrder, ProcessStatus, Rsp, HistoryParam, StatusParam};
-use status_board::Status;
+use data_type::{Card, CardTransfer, CashTransfer, LedgerEntry, OrderAck, RejectReason, RequestOrder, AmendOrder, ProcessStatus, ProcessResult, OrderStatus, Rsp, HistoryParam, StatusParam, BookParam, CandleParam};
+use status_board::{Stats, Status};
+use trade_board::{Trade, TradeHistory};
+use tx_board::OrderBook;
 use scheduler::Scheduler;
-use std::sync::{Arc, Mutex};
+use engine::Engines;
+use pool::StorePool;
+use validator::Validator;
+use account::{Account, Accounts, BookingError, Reconciliation};
+use portfolio::Portfolio;
+use candle::Candle;
+use auth::Authenticator;
+use rate_limit::RateLimiter;
+use market_data::MarketData;
+use order_queue::{QueueDepth, QueueError};
 use tide::{Body, Request, Response, StatusCode};
 use std::env;
 use settings::Settings;
 use std::thread;
 use std::time::Duration;
 use ctrlc;
+use uuid::Uuid;
+use async_std::future;
+use serde::Serialize;
 
 mod settings;
 mod data_type;
@@ -20,6 +34,22 @@ mod status_board;
 mod trade_board;
 mod tx_board;
 mod database;
+mod store;
+mod sqlite;
+mod migration;
+mod pool;
+mod validator;
+mod order_queue;
+mod engine;
+mod market_data;
+mod websocket;
+mod sse;
+mod openapi;
+mod account;
+mod portfolio;
+mod candle;
+mod auth;
+mod rate_limit;
 
 static STOP: AtomicBool = AtomicBool::new(false);
 
@@ -35,103 +65,636 @@ pub fn get_server_config(mut args: env::Args) -> String {
     }
 }
 
-pub fn order_queue_proc(schler: &std::sync::Arc<std::sync::Mutex<scheduler::Scheduler>>) {
-    let handler = Arc::clone(&schler);
-    loop {
-        match handler.lock() {
-            Ok(mut res) => {
-                if let Some(req) = res.order_queue.pop_front() {
-                    res.process(&req);
-                    // println!("{:?}", &req);
-                }
-            }
-            Err(err) => {
-                eprintln!("[ERROR] {}", err);
-            }
+pub fn get_ack_timeout(mut args: env::Args) -> Duration {
+    match args.nth(1) {
+        Some(config) => {
+            let cfg = Settings::new(config);
+            Duration::from_millis(cfg.get_ack_timeout_ms())
+        },
+        None => {
+            Duration::from_millis(3000)
+        }
+    }
+}
+
+pub fn get_queue_capacity(mut args: env::Args) -> usize {
+    match args.nth(1) {
+        Some(config) => {
+            let cfg = Settings::new(config);
+            cfg.get_queue_capacity()
+        },
+        None => {
+            10000
+        }
+    }
+}
+
+pub fn get_pool_size(mut args: env::Args) -> usize {
+    match args.nth(1) {
+        Some(config) => {
+            let cfg = Settings::new(config);
+            cfg.get_pool_size()
+        },
+        None => {
+            4
         }
     }
 }
 
-pub fn shudown_checker(handler: &std::sync::Arc<std::sync::Mutex<scheduler::Scheduler>>) {
+pub fn get_auth_skew_ms(mut args: env::Args) -> i64 {
+    match args.nth(1) {
+        Some(config) => {
+            let cfg = Settings::new(config);
+            cfg.get_auth_skew_ms()
+        },
+        None => {
+            30000
+        }
+    }
+}
+
+pub fn get_account_enabled(mut args: env::Args) -> bool {
+    match args.nth(1) {
+        Some(config) => {
+            let cfg = Settings::new(config);
+            cfg.get_account_enabled()
+        },
+        None => {
+            false
+        }
+    }
+}
+
+pub fn get_operators(mut args: env::Args) -> Vec<i32> {
+    match args.nth(1) {
+        Some(config) => {
+            let cfg = Settings::new(config);
+            cfg.get_operators()
+        },
+        None => {
+            Vec::new()
+        }
+    }
+}
+
+// token buckets of order submissions and of history queries, 10/s and 1/s by default
+pub fn get_rate_limiter(mut args: env::Args, kind: &str) -> RateLimiter {
+    let default = match kind {
+        "order" => RateLimiter::new(10.0, 20.0),
+        _ => RateLimiter::new(1.0, 5.0),
+    };
+    match args.nth(1) {
+        Some(config) => {
+            let cfg = Settings::new(config);
+            cfg.get_rate_limiter(kind, &default)
+        },
+        None => {
+            default
+        }
+    }
+}
+
+pub fn get_card(param: &str) -> Option<Card> {
+    match param {
+        "Pikachu" => Some(Card::Pikachu),
+        "Bulbasaur" => Some(Card::Bulbasaur),
+        "Charmander" => Some(Card::Charmander),
+        "Squirtle" => Some(Card::Squirtle),
+        _ => None,
+    }
+}
+
+pub fn get_rejection_msg(scheduler: &Scheduler, req: &RequestOrder, reason: &RejectReason) -> String {
+    let (card, px) = (req.get_card(), req.get_order_px());
+    match (scheduler.tx_board.get_grid(&card), reason) {
+        (_, RejectReason::InvalidPrice) => format!("order_px {} of {:?} is finer than a cent", px, card),
+        (Some(grid), RejectReason::OffTick) => format!("order_px {} of {:?} is off the price grid with tick {}", px, card, grid.get_tick()),
+        (Some(grid), RejectReason::PxOutOfRange) => format!("order_px {} of {:?} is out of the range [{}, {}]", px, card, grid.get_min(), grid.get_max()),
+        (_, RejectReason::InvalidVolume) => format!("vol {} is out of the range [1, {}]", req.get_vol(), scheduler.validator.get_max_vol()),
+        (_, RejectReason::InvalidTraderId) => format!("trader_id {} is out of the range [0, {})", req.get_trade_id(), scheduler.validator.get_max_traders()),
+        (_, RejectReason::TimestampSkew) => format!("tm {} deviates from the server time by more than {} ms", req.get_tm(), scheduler.validator.get_max_tm_skew().num_milliseconds()),
+        (_, RejectReason::DuplicateUuid) => format!("the order with uuid: {} has been received", req.get_uuid()),
+        (_, RejectReason::TooManyOpenOrders) => format!("trader {} reaches the limit of {} open orders", req.get_trade_id(), scheduler.validator.get_max_open_orders()),
+        (_, RejectReason::InsufficientCash) => format!("trader {} has {} of cash available, short of {} x {}", req.get_trade_id(), scheduler.get_accounts().get_account(&req.get_trade_id()).get_available_cash(), req.get_vol(), scheduler.get_reserve_px(req)),
+        (_, RejectReason::InsufficientCards) => format!("trader {} has {} {:?} available, short of {}", req.get_trade_id(), scheduler.get_accounts().get_account(&req.get_trade_id()).get_holding(&card).get_available(), card, req.get_vol()),
+        _ => format!("the order with uuid: {} is rejected", req.get_uuid()),
+    }
+}
+
+/**
+ * Book a balance change of an operator on the accounts shared by the
+ * engines, answered with the account of the trader after the change
+ **/
+pub async fn get_booking_response(engines: &Engines, entry: LedgerEntry) -> tide::Result {
+    let accounts = engines.get_accounts().clone();
+    let (status, msg, result) = if entry.get_reason().trim().is_empty() || (entry.get_amount().get_cents() == 0 && entry.get_qty() == &0) || !entry.get_amount().is_whole_cents() {
+        (StatusCode::BadRequest, String::from("a non-zero amount in whole cents or qty and a reason are required"), None)
+    } else if !accounts.is_enabled() {
+        (StatusCode::Conflict, String::from("accounts are disabled, enable them in the [account] section"), None)
+    } else {
+        let booking = entry.clone();
+        // booked on a store of the pool, so bookings neither wait for one another nor hold up a worker
+        let booked = match engines.get_pool().read(move |db| accounts.book(db, &booking)).await {
+            Ok(booked) => booked,
+            Err(e) => return get_unavailable_response(e, String::from("{}")),
+        };
+        match booked {
+            Ok(account) => (StatusCode::Ok, format!("{:?} of trader {} booked by operator {}", entry.get_kind(), entry.get_trader_id(), entry.get_operator_id()), Some(Ok(account))),
+            Err(BookingError::Rejected(reason)) => (StatusCode::UnprocessableEntity, format!("{:?} of trader {} exceeds the available balance", entry.get_kind(), entry.get_trader_id()), Some(Err(reason))),
+            // neither the balance nor the entry is kept, so the operator could book it again
+            Err(BookingError::StoreFailed(e)) => return get_unavailable_response(e, String::from("{}")),
+        }
+    };
+
+    let mut res = Response::new(status);
+    match result {
+        Some(Ok(account)) => res.set_body(Body::from_json(&Rsp::<Account>::new(ProcessStatus::Success, msg, account))?),
+        Some(Err(reason)) => res.set_body(Body::from_json(&Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}")).with_reason(reason))?),
+        None => res.set_body(Body::from_json(&Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}")))?),
+    }
+    Ok(res)
+}
+
+// a read finding the database unreachable is answered like a full order queue
+pub fn get_unavailable_response<T: Serialize>(e: String, data: T) -> tide::Result {
+    let mut res = Response::new(StatusCode::ServiceUnavailable);
+    res.insert_header("Retry-After", "1");
+    let rsp = Rsp::<T>::new(ProcessStatus::Failed, format!("the database is unavailable, retry later: {}", e), data);
+    res.set_body(Body::from_json(&rsp)?);
+    Ok(res)
+}
+
+pub fn shudown_checker(engines: &Engines) {
     let mut check_times: i32 = 0;
     loop {
-        match handler.lock() {
-            Ok(res) => {
-                let livings: usize = res.order_queue.len();
-                if livings == 0 {
-                    check_times = check_times + 1;
-                    if check_times >= 10 {
-                        println!("[SHUTDOWN] Server shutdown.");
-                        std::process::exit(-1);
-                    }
-                } else {
-                    println!("[SHUTDOWN] Server shutting down. Consuming rest requests");
-                }
-                thread::sleep(Duration::from_millis(100));
-            },
-            Err(err) => {
-                eprintln!("[ERROR] {}", err);
+        let livings: usize = *engines.get_depth().get_depth();
+        if livings == 0 {
+            check_times = check_times + 1;
+            if check_times >= 10 {
+                println!("[SHUTDOWN] Server shutdown.");
+                std::process::exit(-1);
             }
+        } else {
+            println!("[SHUTDOWN] Server shutting down. Consuming rest requests");
         }
+        thread::sleep(Duration::from_millis(100));
     }
 }
 
-pub fn signal_handler(terminator: &std::sync::Arc<std::sync::Mutex<scheduler::Scheduler>>) {
+pub fn signal_handler(terminator: &Engines) {
     STOP.store(true, Ordering::Release);
-    let handler = Arc::clone(&terminator);
+    let engines = terminator.clone();
     println!("\n[SHUTDOWN] Server shutting down. Consuming rest requests");
-    std::thread::spawn(move || shudown_checker(&handler));    
+    std::thread::spawn(move || shudown_checker(&engines));    
 }
 
 #[async_std::main]
 async fn main() -> tide::Result<()> {
     // Obtain config file path
     let args = env::args();
-    if args.len() != 2 {
-        panic!("Usage: ./[executable] [config_file_path]");
+    if args.len() < 2 {
+        panic!("Usage: ./[executable] [config_file_path] [register trader_id... | migrate up|down|status]");
+    }
+
+    // Bring the schema of the store up to date, or back by one version
+    if env::args().nth(2).as_deref() == Some("migrate") {
+        let mut migrator = match migration::open() {
+            Some(migrator) => migrator,
+            None => {
+                println!("[MIGRATE] the memory store keeps no schema");
+                return Ok(());
+            }
+        };
+        match env::args().nth(3).as_deref().unwrap_or("up") {
+            "up" => {
+                let applied = migration::up(&mut *migrator);
+                for m in applied.iter() {
+                    println!("[MIGRATE] applied {:04}_{}", m.get_version(), m.get_name());
+                }
+                println!("[MIGRATE] {} migrations applied", applied.len());
+            },
+            "down" => match migration::down(&mut *migrator) {
+                Some(m) => println!("[MIGRATE] reverted {:04}_{}", m.get_version(), m.get_name()),
+                None => println!("[MIGRATE] no migration to revert"),
+            },
+            "status" => {
+                for (m, applied_at) in migration::status(&mut *migrator).iter() {
+                    match applied_at {
+                        Some(tm) => println!("{:04}_{}\tapplied at {}", m.get_version(), m.get_name(), tm),
+                        None => println!("{:04}_{}\tpending", m.get_version(), m.get_name()),
+                    }
+                }
+            },
+            other => eprintln!("[ERROR] unknown migrate command {}, expected up, down or status", other),
+        }
+        return Ok(());
     }
 
+    // Issue the key pairs of traders, printed as trader_id,api_key,secret
+    let auth = Authenticator::new(store::open(), get_auth_skew_ms(env::args()));
+    if env::args().nth(2).as_deref() == Some("register") {
+        for id in env::args().skip(3) {
+            match id.parse::<i32>() {
+                Ok(trader_id) => {
+                    let credentials = auth.register(trader_id);
+                    println!("{},{},{}", credentials.get_trader_id(), credentials.get_api_key(), credentials.get_secret());
+                },
+                Err(e) => eprintln!("[ERROR] invalid trader id {}: {}", id, e),
+            }
+        }
+        return Ok(());
+    }
+
+    // Balances are booked by the operators only
+    let admin = auth.operators(&get_operators(env::args()));
+
+    // Throttle order submissions and history queries per trader
+    let (order_limit, query_limit) = (get_rate_limiter(env::args(), "order"), get_rate_limiter(env::args(), "query"));
+
     // Set server configurations
     let srv = get_server_config(args);
+    let ack_timeout = get_ack_timeout(env::args());
 
-    // Recover transaction board if there are interruptions during a day
-    let scheduler = Arc::new(Mutex::new(Scheduler::new()));
-    scheduler.lock().unwrap().recover();
-    
-    let (req_checker, trade_checker, order_checker, activator, terminator, trade_history, order_history, status_checker) = (
-        scheduler.clone(),
-        scheduler.clone(),
-        scheduler.clone(),
-        scheduler.clone(),
-        scheduler.clone(),
-        scheduler.clone(),
-        scheduler.clone(),
-        scheduler.clone(),
+    // Recover the board of each card and spawn its matching engine, sharing the accounts of traders,
+    // while histories are read through a pool of stores apart from the ones matching orders
+    let accounts = Accounts::new(get_account_enabled(env::args()));
+    let engines = Engines::spawn(get_queue_capacity(env::args()), MarketData::new(), accounts, StorePool::new(get_pool_size(env::args())));
+
+    let (cash_booker, card_booker, ledger_checker, reconciler, portfolio_checker, candle_checker) = (engines.clone(), engines.clone(), engines.clone(), engines.clone(), engines.clone(), engines.clone());
+    let (submitter, canceller, amender, depth_checker, book_checker, subscriber, trade_streamer, order_streamer, trade_checker_v2, order_checker_v2, trade_history_v2, order_history_v2, trade_checker, order_checker, trade_history, order_history, status_checker, terminator) = (
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines.clone(),
+        engines,
     );
 
     let mut server = tide::new();
 
-    // Spawn process of an order queue
-    std::thread::spawn(move || order_queue_proc(&activator));
-
     // Graceful shutdown handler
     ctrlc::set_handler(move || signal_handler(&terminator)).expect("Error setting Ctrl-C handler");
 
     server
         .at("/api/pokemon/card")
+        .with(auth.clone())
+        .with(order_limit)
         .post(move |mut req: Request<()>| {
-            let handler = Arc::clone(&req_checker);
+            let engines = submitter.clone();
             async move {
                 if !STOP.load(Ordering::Acquire) {
-                    let order: RequestOrder = req.body_json().await?;
+                    let order = req.body_json::<RequestOrder>().await?.with_trader_id(auth::get_trader_id(&req)?);
+                    let engine = match engines.get(&order.get_card()) {
+                        Some(engine) => engine,
+                        None => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, format!("no matching engine for card {:?}", order.get_card()), String::from("{}"));
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    // the scheduler of the card must not stay locked while waiting for the acknowledgement
+                    let validated = {
+                        let scheduler = match engine.lock() {
+                            Ok(scheduler) => scheduler,
+                            Err(e) => return get_unavailable_response(e, String::from("{}")),
+                        };
+                        scheduler.validate(&order).map_err(|reason| (reason, get_rejection_msg(&scheduler, &order, &reason)))
+                    };
+                    // a stored uuid is looked up through the pool, never on the connection matching the card
+                    let validated = match validated {
+                        Ok(_) => match engines.get_pool().read(move |db| Validator::check_uuid(&order, db)).await {
+                            Ok(checked) => checked.map_err(|reason| (reason, format!("the order with uuid: {} has been received", order.get_uuid()))),
+                            Err(e) => return get_unavailable_response(e, String::from("{}")),
+                        },
+                        Err(rejected) => Err(rejected),
+                    };
+
+                    let pushed = match validated {
+                        Ok(_) => engines.push(order),
+                        Err((reason, msg)) => {
+                            let mut res = Response::new(StatusCode::UnprocessableEntity);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}")).with_reason(reason);
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    let receiver = match pushed {
+                        Ok(receiver) => receiver,
+                        Err(QueueError::TooManyOpenOrders) => {
+                            let msg = format!("trader {} reaches the limit of {} open orders", order.get_trade_id(), engines.get_max_open_orders());
+                            let mut res = Response::new(StatusCode::UnprocessableEntity);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}")).with_reason(RejectReason::TooManyOpenOrders);
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        },
+                        Err(QueueError::Duplicate) => {
+                            let msg = format!("the order with uuid: {} has been received", order.get_uuid());
+                            let mut res = Response::new(StatusCode::UnprocessableEntity);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}")).with_reason(RejectReason::DuplicateUuid);
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        },
+                        Err(e) => {
+                            // a full queue asks the trader to retry later on
+                            let depth = engine.get_queue().get_depth();
+                            let msg = format!("the order queue of {:?} is unavailable ({:?}, {}/{} queued), retry later", order.get_card(), e, depth.get_depth(), depth.get_capacity());
+                            let mut res = Response::new(StatusCode::ServiceUnavailable);
+                            res.insert_header("Retry-After", "1");
+                            let rsp = Rsp::<QueueDepth>::new(ProcessStatus::Failed, msg, depth);
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    match future::timeout(ack_timeout, receiver.recv()).await {
+                        Ok(Ok(ack)) => {
+                            let msg = format!("the order with uuid: {} processed: {:?}", order.get_uuid(), ack.get_result());
+                            let mut res = match ack.get_result() {
+                                ProcessResult::TxRejected(_) => Response::new(StatusCode::UnprocessableEntity),
+                                ProcessResult::TxBoardUpdateFail | ProcessResult::UnknownCard => Response::new(StatusCode::InternalServerError),
+                                ProcessResult::TxStoreFailed => {
+                                    // nothing of the order is kept, so it could be sent again
+                                    let mut res = Response::new(StatusCode::ServiceUnavailable);
+                                    res.insert_header("Retry-After", "1");
+                                    res
+                                },
+                                _ => Response::new(StatusCode::Ok),
+                            };
+                            let mut rsp = match ack.get_status() {
+                                Some(_) => Rsp::<OrderAck>::new(ProcessStatus::Success, msg, ack.clone()),
+                                None => Rsp::<OrderAck>::new(ProcessStatus::Failed, msg, ack.clone()),
+                            };
+                            if let ProcessResult::TxRejected(reason) = ack.get_result() {
+                                rsp = rsp.with_reason(*reason);
+                            }
+                            res.set_body(Body::from_json(&rsp)?);
+                            Ok(res)
+                        },
+                        _ => {
+                            // still queued, its status could be queried later on
+                            let msg = format!("the order with uuid: {} is queued but not processed within {} ms", order.get_uuid(), ack_timeout.as_millis());
+                            let mut res = Response::new(StatusCode::Accepted);
+                            let rsp = Rsp::<RequestOrder>::new(ProcessStatus::Success, msg, order);
+                            res.set_body(Body::from_json(&rsp)?);
+                            Ok(res)
+                        }
+                    }
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), String::from("[{}]"));
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/pokemon/card/:uuid")
+        .with(auth.clone())
+        .delete(move |req: Request<()>| {
+            let engines = canceller.clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    let uuid = match req.param("uuid").map(Uuid::parse_str) {
+                        Ok(Ok(uuid)) => uuid,
+                        _ => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Invalid uuid"), String::from("{}"));
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    let trader_id = auth::get_trader_id(&req)?;
+                    let result = match engines.locate(&uuid).await {
+                        Ok(Some(engine)) => match engine.lock() {
+                            Ok(mut scheduler) => scheduler.cancel(&uuid, &trader_id),
+                            Err(e) => return get_unavailable_response(e, String::from("{}")),
+                        },
+                        Ok(None) => ProcessResult::TxNotFound,
+                        Err(e) => return get_unavailable_response(e, String::from("{}")),
+                    };
+                    let (status, msg) = match result {
+                        ProcessResult::TxCancelled => (StatusCode::Ok, format!("the order with uuid: {} cancelled", uuid)),
+                        ProcessResult::TxNotFound => (StatusCode::NotFound, format!("the order with uuid: {} not found", uuid)),
+                        ProcessResult::TxNotOwner => (StatusCode::Forbidden, format!("the order with uuid: {} does not belong to trader {}", uuid, trader_id)),
+                        ProcessResult::TxNotCancellable => (StatusCode::Conflict, format!("the order with uuid: {} is no longer queued and cannot be cancelled", uuid)),
+                        ProcessResult::TxStoreFailed => (StatusCode::ServiceUnavailable, format!("the cancellation of the order with uuid: {} could not be stored, retry later", uuid)),
+                        _ => (StatusCode::InternalServerError, format!("failed to cancel the order with uuid: {}", uuid)),
+                    };
+
+                    let mut res = Response::new(status);
+                    if result == ProcessResult::TxCancelled {
+                        let rsp = Rsp::<Status>::new(ProcessStatus::Success, msg, Status::new(uuid, OrderStatus::Cancelled));
+                        res.set_body(Body::from_json(&rsp)?);
+                    } else {
+                        let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}"));
+                        res.set_body(Body::from_json(&rsp)?);
+                    }
+                    Ok(res)
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), String::from("{}"));
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        })
+        .patch(move |mut req: Request<()>| {
+            let engines = amender.clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    let uuid = match req.param("uuid").map(Uuid::parse_str) {
+                        Ok(Ok(uuid)) => uuid,
+                        _ => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Invalid uuid"), String::from("{}"));
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    let amend = req.body_json::<AmendOrder>().await?.with_trader_id(auth::get_trader_id(&req)?);
+                    let engine = match engines.locate(&uuid).await {
+                        Ok(Some(engine)) => engine,
+                        Err(e) => return get_unavailable_response(e, String::from("{}")),
+                        Ok(None) => {
+                            let mut res = Response::new(StatusCode::NotFound);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, format!("the order with uuid: {} not found", uuid), String::from("{}"));
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+                    let mut scheduler = match engine.lock() {
+                        Ok(scheduler) => scheduler,
+                        Err(e) => return get_unavailable_response(e, String::from("{}")),
+                    };
+                    let (result, amended) = scheduler.amend_order(&uuid, &amend);
+                    let (status, msg) = match result {
+                        ProcessResult::TxAmended => (StatusCode::Ok, format!("the order with uuid: {} amended", uuid)),
+                        ProcessResult::TxNotFound => (StatusCode::NotFound, format!("the order with uuid: {} not found", uuid)),
+                        ProcessResult::TxNotOwner => (StatusCode::Forbidden, format!("the order with uuid: {} does not belong to trader {}", uuid, amend.get_trade_id())),
+                        ProcessResult::TxNotAmendable => (StatusCode::Conflict, format!("the order with uuid: {} is no longer queued and cannot be amended", uuid)),
+                        ProcessResult::TxAmendRejected => (StatusCode::UnprocessableEntity, format!("the amendment of the order with uuid: {} is invalid or crosses the opposite side", uuid)),
+                        ProcessResult::TxStoreFailed => (StatusCode::ServiceUnavailable, format!("the amendment of the order with uuid: {} could not be stored, retry later", uuid)),
+                        ProcessResult::TxRejected(reason) => match amended {
+                            // the stored order at the amended price and volume, as it would have rested
+                            Some(amended) => (StatusCode::UnprocessableEntity, get_rejection_msg(&scheduler, &amended, &reason)),
+                            None => (StatusCode::UnprocessableEntity, format!("the amendment of the order with uuid: {} is rejected", uuid)),
+                        },
+                        _ => (StatusCode::InternalServerError, format!("failed to amend the order with uuid: {}", uuid)),
+                    };
+
+                    let mut res = Response::new(status);
+                    if result == ProcessResult::TxAmended {
+                        let rsp = Rsp::<AmendOrder>::new(ProcessStatus::Success, msg, amend);
+                        res.set_body(Body::from_json(&rsp)?);
+                    } else if let ProcessResult::TxRejected(reason) = result {
+                        let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}")).with_reason(reason);
+                        res.set_body(Body::from_json(&rsp)?);
+                    } else {
+                        let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}"));
+                        res.set_body(Body::from_json(&rsp)?);
+                    }
+                    Ok(res)
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), String::from("{}"));
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/pokemon/queue")
+        .get(move |_req: Request<()>| {
+            let engines = depth_checker.clone();
+            async move {
+                let depth = engines.get_depth();
+                let msg = format!("{} of {} orders queued for matching", depth.get_depth(), depth.get_capacity());
+                let mut res = Response::new(StatusCode::Ok);
+                let rsp = Rsp::<QueueDepth>::new(ProcessStatus::Success, msg, depth);
+                res.set_body(Body::from_json(&rsp)?);
+                Ok(res)
+            }
+        });
+
+    server
+        .at("/api/pokemon/ws")
+        .with(auth.optional())
+        .get(move |req: Request<()>| {
+            let feed = subscriber.get_feed().clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    let identity = auth::get_identity(&req);
+                    websocket::upgrade(req, feed, identity).await
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), String::from("{}"));
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/pokemon/stream/trades/:card")
+        .get(tide::sse::endpoint(move |req: Request<()>, sender| sse::stream_trades(req, sender, trade_streamer.clone())));
+
+    server
+        .at("/api/pokemon/stream/orders/:id")
+        .with(auth.streams())
+        .get(tide::sse::endpoint(move |req: Request<()>, sender| sse::stream_orders(req, sender, order_streamer.clone())));
+
+    server
+        .at("/api/pokemon/book/:card")
+        .get(move |req: Request<()>| {
+            let engines = book_checker.clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    let (card, param) = match (req.param("card").ok().and_then(get_card), req.query::<BookParam>()) {
+                        (Some(card), Ok(param)) => (card, param),
+                        _ => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Invalid card or depth"), String::from("{}"));
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    let book = match engines.get(&card).map(|engine| engine.lock()) {
+                        Some(Ok(scheduler)) => scheduler.get_book(&card, param.get_depth()),
+                        Some(Err(e)) => return get_unavailable_response(e, String::from("{}")),
+                        None => None,
+                    };
+                    let mut res = Response::new(StatusCode::Ok);
+                    match book {
+                        Some(book) => {
+                            let msg = format!("view the best {} levels of the order book on card - {:?}", param.get_depth(), card);
+                            let rsp = Rsp::<OrderBook>::new(ProcessStatus::Success, msg, book);
+                            res.set_body(Body::from_json(&rsp)?);
+                        },
+                        None => {
+                            let msg = format!("the order book on card - {:?} not found", card);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}"));
+                            res.set_status(StatusCode::NotFound);
+                            res.set_body(Body::from_json(&rsp)?);
+                        }
+                    }
+                    Ok(res)
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), String::from("{}"));
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/pokemon/candles/:card")
+        .get(move |req: Request<()>| {
+            let engines = candle_checker.clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    let (card, param) = match (req.param("card").ok().and_then(get_card), req.query::<CandleParam>()) {
+                        (Some(card), Ok(param)) if param.get_from() < param.get_to() => (card, param),
+                        _ => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Invalid card, interval or time range"), String::from("{}"));
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    let candles = match engines.get_candles(&card, &param).await {
+                        Ok(candles) => candles,
+                        Err(e) => return get_unavailable_response(e, Vec::<Candle>::new()),
+                    };
+                    let msg = format!("view {} candles of {:?} on card - {:?}", candles.len(), param.get_interval(), card);
+                    let rsp = Rsp::<Vec<Candle>>::new(ProcessStatus::Success, msg, candles);
                     let mut res = Response::new(StatusCode::Ok);
-                    let rsp = Rsp::<RequestOrder>::new(ProcessStatus::Success, String::from("Processed"), order);
-                    handler.lock().unwrap().order_queue.push_back(order);
                     res.set_body(Body::from_json(&rsp)?);
                     Ok(res)
                 } else {
                     let mut res = Response::new(StatusCode::BadGateway);
-                    let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), String::from("[{}]"));
+                    let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), String::from("{}"));
                     res.set_body(Body::from_json(&rsp)?);
                     Ok(res)
                 }
@@ -141,24 +704,23 @@ async fn main() -> tide::Result<()> {
     server
         .at("/api/pokemon/trade/:card")
         .get(move |req: Request<()>| {
-            let handler = Arc::clone(&trade_checker);
+            let engines = trade_checker.clone();
             async move {
                 if !STOP.load(Ordering::Acquire) {
-                    let card = req.param("card").unwrap_or("None");
-                    let param = match card {
-                        "Pikachu" => Card::Pikachu,
-                        "Bulbasaur" => Card::Bulbasaur,
-                        "Charmander" => Card::Charmander,
-                        "Squirtle" => Card::Squirtle,
-                        _ => Card::Pikachu,
-                    };
+                    let param = req.param("card").ok().and_then(get_card).unwrap_or(Card::Pikachu);
 
                     let mut data = String::from("");
-                    if let Some(list) = handler.lock().unwrap().get_latest_trades(&param) {
-                        if list.len() > 0 {
-                            for elem in list {
-                                data += &elem.to_str();
-                                data.push(',');
+                    if let Some(engine) = engines.get(&param) {
+                        let scheduler = match engine.lock() {
+                            Ok(scheduler) => scheduler,
+                            Err(e) => return get_unavailable_response(e, String::from("[]")),
+                        };
+                        if let Some(list) = scheduler.get_latest_trades(&param) {
+                            if list.len() > 0 {
+                                for elem in list {
+                                    data += &elem.to_str();
+                                    data.push(',');
+                                }
                             }
                         }
                     }
@@ -179,14 +741,15 @@ async fn main() -> tide::Result<()> {
 
     server
         .at("/api/pokemon/order/:id")
+        .with(auth.clone())
         .get(move |req: Request<()>| {
-            let handler = Arc::clone(&order_checker);
+            let engines = order_checker.clone();
             async move {
                 if !STOP.load(Ordering::Acquire) {
-                    let id: i32 = 0;
+                    let id: i32;
                     if let Ok(s) = req.param("id") {
-                        if let Ok(id) = s.parse::<i32>() {
-
+                        if let Ok(parsed) = s.parse::<i32>() {
+                            id = parsed;
                         } else {
                             let mut res = Response::new(StatusCode::BadRequest);
                             let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Digit Parsed Error"), String::from("[]"));
@@ -201,7 +764,11 @@ async fn main() -> tide::Result<()> {
                     }
 
                     let mut data = String::from("");
-                    if let Some(stats) = handler.lock().unwrap().get_latest_orders(&id) {             
+                    let latest = match engines.get_latest_orders(&id) {
+                        Ok(latest) => latest,
+                        Err(e) => return get_unavailable_response(e, String::from("[]")),
+                    };
+                    if let Some(stats) = latest {             
                         if stats.len() > 0 {
                             for elem in stats {
                                 data.push_str(&elem.to_str());
@@ -226,17 +793,25 @@ async fn main() -> tide::Result<()> {
 
     server
         .at("/api/pokemon/trade/history")
+        .with(auth.clone())
+        .with(query_limit.clone())
         .get(move |req: Request<()>|{
-            let handler = Arc::clone(&trade_history);
+            let pool = trade_history.get_pool().clone();
             async move {
                 if !STOP.load(Ordering::Acquire) {
 
                     match req.query::<HistoryParam>() {
                         Ok(param) => {
+                            let (id, date) = (*param.get_id(), param.get_date().to_string());
+                            let history = match pool.read(move |db| db.get_trade_history(&id, &date)).await {
+                                Ok(history) => history,
+                                Err(e) => return get_unavailable_response(e, String::from("[{}]")),
+                            };
+
                             let mut res = Response::new(StatusCode::Ok);
                             let msg = format!("view the trade history of trader {} on {}", param.get_id(), param.get_date());
 
-                            if let Some(history) = handler.lock().unwrap().get_trade_record(param.get_id(), param.get_date()) {
+                            if !history.is_empty() {
                                 let mut data = String::from("");
                                 for elem in history {
                                     data.push_str(&elem.to_str());
@@ -270,17 +845,25 @@ async fn main() -> tide::Result<()> {
     
     server
         .at("/api/pokemon/request/history")
+        .with(auth.clone())
+        .with(query_limit.clone())
         .get(move |req: Request<()>|{
-            let handler = Arc::clone(&order_history);
+            let pool = order_history.get_pool().clone();
             async move {
                 if !STOP.load(Ordering::Acquire) {
 
                     match req.query::<HistoryParam>() {
                         Ok(param) => {
+                            let (id, date) = (*param.get_id(), param.get_date().to_string());
+                            let history = match pool.read(move |db| db.get_request_history(&id, &date)).await {
+                                Ok(history) => history,
+                                Err(e) => return get_unavailable_response(e, String::from("[{}]")),
+                            };
+
                             let mut res = Response::new(StatusCode::Ok);
                             let msg = format!("view the request history of trader {} on {}", param.get_id(), param.get_date());
 
-                            if let Some(history) = handler.lock().unwrap().get_request_record(param.get_id(), param.get_date()) {
+                            if !history.is_empty() {
                                 let mut data = String::from("");
                                 for elem in history {
                                     data.push_str(&elem.to_str());
@@ -314,20 +897,34 @@ async fn main() -> tide::Result<()> {
 
     server
         .at("/api/pokemon/order/status")
+        .with(auth.clone())
         .get(move |req: Request<()>|{
-            let handler = Arc::clone(&status_checker);
+            let pool = status_checker.get_pool().clone();
             async move {
                 if !STOP.load(Ordering::Acquire) {
 
                     match req.query::<StatusParam>() {
                         Ok(param) => {
+                            // the status of an order is only served to its owner
+                            let trader_id = auth::get_trader_id(&req)?;
+                            let uuid = *param.get_uuid();
+                            let (owner, mut record): (Option<i32>, Vec<Status>) = match pool.read(move |db| (db.get_request(&uuid).map(|req| req.get_trade_id()), db.get_status_history(&uuid))).await {
+                                Ok(read) => read,
+                                Err(e) => return get_unavailable_response(e, String::from("{}")),
+                            };
+                            if owner.is_some_and(|owner| owner != trader_id) {
+                                let mut res = Response::new(StatusCode::Forbidden);
+                                let msg = format!("the order with uuid: {} does not belong to trader {}", param.get_uuid(), trader_id);
+                                let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}"));
+                                res.set_body(Body::from_json(&rsp)?);
+                                return Ok(res)
+                            }
+
                             let mut res = Response::new(StatusCode::Ok);
-                            if let Some(mut record) = handler.lock().unwrap().get_status_record(param.get_uuid()) {
-                                if let Some(elem) = record.pop_back() {
-                                    let msg = format!("view the status of the order with uuid: {}", param.get_uuid());
-                                    let rsp = Rsp::<Status>::new(ProcessStatus::Success, msg, elem);
-                                    res.set_body(Body::from_json(&rsp)?);
-                                }
+                            if let Some(elem) = record.pop() {
+                                let msg = format!("view the status of the order with uuid: {}", param.get_uuid());
+                                let rsp = Rsp::<Status>::new(ProcessStatus::Success, msg, elem);
+                                res.set_body(Body::from_json(&rsp)?);
                             } else {
                                 let msg = format!("the status of the order with uuid: {} not found", param.get_uuid());
                                 let rsp = Rsp::<String>::new(ProcessStatus::Success, msg, String::from("{}"));
@@ -352,6 +949,288 @@ async fn main() -> tide::Result<()> {
                 }
             }
         });        
+    // v2 serves serde-serialised lists instead of the strings built by to_str
+    server
+        .at("/api/v2/pokemon/trade/:card")
+        .get(move |req: Request<()>| {
+            let engines = trade_checker_v2.clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    let card = match req.param("card").ok().and_then(get_card) {
+                        Some(card) => card,
+                        None => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<Vec<Trade>>::new(ProcessStatus::Failed, String::from("Invalid card"), Vec::new());
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    let trades: Vec<Trade> = match engines.get(&card).map(|engine| engine.lock()) {
+                        Some(Ok(scheduler)) => scheduler.get_latest_trades(&card).map(|list| list.iter().cloned().collect()).unwrap_or_default(),
+                        Some(Err(e)) => return get_unavailable_response(e, Vec::<Trade>::new()),
+                        None => Vec::new(),
+                    };
+                    let msg = format!("view the latest 50 trades on card - {:?}", card);
+                    let mut res = Response::new(StatusCode::Ok);
+                    let rsp = Rsp::<Vec<Trade>>::new(ProcessStatus::Success, msg, trades);
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<Vec<Trade>>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), Vec::new());
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/v2/pokemon/order/:id")
+        .with(auth.clone())
+        .get(move |req: Request<()>| {
+            let engines = order_checker_v2.clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    let id = match req.param("id").map(|id| id.parse::<i32>()) {
+                        Ok(Ok(id)) => id,
+                        _ => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<Vec<Stats>>::new(ProcessStatus::Failed, String::from("Invalid trader id"), Vec::new());
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    let stats: Vec<Stats> = match engines.get_latest_orders(&id) {
+                        Ok(latest) => latest.map(|list| list.into_iter().collect()).unwrap_or_default(),
+                        Err(e) => return get_unavailable_response(e, Vec::<Stats>::new()),
+                    };
+                    let msg = format!("view the status of latest 50 orders of trader {}", id);
+                    let mut res = Response::new(StatusCode::Ok);
+                    let rsp = Rsp::<Vec<Stats>>::new(ProcessStatus::Success, msg, stats);
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<Vec<Stats>>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), Vec::new());
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/v2/pokemon/trade/history")
+        .with(auth.clone())
+        .with(query_limit.clone())
+        .get(move |req: Request<()>| {
+            let pool = trade_history_v2.get_pool().clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    match req.query::<HistoryParam>() {
+                        Ok(param) => {
+                            let (id, date) = (*param.get_id(), param.get_date().to_string());
+                            let history: Vec<TradeHistory> = match pool.read(move |db| db.get_trade_history(&id, &date)).await {
+                                Ok(history) => history,
+                                Err(e) => return get_unavailable_response(e, Vec::<TradeHistory>::new()),
+                            };
+                            let msg = format!("view the trade history of trader {} on {}", param.get_id(), param.get_date());
+                            let mut res = Response::new(StatusCode::Ok);
+                            let rsp = Rsp::<Vec<TradeHistory>>::new(ProcessStatus::Success, msg, history);
+                            res.set_body(Body::from_json(&rsp)?);
+                            Ok(res)
+                        },
+                        Err(e) => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<Vec<TradeHistory>>::new(ProcessStatus::Failed, e.to_string(), Vec::new());
+                            res.set_body(Body::from_json(&rsp)?);
+                            Ok(res)
+                        }
+                    }
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<Vec<TradeHistory>>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), Vec::new());
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/v2/pokemon/request/history")
+        .with(auth.clone())
+        .with(query_limit.clone())
+        .get(move |req: Request<()>| {
+            let pool = order_history_v2.get_pool().clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    match req.query::<HistoryParam>() {
+                        Ok(param) => {
+                            let (id, date) = (*param.get_id(), param.get_date().to_string());
+                            let history: Vec<RequestOrder> = match pool.read(move |db| db.get_request_history(&id, &date)).await {
+                                Ok(history) => history,
+                                Err(e) => return get_unavailable_response(e, Vec::<RequestOrder>::new()),
+                            };
+                            let msg = format!("view the request history of trader {} on {}", param.get_id(), param.get_date());
+                            let mut res = Response::new(StatusCode::Ok);
+                            let rsp = Rsp::<Vec<RequestOrder>>::new(ProcessStatus::Success, msg, history);
+                            res.set_body(Body::from_json(&rsp)?);
+                            Ok(res)
+                        },
+                        Err(e) => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<Vec<RequestOrder>>::new(ProcessStatus::Failed, e.to_string(), Vec::new());
+                            res.set_body(Body::from_json(&rsp)?);
+                            Ok(res)
+                        }
+                    }
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<Vec<RequestOrder>>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), Vec::new());
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/pokemon/portfolio/:id")
+        .with(auth.clone())
+        .with(query_limit.clone())
+        .get(move |req: Request<()>| {
+            let engines = portfolio_checker.clone();
+            async move {
+                if !STOP.load(Ordering::Acquire) {
+                    let id = match req.param("id").map(|id| id.parse::<i32>()) {
+                        Ok(Ok(id)) => id,
+                        _ => {
+                            let mut res = Response::new(StatusCode::BadRequest);
+                            let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Invalid trader id"), String::from("{}"));
+                            res.set_body(Body::from_json(&rsp)?);
+                            return Ok(res)
+                        }
+                    };
+
+                    let portfolio = match engines.get_portfolio(&id).await {
+                        Ok(portfolio) => portfolio,
+                        Err(e) => return get_unavailable_response(e, String::from("{}")),
+                    };
+                    let msg = format!("view the portfolio of trader {}", id);
+                    let mut res = Response::new(StatusCode::Ok);
+                    let rsp = Rsp::<Portfolio>::new(ProcessStatus::Success, msg, portfolio);
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                } else {
+                    let mut res = Response::new(StatusCode::BadGateway);
+                    let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Server shutting down. Stop serving requests"), String::from("{}"));
+                    res.set_body(Body::from_json(&rsp)?);
+                    Ok(res)
+                }
+            }
+        });
+
+    server
+        .at("/api/admin/account/:id/cash")
+        .with(admin.clone())
+        .post(move |mut req: Request<()>| {
+            let engines = cash_booker.clone();
+            async move {
+                let id = match req.param("id").map(|id| id.parse::<i32>()) {
+                    Ok(Ok(id)) => id,
+                    _ => {
+                        let mut res = Response::new(StatusCode::BadRequest);
+                        let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Invalid trader id"), String::from("{}"));
+                        res.set_body(Body::from_json(&rsp)?);
+                        return Ok(res)
+                    }
+                };
+                let transfer = req.body_json::<CashTransfer>().await?;
+                get_booking_response(&engines, LedgerEntry::for_cash(id, auth::get_trader_id(&req)?, &transfer)).await
+            }
+        });
+
+    server
+        .at("/api/admin/account/:id/cards")
+        .with(admin.clone())
+        .post(move |mut req: Request<()>| {
+            let engines = card_booker.clone();
+            async move {
+                let id = match req.param("id").map(|id| id.parse::<i32>()) {
+                    Ok(Ok(id)) => id,
+                    _ => {
+                        let mut res = Response::new(StatusCode::BadRequest);
+                        let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("Invalid trader id"), String::from("{}"));
+                        res.set_body(Body::from_json(&rsp)?);
+                        return Ok(res)
+                    }
+                };
+                let transfer = req.body_json::<CardTransfer>().await?;
+                get_booking_response(&engines, LedgerEntry::for_cards(id, auth::get_trader_id(&req)?, &transfer)).await
+            }
+        });
+
+    server
+        .at("/api/admin/account/:id/ledger")
+        .with(admin.clone())
+        .get(move |req: Request<()>| {
+            let engines = ledger_checker.clone();
+            async move {
+                let id = match req.param("id").map(|id| id.parse::<i32>()) {
+                    Ok(Ok(id)) => id,
+                    _ => {
+                        let mut res = Response::new(StatusCode::BadRequest);
+                        let rsp = Rsp::<Vec<LedgerEntry>>::new(ProcessStatus::Failed, String::from("Invalid trader id"), Vec::new());
+                        res.set_body(Body::from_json(&rsp)?);
+                        return Ok(res)
+                    }
+                };
+
+                let accounts = engines.get_accounts().clone();
+                let entries = match engines.get_pool().read(move |db| accounts.get_ledger(db, &id)).await {
+                    Ok(entries) => entries,
+                    Err(e) => return get_unavailable_response(e, Vec::<LedgerEntry>::new()),
+                };
+                let msg = format!("view the ledger of trader {}", id);
+                let mut res = Response::new(StatusCode::Ok);
+                let rsp = Rsp::<Vec<LedgerEntry>>::new(ProcessStatus::Success, msg, entries);
+                res.set_body(Body::from_json(&rsp)?);
+                Ok(res)
+            }
+        });
+
+    server
+        .at("/api/admin/reconcile")
+        .with(admin)
+        .get(move |_req: Request<()>| {
+            let engines = reconciler.clone();
+            async move {
+                let accounts = engines.get_accounts().clone();
+                let differences = match engines.get_pool().read(move |db| accounts.reconcile(db)).await {
+                    Ok(differences) => differences,
+                    Err(e) => return get_unavailable_response(e, Vec::<Reconciliation>::new()),
+                };
+                let msg = format!("{} balances differ from the ledger and the trades", differences.len());
+                let mut res = Response::new(StatusCode::Ok);
+                let rsp = Rsp::<Vec<Reconciliation>>::new(ProcessStatus::Success, msg, differences);
+                res.set_body(Body::from_json(&rsp)?);
+                Ok(res)
+            }
+        });
+
+    // the document is generated once from the routes above and their types
+    let spec = openapi::document();
+    server
+        .at("/api/openapi.json")
+        .get(move |_req: Request<()>| {
+            let spec = spec.clone();
+            async move {
+                let mut res = Response::new(StatusCode::Ok);
+                res.set_body(Body::from_json(&spec)?);
+                Ok(res)
+            }
+        });
+
     server.listen(srv).await?;
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,289 @@
+use crate::data_type::{Card, Side};
+use crate::status_board::Stats;
+use crate::trade_board::Trade;
+use crate::tx_board::BookLevel;
+use async_std::channel;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tide::prelude::{Deserialize, Serialize};
+
+// messages a slow subscriber could fall behind before missing some
+const SUBSCRIBER_BACKLOG: usize = 1024;
+
+// sequence numbers of a topic a run of the server could give before reaching the next run
+const RUN_SEQS: u64 = 1_000_000_000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topic {
+    Card(Card),
+    Trader(i32),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MarketEvent {
+    // a trade print of a card
+    Trade { card: Card, trade: Trade },
+    // the latest state of a price level, a level with zero vol is emptied
+    Book { card: Card, side: Side, level: BookLevel },
+    // a status transition of an order of a trader
+    Order { trader_id: i32, stats: Stats },
+}
+
+impl MarketEvent {
+    pub fn get_topic(&self) -> Topic {
+        match self {
+            MarketEvent::Trade { card, .. } | MarketEvent::Book { card, .. } => Topic::Card(*card),
+            MarketEvent::Order { trader_id, .. } => Topic::Trader(*trader_id),
+        }
+    }
+}
+
+/**
+ * An event along with its sequence number. Sequence numbers are counted
+ * per topic, so a subscriber detects a gap once a number is skipped
+ **/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketMessage {
+    seq: u64,
+    #[serde(flatten)]
+    event: MarketEvent,
+}
+
+impl MarketMessage {
+    pub fn get_seq(&self) -> &u64 {
+        &self.seq
+    }
+
+    pub fn get_event(&self) -> &MarketEvent {
+        &self.event
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Subscribe,
+    Unsubscribe,
+}
+
+// e.g. {"action": "subscribe", "card": "Pikachu"} or {"action": "unsubscribe", "trader_id": 7}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subscription {
+    action: Action,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    card: Option<Card>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trader_id: Option<i32>,
+}
+
+impl Subscription {
+    pub fn get_action(&self) -> &Action {
+        &self.action
+    }
+
+    // exactly one of card and trader_id is given
+    pub fn get_topic(&self) -> Option<Topic> {
+        match (self.card, self.trader_id) {
+            (Some(card), None) => Some(Topic::Card(card)),
+            (None, Some(id)) => Some(Topic::Trader(id)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    topics: HashSet<Topic>,
+    sender: channel::Sender<MarketMessage>,
+}
+
+#[derive(Debug, Default)]
+struct Subscribers {
+    next_id: u64,
+    // sequence number every topic starts from in this run
+    base: u64,
+    seqs: HashMap<Topic, u64>,
+    subscribers: HashMap<u64, Subscriber>,
+}
+
+/**
+ * Hub of the real-time market data. Boards publish their changes here
+ * and each connected subscriber receives the events of its topics
+ * through a channel of its own
+ **/
+#[derive(Debug, Clone, Default)]
+pub struct MarketData {
+    inner: Arc<Mutex<Subscribers>>,
+}
+
+impl MarketData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connect(&self) -> (u64, channel::Receiver<MarketMessage>) {
+        let mut inner = self.inner.lock().unwrap();
+        let (sender, receiver) = channel::bounded(SUBSCRIBER_BACKLOG);
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.subscribers.insert(id, Subscriber { topics: HashSet::new(), sender });
+        (id, receiver)
+    }
+
+    // the receiver of the subscriber is closed as well
+    pub fn disconnect(&self, id: &u64) {
+        self.inner.lock().unwrap().subscribers.remove(id);
+    }
+
+    pub fn subscribe(&self, id: &u64, topic: Topic) -> bool {
+        match self.inner.lock().unwrap().subscribers.get_mut(id) {
+            Some(subscriber) => subscriber.topics.insert(topic),
+            None => false,
+        }
+    }
+
+    pub fn unsubscribe(&self, id: &u64, topic: &Topic) -> bool {
+        match self.inner.lock().unwrap().subscribers.get_mut(id) {
+            Some(subscriber) => subscriber.topics.remove(topic),
+            None => false,
+        }
+    }
+
+    /**
+     * Number the events of this run past those of the former runs, given
+     * the number of the run kept by the store. A client resuming with the
+     * sequence number of an event from before a restart is then served
+     * every event of the run instead of the ones past its number
+     **/
+    pub fn start_run(&self, run: i64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.base = run.max(0) as u64 * RUN_SEQS;
+        inner.seqs.clear();
+    }
+
+    // sequence number of the latest event of the topic
+    pub fn get_seq(&self, topic: &Topic) -> u64 {
+        let inner = self.inner.lock().unwrap();
+        inner.seqs.get(topic).copied().unwrap_or(inner.base)
+    }
+
+    // the sequence number given to the event is returned
+    pub fn publish(&self, event: MarketEvent) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        let (topic, base) = (event.get_topic(), inner.base);
+        let seq = inner.seqs.entry(topic).or_insert(base);
+        *seq += 1;
+
+        let seq = *seq;
+        let msg = MarketMessage { seq, event };
+        for subscriber in inner.subscribers.values() {
+            if subscriber.topics.contains(&topic) {
+                // a lagging subscriber misses the message and sees the gap by seq
+                let _ = subscriber.sender.try_send(msg.clone());
+            }
+        }
+        seq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_type::{Card, OrderStatus, RequestOrder, Side};
+    use crate::market_data::{MarketData, MarketEvent, Subscription, Topic};
+    use crate::scheduler::Scheduler;
+    use crate::status_board::Stats;
+    use crate::store::{MemoryStore, Store};
+    use crate::trade_board::Trade;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    #[test]
+    fn given_subscribers_when_events_published_then_received_by_topic_with_sequences() {
+        let feed = MarketData::new();
+        let (card_sub, card_receiver) = feed.connect();
+        let (trader_sub, trader_receiver) = feed.connect();
+        assert!(feed.subscribe(&card_sub, Topic::Card(Card::Pikachu)));
+        assert!(!feed.subscribe(&card_sub, Topic::Card(Card::Pikachu)));
+        assert!(feed.subscribe(&trader_sub, Topic::Trader(3)));
+
+        let trade = MarketEvent::Trade { card: Card::Pikachu, trade: Trade::new(Utc::now(), 3, 4, 2.50, 1) };
+        let stats = Stats::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.50, 1, Card::Pikachu, OrderStatus::Filled);
+        feed.publish(trade.clone());
+        feed.publish(MarketEvent::Trade { card: Card::Squirtle, trade: Trade::new(Utc::now(), 3, 4, 2.50, 1) });
+        feed.publish(MarketEvent::Order { trader_id: 3, stats });
+        feed.publish(trade.clone());
+
+        let card_msgs: Vec<(u64, MarketEvent)> = std::iter::from_fn(|| card_receiver.try_recv().ok()).map(|msg| (*msg.get_seq(), msg.get_event().clone())).collect();
+        assert_eq!(vec![(1, trade.clone()), (2, trade)], card_msgs);
+        assert_eq!(1, trader_receiver.try_recv().map(|msg| *msg.get_seq()).unwrap());
+        assert_eq!((2, 1, 0), (feed.get_seq(&Topic::Card(Card::Pikachu)), feed.get_seq(&Topic::Card(Card::Squirtle)), feed.get_seq(&Topic::Trader(4))));
+
+        assert!(feed.unsubscribe(&card_sub, &Topic::Card(Card::Pikachu)));
+        feed.publish(MarketEvent::Trade { card: Card::Pikachu, trade: Trade::new(Utc::now(), 3, 4, 2.50, 1) });
+        assert!(card_receiver.try_recv().is_err());
+
+        feed.disconnect(&trader_sub);
+        assert!(trader_receiver.is_closed());
+    }
+
+    #[test]
+    fn given_a_restart_when_events_published_then_numbered_past_the_former_run() {
+        let tables = MemoryStore::new();
+        let stats = Stats::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.50, 1, Card::Pikachu, OrderStatus::Confirmed);
+        let before = MarketData::new();
+        before.start_run(tables.clone().insert_run_table());
+        let last = before.publish(MarketEvent::Order { trader_id: 3, stats: stats.clone() });
+
+        // a client resuming from the last event before the restart is served the whole run after it
+        let after = MarketData::new();
+        after.start_run(tables.clone().insert_run_table());
+        assert!(after.get_seq(&Topic::Trader(3)) >= last);
+        assert!(after.publish(MarketEvent::Order { trader_id: 3, stats }) > last);
+        assert!(after.get_seq(&Topic::Card(Card::Pikachu)) > before.get_seq(&Topic::Card(Card::Pikachu)));
+    }
+
+    #[test]
+    fn given_subscription_messages_when_parsed_then_topics_resolved() {
+        let card: Subscription = serde_json::from_str(r#"{"action": "subscribe", "card": "Charmander"}"#).unwrap();
+        let trader: Subscription = serde_json::from_str(r#"{"action": "unsubscribe", "trader_id": 7}"#).unwrap();
+        let both: Subscription = serde_json::from_str(r#"{"action": "subscribe", "card": "Charmander", "trader_id": 7}"#).unwrap();
+        assert_eq!(Some(Topic::Card(Card::Charmander)), card.get_topic());
+        assert_eq!(Some(Topic::Trader(7)), trader.get_topic());
+        assert_eq!(None, both.get_topic());
+    }
+
+    #[test]
+    fn given_a_scheduler_with_feed_when_orders_matched_then_trades_levels_and_statuses_published() {
+        let feed = MarketData::new();
+        let (id, receiver) = feed.connect();
+        feed.subscribe(&id, Topic::Card(Card::Charmander));
+        feed.subscribe(&id, Topic::Trader(71));
+
+        let mut scheduler = Scheduler::for_cards(&[Card::Charmander]);
+        scheduler.set_feed(feed.clone());
+        let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 8.15, 2, Card::Charmander, 71);
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 8.15, 1, Card::Charmander, 72);
+        scheduler.execute(&sell);
+        scheduler.execute(&buy);
+
+        let events: Vec<MarketEvent> = std::iter::from_fn(|| receiver.try_recv().ok()).map(|msg| msg.get_event().clone()).collect();
+        let statuses: Vec<OrderStatus> = events.iter().filter_map(|event| match event {
+            MarketEvent::Order { trader_id: 71, stats } => Some(stats.get_status().clone()),
+            _ => None,
+        }).collect();
+        let levels: Vec<(Side, i32, usize)> = events.iter().filter_map(|event| match event {
+            MarketEvent::Book { side, level, .. } => Some((*side, *level.get_vol(), *level.get_orders())),
+            _ => None,
+        }).collect();
+        let trades: Vec<(i32, i32)> = events.iter().filter_map(|event| match event {
+            MarketEvent::Trade { trade, .. } => Some((*trade.get_buy_side_id(), *trade.get_sell_side_id())),
+            _ => None,
+        }).collect();
+
+        assert_eq!(vec![OrderStatus::Confirmed, OrderStatus::PartiallyFilled], statuses);
+        assert_eq!(vec![(Side::Sell, 2, 1), (Side::Sell, 1, 1)], levels);
+        assert_eq!(vec![(72, 71)], trades);
+    }
+}
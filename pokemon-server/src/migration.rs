@@ -0,0 +1,177 @@
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::sqlite::SqliteStore;
+use chrono::{DateTime, Utc};
+use std::env;
+
+/**
+ * A numbered change of the schema, along with the SQL reverting it. The
+ * SQL lives in migrations/<backend>/<version>_<name>.<up|down>.sql and
+ * is built into the server, so a release always carries its schema
+ **/
+pub struct Migration {
+    version: i32,
+    name: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+impl Migration {
+    pub fn get_version(&self) -> &i32 {
+        &self.version
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name
+    }
+
+    pub fn get_up(&self) -> &str {
+        self.up
+    }
+
+    pub fn get_down(&self) -> &str {
+        self.down
+    }
+}
+
+// new migrations are appended with the next version, applied ones are never edited
+pub const POSTGRES: [Migration; 3] = [
+    Migration {
+        version: 1,
+        name: "create_tables",
+        up: include_str!("../migrations/postgres/0001_create_tables.up.sql"),
+        down: include_str!("../migrations/postgres/0001_create_tables.down.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "add_keys_and_indexes",
+        up: include_str!("../migrations/postgres/0002_add_keys_and_indexes.up.sql"),
+        down: include_str!("../migrations/postgres/0002_add_keys_and_indexes.down.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "create_run_table",
+        up: include_str!("../migrations/postgres/0003_create_run_table.up.sql"),
+        down: include_str!("../migrations/postgres/0003_create_run_table.down.sql"),
+    },
+];
+
+pub const SQLITE: [Migration; 3] = [
+    Migration {
+        version: 1,
+        name: "create_tables",
+        up: include_str!("../migrations/sqlite/0001_create_tables.up.sql"),
+        down: include_str!("../migrations/sqlite/0001_create_tables.down.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "add_keys_and_indexes",
+        up: include_str!("../migrations/sqlite/0002_add_keys_and_indexes.up.sql"),
+        down: include_str!("../migrations/sqlite/0002_add_keys_and_indexes.down.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "create_run_table",
+        up: include_str!("../migrations/sqlite/0003_create_run_table.up.sql"),
+        down: include_str!("../migrations/sqlite/0003_create_run_table.down.sql"),
+    },
+];
+
+/**
+ * Backend keeping the versions of its schema in schema_version. Applying
+ * or reverting a migration runs its SQL and records the version in one
+ * transaction, so a failed migration leaves the former version in place
+ **/
+pub trait Migrator {
+    // migrations of the backend in version order
+    fn get_migrations(&self) -> &'static [Migration];
+    // applied versions with their times in version order
+    fn get_applied(&mut self) -> Vec<(i32, DateTime<Utc>)>;
+    fn apply(&mut self, migration: &Migration);
+    fn revert(&mut self, migration: &Migration);
+}
+
+/**
+ * Apply every pending migration in version order, returning those applied
+ **/
+pub fn up(migrator: &mut dyn Migrator) -> Vec<&'static Migration> {
+    let applied: Vec<i32> = migrator.get_applied().iter().map(|(version, _)| *version).collect();
+    let pending: Vec<&'static Migration> = migrator.get_migrations().iter().filter(|migration| !applied.contains(migration.get_version())).collect();
+    for migration in pending.iter() {
+        migrator.apply(migration);
+    }
+    pending
+}
+
+/**
+ * Revert the latest applied migration, if there is one known by this release
+ **/
+pub fn down(migrator: &mut dyn Migrator) -> Option<&'static Migration> {
+    let (latest, _) = migrator.get_applied().pop()?;
+    let migration = migrator.get_migrations().iter().find(|migration| *migration.get_version() == latest)?;
+    migrator.revert(migration);
+    Some(migration)
+}
+
+/**
+ * Every migration with the time it was applied, or none when pending
+ **/
+pub fn status(migrator: &mut dyn Migrator) -> Vec<(&'static Migration, Option<DateTime<Utc>>)> {
+    let applied = migrator.get_applied();
+    migrator
+        .get_migrations()
+        .iter()
+        .map(|migration| (migration, applied.iter().find(|(version, _)| version == migration.get_version()).map(|(_, tm)| *tm)))
+        .collect()
+}
+
+/**
+ * Migrator of the store named by the config file given by the command
+ * line, connected without migrating. The memory store has no schema
+ **/
+pub fn open() -> Option<Box<dyn Migrator>> {
+    let cfg = Settings::new(env::args().nth(1)?);
+    match cfg.get_prefix().as_str() {
+        "memory" => None,
+        "sqlite" => Some(Box::new(SqliteStore::connect(&cfg.get_database_name()))),
+        _ => Some(Box::new(Database::connect())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::migration::{self, Migrator, SQLITE};
+    use crate::sqlite::SqliteStore;
+    use rusqlite::Connection;
+    use std::env;
+    use uuid::Uuid;
+
+    fn index_exist(path: &str, name: &str) -> bool {
+        let conn = Connection::open(path).unwrap();
+        conn.query_row("select count(*) from sqlite_master where type = 'index' and name = ?1", [name], |row| row.get::<_, i32>(0)).unwrap() > 0
+    }
+
+    #[test]
+    fn given_sqlite_file_when_migrated_up_and_down_then_versions_follow_in_order() {
+        let path = env::temp_dir().join(format!("pokemon-{}.db", Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let mut store = SqliteStore::connect(path);
+        assert!(migration::status(&mut store).iter().all(|(_, tm)| tm.is_none()));
+
+        let applied: Vec<i32> = migration::up(&mut store).iter().map(|migration| *migration.get_version()).collect();
+        assert_eq!(vec![1, 2, 3], applied);
+        assert!(migration::up(&mut store).is_empty());
+        assert!(index_exist(path, "request_table_uuid"));
+
+        // the latest ones are reverted first, then applied again alone
+        assert_eq!(Some(3), migration::down(&mut store).map(|migration| *migration.get_version()));
+        assert_eq!(Some(2), migration::down(&mut store).map(|migration| *migration.get_version()));
+        assert!(!index_exist(path, "request_table_uuid"));
+        assert_eq!(vec![1], store.get_applied().iter().map(|(version, _)| *version).collect::<Vec<i32>>());
+        assert_eq!(2, migration::up(&mut store).len());
+        assert_eq!(SQLITE.len(), migration::status(&mut store).iter().filter(|(_, tm)| tm.is_some()).count());
+
+        // a store opened on the file finds its schema up to date
+        assert_eq!(3, SqliteStore::new(path).get_applied().len());
+    }
+}
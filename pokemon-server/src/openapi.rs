@@ -0,0 +1,363 @@
+use crate::account::{Account, Reconciliation};
+use crate::auth::{API_KEY, NONCE, SIGNATURE, TIMESTAMP, TOKEN};
+use crate::candle::Candle;
+use crate::data_type::{AmendOrder, BookParam, CandleParam, Card, CardTransfer, CashTransfer, HistoryParam, LedgerEntry, OrderAck, RequestOrder, Rsp, StatusParam};
+use crate::order_queue::QueueDepth;
+use crate::portfolio::Portfolio;
+use crate::status_board::{Stats, Status};
+use crate::trade_board::{Trade, TradeHistory};
+use crate::tx_board::OrderBook;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+/**
+ * An operation served by the server. Routes registered in main.rs are
+ * listed in routes() as well, so the document is generated from them
+ * and the types they exchange
+ **/
+pub struct Route {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    content_type: &'static str,
+    security: Vec<Value>,
+    query: Vec<Value>,
+    body: Option<Schema>,
+    responses: Vec<(u16, &'static str, Option<Schema>)>,
+}
+
+impl Route {
+    pub fn new(method: &'static str, path: &'static str, summary: &'static str) -> Self {
+        Self {
+            method,
+            path,
+            summary,
+            content_type: "application/json",
+            security: Vec::new(),
+            query: Vec::new(),
+            body: None,
+            responses: Vec::new(),
+        }
+    }
+
+    // each field of the parameter type becomes a query parameter
+    pub fn with_query<T: JsonSchema>(mut self, gen: &mut SchemaGenerator) -> Self {
+        let mut schema = T::json_schema(gen).into_object();
+        let object = schema.object();
+        for (name, property) in object.properties.iter() {
+            self.query.push(json!({
+                "name": name,
+                "in": "query",
+                "required": object.required.contains(name),
+                "schema": property,
+            }));
+        }
+        self
+    }
+
+    pub fn with_body<T: JsonSchema>(mut self, gen: &mut SchemaGenerator) -> Self {
+        self.body = Some(gen.subschema_for::<T>());
+        self
+    }
+
+    pub fn with_response<T: JsonSchema>(mut self, gen: &mut SchemaGenerator, code: u16, description: &'static str) -> Self {
+        self.responses.push((code, description, Some(gen.subschema_for::<T>())));
+        self
+    }
+
+    // a response without any body, e.g. switching protocols
+    pub fn with_status(mut self, code: u16, description: &'static str) -> Self {
+        self.responses.push((code, description, None));
+        self
+    }
+
+    // signed by a registered trader, or anonymous as well if optional
+    pub fn with_signature(mut self, gen: &mut SchemaGenerator, optional: bool) -> Self {
+        self.security.push(json!({ API_KEY: [], TIMESTAMP: [], NONCE: [], SIGNATURE: [] }));
+        if optional {
+            self.security.push(json!({}));
+        }
+        self.with_response::<Rsp<String>>(gen, 401, "unsigned request or invalid signature")
+            .with_response::<Rsp<String>>(gen, 403, "another trader is accessed")
+    }
+
+    // a stream token in the query taken in place of the signature headers
+    pub fn with_token(mut self) -> Self {
+        self.security.push(json!({ TOKEN: [] }));
+        self
+    }
+
+    pub fn with_content_type(mut self, content_type: &'static str) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
+    pub fn get_method(&self) -> &str {
+        self.method
+    }
+
+    // e.g. /api/pokemon/card/:uuid -> /api/pokemon/card/{uuid}
+    pub fn get_openapi_path(&self) -> String {
+        to_openapi_path(self.path)
+    }
+
+    fn to_operation(&self, gen: &mut SchemaGenerator) -> Value {
+        let mut parameters: Vec<Value> = self
+            .path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix(':'))
+            .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": get_path_param(gen, name) }))
+            .collect();
+        parameters.extend(self.query.iter().cloned());
+
+        let mut responses = Map::new();
+        for (code, description, schema) in self.responses.iter() {
+            let mut response = json!({ "description": description });
+            if let Some(schema) = schema {
+                response["content"] = json!({ self.content_type: { "schema": schema } });
+            }
+            responses.insert(code.to_string(), response);
+        }
+
+        let mut operation = json!({
+            "summary": self.summary,
+            "parameters": parameters,
+            "responses": responses,
+        });
+        if !self.security.is_empty() {
+            operation["security"] = json!(self.security);
+        }
+        if let Some(body) = &self.body {
+            operation["requestBody"] = json!({ "required": true, "content": { "application/json": { "schema": body } } });
+        }
+        operation
+    }
+}
+
+pub fn to_openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+fn get_path_param(gen: &mut SchemaGenerator, name: &str) -> Schema {
+    match name {
+        "card" => gen.subschema_for::<Card>(),
+        "uuid" => gen.subschema_for::<Uuid>(),
+        _ => gen.subschema_for::<i32>(),
+    }
+}
+
+pub fn routes(gen: &mut SchemaGenerator) -> Vec<Route> {
+    vec![
+        Route::new("post", "/api/pokemon/card", "Place an order and wait for its acknowledgement")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 429, "too many requests of the trader, retry after Retry-After seconds")
+            .with_body::<RequestOrder>(gen)
+            .with_response::<Rsp<OrderAck>>(gen, 200, "processed by the matching engine")
+            .with_response::<Rsp<RequestOrder>>(gen, 202, "queued but not processed within ack_timeout_ms")
+            .with_response::<Rsp<String>>(gen, 422, "rejected with a reason, e.g. TOO_MANY_OPEN_ORDERS")
+            .with_response::<Rsp<QueueDepth>>(gen, 503, "the order queue is full, or the order could not be stored, retry after Retry-After seconds"),
+        Route::new("delete", "/api/pokemon/card/:uuid", "Cancel a resting order")
+            .with_signature(gen, false)
+            .with_response::<Rsp<Status>>(gen, 200, "cancelled")
+            .with_response::<Rsp<String>>(gen, 404, "not found")
+            .with_response::<Rsp<String>>(gen, 503, "the order could not be read or its cancellation stored, retry after Retry-After seconds"),
+        Route::new("patch", "/api/pokemon/card/:uuid", "Amend price and/or quantity of a resting order")
+            .with_signature(gen, false)
+            .with_body::<AmendOrder>(gen)
+            .with_response::<Rsp<AmendOrder>>(gen, 200, "amended")
+            .with_response::<Rsp<String>>(gen, 422, "rejected amendment")
+            .with_response::<Rsp<String>>(gen, 503, "the order could not be read or its amendment stored, retry after Retry-After seconds"),
+        Route::new("get", "/api/pokemon/queue", "Depth of the order queues")
+            .with_response::<Rsp<QueueDepth>>(gen, 200, "orders queued for matching"),
+        Route::new("get", "/api/pokemon/ws", "WebSocket streaming trades, book levels and order statuses")
+            .with_signature(gen, true)
+            .with_status(101, "switching to the WebSocket protocol")
+            .with_response::<Rsp<String>>(gen, 426, "WebSocket upgrade expected"),
+        Route::new("get", "/api/pokemon/stream/trades/:card", "Server-Sent Events of the trades of a card")
+            .with_content_type("text/event-stream")
+            .with_response::<Trade>(gen, 200, "trade events, resumed by Last-Event-ID"),
+        Route::new("get", "/api/pokemon/stream/orders/:id", "Server-Sent Events of the order statuses of a trader")
+            .with_signature(gen, false)
+            .with_token()
+            .with_content_type("text/event-stream")
+            .with_response::<Stats>(gen, 200, "order events, resumed by Last-Event-ID"),
+        Route::new("get", "/api/pokemon/book/:card", "Aggregated order book of a card")
+            .with_query::<BookParam>(gen)
+            .with_response::<Rsp<OrderBook>>(gen, 200, "best levels of each side")
+            .with_response::<Rsp<String>>(gen, 400, "invalid card or depth"),
+        Route::new("get", "/api/pokemon/candles/:card", "OHLCV candles of a card aggregated from its trades")
+            .with_query::<CandleParam>(gen)
+            .with_response::<Rsp<Vec<Candle>>>(gen, 200, "candles in time order, the current one live")
+            .with_response::<Rsp<String>>(gen, 400, "invalid card, interval or time range")
+            .with_response::<Rsp<Vec<Candle>>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("get", "/api/pokemon/trade/:card", "Latest trades of a card, listed by to_str")
+            .with_response::<Rsp<String>>(gen, 200, "latest 50 trades"),
+        Route::new("get", "/api/pokemon/order/:id", "Latest orders of a trader, listed by to_str")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 200, "latest 50 orders"),
+        Route::new("get", "/api/pokemon/trade/history", "Trade history of a trader on a date, listed by to_str")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 429, "too many requests of the trader, retry after Retry-After seconds")
+            .with_query::<HistoryParam>(gen)
+            .with_response::<Rsp<String>>(gen, 200, "trades of the date")
+            .with_response::<Rsp<String>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("get", "/api/pokemon/request/history", "Request history of a trader on a date, listed by to_str")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 429, "too many requests of the trader, retry after Retry-After seconds")
+            .with_query::<HistoryParam>(gen)
+            .with_response::<Rsp<String>>(gen, 200, "requests of the date")
+            .with_response::<Rsp<String>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("get", "/api/pokemon/order/status", "Latest status of an order")
+            .with_signature(gen, false)
+            .with_query::<StatusParam>(gen)
+            .with_response::<Rsp<Status>>(gen, 200, "status of the order")
+            .with_response::<Rsp<String>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("get", "/api/v2/pokemon/trade/:card", "Latest trades of a card")
+            .with_response::<Rsp<Vec<Trade>>>(gen, 200, "latest 50 trades"),
+        Route::new("get", "/api/v2/pokemon/order/:id", "Latest orders of a trader")
+            .with_signature(gen, false)
+            .with_response::<Rsp<Vec<Stats>>>(gen, 200, "latest 50 orders"),
+        Route::new("get", "/api/v2/pokemon/trade/history", "Trade history of a trader on a date")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 429, "too many requests of the trader, retry after Retry-After seconds")
+            .with_query::<HistoryParam>(gen)
+            .with_response::<Rsp<Vec<TradeHistory>>>(gen, 200, "trades of the date")
+            .with_response::<Rsp<Vec<TradeHistory>>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("get", "/api/v2/pokemon/request/history", "Request history of a trader on a date")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 429, "too many requests of the trader, retry after Retry-After seconds")
+            .with_query::<HistoryParam>(gen)
+            .with_response::<Rsp<Vec<RequestOrder>>>(gen, 200, "requests of the date")
+            .with_response::<Rsp<Vec<RequestOrder>>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("get", "/api/pokemon/portfolio/:id", "Positions, P&L and open orders of a trader")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 429, "too many requests of the trader, retry after Retry-After seconds")
+            .with_response::<Rsp<Portfolio>>(gen, 200, "positions of the cards the trader traded or rests orders on")
+            .with_response::<Rsp<String>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("post", "/api/admin/account/:id/cash", "Deposit cash to a trader, or withdraw it by a negative amount")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 403, "the signer is not an operator")
+            .with_body::<CashTransfer>(gen)
+            .with_response::<Rsp<Account>>(gen, 200, "account after the deposit or withdrawal")
+            .with_response::<Rsp<String>>(gen, 400, "zero amount or missing reason")
+            .with_response::<Rsp<String>>(gen, 409, "accounts are disabled")
+            .with_response::<Rsp<String>>(gen, 422, "withdrawal beyond the available cash")
+            .with_response::<Rsp<String>>(gen, 503, "neither the balance nor the entry could be stored, retry after Retry-After seconds"),
+        Route::new("post", "/api/admin/account/:id/cards", "Mint cards for a trader, or burn them by a negative qty")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 403, "the signer is not an operator")
+            .with_body::<CardTransfer>(gen)
+            .with_response::<Rsp<Account>>(gen, 200, "account after the mint or burn")
+            .with_response::<Rsp<String>>(gen, 400, "zero qty or missing reason")
+            .with_response::<Rsp<String>>(gen, 409, "accounts are disabled")
+            .with_response::<Rsp<String>>(gen, 422, "burn beyond the available cards")
+            .with_response::<Rsp<String>>(gen, 503, "neither the balance nor the entry could be stored, retry after Retry-After seconds"),
+        Route::new("get", "/api/admin/account/:id/ledger", "Ledger entries booked for a trader")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 403, "the signer is not an operator")
+            .with_response::<Rsp<Vec<LedgerEntry>>>(gen, 200, "entries in booking order")
+            .with_response::<Rsp<Vec<LedgerEntry>>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("get", "/api/admin/reconcile", "Balances differing from the ledger and the trades")
+            .with_signature(gen, false)
+            .with_response::<Rsp<String>>(gen, 403, "the signer is not an operator")
+            .with_response::<Rsp<Vec<Reconciliation>>>(gen, 200, "differing balances, empty if reconciled")
+            .with_response::<Rsp<Vec<Reconciliation>>>(gen, 503, "the database is unavailable, retry after Retry-After seconds"),
+        Route::new("get", "/api/openapi.json", "This document")
+            .with_status(200, "OpenAPI 3.0 document"),
+    ]
+}
+
+// OpenAPI 3.0 document versioned after the package
+pub fn document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+    for route in routes(&mut gen) {
+        let operation = route.to_operation(&mut gen);
+        let path = paths.entry(route.get_openapi_path()).or_insert_with(|| json!({}));
+        path[route.get_method()] = operation;
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Pokemon Trading Platform",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": gen.definitions(),
+            // a request is signed by all of the headers, see auth::sign
+            "securitySchemes": {
+                API_KEY: { "type": "apiKey", "in": "header", "name": API_KEY },
+                TIMESTAMP: { "type": "apiKey", "in": "header", "name": TIMESTAMP, "description": "unix time in milliseconds" },
+                NONCE: { "type": "apiKey", "in": "header", "name": NONCE, "description": "never reused within max_skew_ms" },
+                SIGNATURE: { "type": "apiKey", "in": "header", "name": SIGNATURE, "description": "hex HMAC-SHA256 of {timestamp}\\n{nonce}\\n{METHOD}\\n{path[?query]}\\n{body} by the secret" },
+                TOKEN: { "type": "apiKey", "in": "query", "name": TOKEN, "description": "{api_key}.{expires}.{hex HMAC-SHA256 of {expires}\\nGET\\n{path} by the secret}, expiring within an hour" },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::openapi::{document, to_openapi_path};
+    use std::collections::BTreeSet;
+
+    // (method, path) of every route registered in main.rs
+    fn get_served_routes() -> BTreeSet<(String, String)> {
+        let mut served = BTreeSet::new();
+        let mut path = String::new();
+        for line in include_str!("main.rs").lines().map(|line| line.trim()) {
+            if let Some(rest) = line.strip_prefix(".at(\"") {
+                path = to_openapi_path(rest.split('"').next().unwrap_or_default());
+            }
+            for method in ["get", "post", "put", "patch", "delete"].iter() {
+                if line.starts_with(&format!(".{}(", method)) {
+                    served.insert((method.to_string(), path.clone()));
+                }
+            }
+        }
+        served
+    }
+
+    #[test]
+    fn given_routes_registered_in_main_when_document_generated_then_they_agree() {
+        let doc = document();
+        let mut documented = BTreeSet::new();
+        for (path, item) in doc["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys() {
+                documented.insert((method.clone(), path.clone()));
+            }
+        }
+        assert_eq!(get_served_routes(), documented);
+        assert_eq!(env!("CARGO_PKG_VERSION"), doc["info"]["version"]);
+    }
+
+    #[test]
+    fn given_the_document_when_schemas_referred_then_all_defined() {
+        let doc = document();
+        let text = doc.to_string();
+        let schemas = doc["components"]["schemas"].as_object().unwrap();
+        for reference in text.split("\"$ref\":\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(schemas.contains_key(name), "[ERROR] Test Failed: Schema {} is not defined", name);
+        }
+        for name in ["RequestOrder", "AmendOrder", "Rsp_for_OrderAck", "Price"].iter() {
+            assert!(schemas.contains_key(*name), "[ERROR] Test Failed: Schema {} is missing", name);
+        }
+
+        // query parameters come from the parameter types
+        let history = &doc["paths"]["/api/pokemon/trade/history"]["get"]["parameters"];
+        let names: BTreeSet<&str> = history.as_array().unwrap().iter().map(|param| param["name"].as_str().unwrap()).collect();
+        assert_eq!(["date", "id"].iter().copied().collect::<BTreeSet<&str>>(), names);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::data_type::{OrderAck, RequestOrder};
+use async_std::channel;
+use schemars::JsonSchema;
+use std::collections::{HashMap, HashSet};
+use std::ops::Add;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use tide::prelude::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueError {
+    // the queue reaches its capacity, the order could be retried later
+    Full,
+    Duplicate,
+    // the trader reaches max_open_orders
+    TooManyOpenOrders,
+    // the matching thread has stopped
+    Closed,
+}
+
+// an order waiting for matching along with the channel its acknowledgement goes to
+pub struct PendingOrder {
+    order: RequestOrder,
+    ack: channel::Sender<OrderAck>,
+}
+
+impl PendingOrder {
+    pub fn get_order(&self) -> &RequestOrder {
+        &self.order
+    }
+
+    pub fn acknowledge(&self, ack: OrderAck) {
+        // the trader may have stopped waiting, so a closed channel is ignored
+        let _ = self.ack.try_send(ack);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct QueueDepth {
+    depth: usize,
+    capacity: usize,
+}
+
+impl QueueDepth {
+    pub fn get_depth(&self) -> &usize {
+        &self.depth
+    }
+
+    pub fn get_capacity(&self) -> &usize {
+        &self.capacity
+    }
+}
+
+/**
+ * Producer side of the bounded channel feeding the matching thread.
+ * Orders are tracked by uuid from being pushed until being matched, so
+ * the depth counts every order accepted but not acknowledged yet
+ **/
+#[derive(Debug, Clone)]
+pub struct OrderQueue {
+    sender: SyncSender<PendingOrder>,
+    queued: Arc<Mutex<HashSet<Uuid>>>,
+    capacity: usize,
+}
+
+/**
+ * Orders of each trader open across the cards, i.e. waiting in the queues
+ * or resting on the boards. They are counted as they are pushed and closed
+ * as they are filled, cancelled or dropped, so the limit of a trader is
+ * checked without taking the lock of any scheduler
+ **/
+#[derive(Debug, Clone, Default)]
+pub struct OpenOrders {
+    counts: Arc<Mutex<HashMap<i32, usize>>>,
+}
+
+impl OpenOrders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // counted unless the trader has reached the limit
+    pub fn open(&self, trader_id: &i32, limit: usize) -> bool {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(*trader_id).or_insert(0);
+        if *count >= limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    pub fn close(&self, trader_id: &i32) {
+        let mut counts = self.counts.lock().unwrap();
+        if let Some(count) = counts.get_mut(trader_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                counts.remove(trader_id);
+            }
+        }
+    }
+
+    pub fn get(&self, trader_id: &i32) -> usize {
+        self.counts.lock().unwrap().get(trader_id).copied().unwrap_or(0)
+    }
+}
+
+// depths of several queues add up to their total
+impl Add for QueueDepth {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            depth: self.depth + other.depth,
+            capacity: self.capacity + other.capacity,
+        }
+    }
+}
+
+impl OrderQueue {
+    pub fn new(capacity: usize) -> (Self, Receiver<PendingOrder>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let queue = Self {
+            sender,
+            queued: Arc::new(Mutex::new(HashSet::new())),
+            capacity,
+        };
+        (queue, receiver)
+    }
+
+    pub fn push(&self, req: RequestOrder) -> Result<channel::Receiver<OrderAck>, QueueError> {
+        let mut queued = self.queued.lock().unwrap();
+        if !queued.insert(req.get_uuid()) {
+            return Err(QueueError::Duplicate);
+        }
+
+        let (ack, receiver) = channel::bounded(1);
+        match self.sender.try_send(PendingOrder { order: req, ack }) {
+            Ok(_) => Ok(receiver),
+            Err(e) => {
+                queued.remove(&req.get_uuid());
+                match e {
+                    TrySendError::Full(_) => Err(QueueError::Full),
+                    TrySendError::Disconnected(_) => Err(QueueError::Closed),
+                }
+            }
+        }
+    }
+
+    pub fn contains(&self, uuid: &Uuid) -> bool {
+        self.queued.lock().unwrap().contains(uuid)
+    }
+
+    // called by the matching thread once the order has been matched
+    pub fn done(&self, uuid: &Uuid) {
+        self.queued.lock().unwrap().remove(uuid);
+    }
+
+    pub fn get_depth(&self) -> QueueDepth {
+        QueueDepth {
+            depth: self.queued.lock().unwrap().len(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_type::{Card, OrderStatus, ProcessResult, RequestOrder, Side};
+    use crate::order_queue::{OrderQueue, QueueError};
+    use crate::Scheduler;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    #[test]
+    fn given_a_queue_with_capacity_two_when_a_third_order_pushed_then_rejected_as_full() {
+        let (queue, receiver) = OrderQueue::new(2);
+        let (first, second, third) = (
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, 1),
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, 1),
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, 1),
+        );
+        assert!(queue.push(first).is_ok());
+        assert_eq!(Some(QueueError::Duplicate), queue.push(first).err());
+        assert!(queue.push(second).is_ok());
+        assert_eq!(Some(QueueError::Full), queue.push(third).err());
+        assert!(!queue.contains(&third.get_uuid()));
+        assert_eq!(&2, queue.get_depth().get_depth());
+        assert_eq!(&2, queue.get_depth().get_capacity());
+
+        // a received order still counts until it is done
+        if let Ok(pending) = receiver.recv() {
+            assert_eq!(first.get_uuid(), pending.get_order().get_uuid());
+        } else {
+            panic!("[ERROR] Test Failed: Pending order is not received");
+        }
+        assert_eq!(&2, queue.get_depth().get_depth());
+        queue.done(&first.get_uuid());
+        assert_eq!(&1, queue.get_depth().get_depth());
+        assert!(queue.push(third).is_ok());
+
+        drop(receiver);
+        let closed = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, 1);
+        assert_eq!(Some(QueueError::Closed), queue.push(closed).err());
+    }
+
+    #[test]
+    fn given_an_order_pushed_when_matched_then_its_ack_sent_through_the_channel() {
+        let mut scheduler = Scheduler::new();
+        let (queue, receiver) = OrderQueue::new(4);
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.50, 1, Card::Squirtle, 52);
+        let ack_receiver = match queue.push(req) {
+            Ok(ack_receiver) => ack_receiver,
+            Err(e) => panic!("[ERROR] Test Failed: Order cannot be pushed: {:?}", e),
+        };
+        assert!(ack_receiver.try_recv().is_err());
+
+        if let Ok(pending) = receiver.try_recv() {
+            let ack = scheduler.execute(pending.get_order());
+            queue.done(&pending.get_order().get_uuid());
+            pending.acknowledge(ack);
+        } else {
+            panic!("[ERROR] Test Failed: Pending order is not received");
+        }
+
+        if let Ok(ack) = ack_receiver.try_recv() {
+            assert_eq!(&ProcessResult::TxConfirmed, ack.get_result());
+            assert_eq!(&Some(OrderStatus::Confirmed), ack.get_status());
+        } else {
+            panic!("[ERROR] Test Failed: Acknowledgement is not received");
+        }
+        assert_eq!(&0, queue.get_depth().get_depth());
+
+        // nobody waits for the acknowledgement any more
+        let dropped = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.50, 1, Card::Squirtle, 52);
+        drop(queue.push(dropped));
+        if let Ok(pending) = receiver.try_recv() {
+            let ack = scheduler.execute(pending.get_order());
+            pending.acknowledge(ack);
+        }
+        assert_eq!(OrderStatus::Confirmed, scheduler.db.get_order_status(&dropped.get_uuid()));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::store::{self, Store};
+use async_std::task;
+use async_trait::async_trait;
+use deadpool::managed::{Manager, Object, Pool, RecycleError, RecycleResult};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+/**
+ * Opens the stores of the pool, by default from the config file given by
+ * the command line the same way as the matching engines open their own ones
+ **/
+pub struct StoreManager {
+    open: Arc<dyn Fn() -> Box<dyn Store> + Send + Sync>,
+}
+
+#[async_trait]
+impl Manager for StoreManager {
+    type Type = Box<dyn Store>;
+    type Error = String;
+
+    // a store failing to connect panics, which is answered as an error here
+    async fn create(&self) -> Result<Box<dyn Store>, String> {
+        let open = self.open.clone();
+        task::spawn_blocking(move || panic::catch_unwind(AssertUnwindSafe(|| open()))).await.map_err(|_| String::from("the store cannot be opened"))
+    }
+
+    async fn recycle(&self, store: &mut Box<dyn Store>) -> RecycleResult<String> {
+        match store.is_closed() {
+            true => Err(RecycleError::Message(String::from("the connection is closed"))),
+            false => Ok(()),
+        }
+    }
+}
+
+/**
+ * Stores shared by the handlers reading histories and statuses. A read
+ * waits for an idle store without blocking the server, and runs on a
+ * blocking thread, so it never takes the lock of any scheduler and a slow
+ * query only holds up the reads queued behind it. A store panicking on a
+ * lost connection fails the read with an error
+ **/
+#[derive(Clone)]
+pub struct StorePool {
+    pool: Pool<StoreManager>,
+}
+
+impl StorePool {
+    pub fn new(size: usize) -> Self {
+        Self::with_opener(size, store::open)
+    }
+
+    // stores opened by the given function instead, e.g. on the tables of a test
+    pub fn with_opener<F>(size: usize, open: F) -> Self
+    where
+        F: Fn() -> Box<dyn Store> + Send + Sync + 'static,
+    {
+        Self {
+            pool: Pool::builder(StoreManager { open: Arc::new(open) }).max_size(size).build().unwrap(),
+        }
+    }
+
+    // the most stores opened at once
+    pub fn get_size(&self) -> usize {
+        self.pool.status().max_size
+    }
+
+    pub async fn read<T, F>(&self, read: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut dyn Store) -> T + Send + 'static,
+    {
+        let mut store: Object<StoreManager> = self.pool.get().await.map_err(|e| e.to_string())?;
+        task::spawn_blocking(move || {
+            let res = panic::catch_unwind(AssertUnwindSafe(|| read(&mut **store)));
+            // a store failing a read is dropped instead of being handed to the next one
+            if res.is_err() {
+                drop(Object::take(store));
+            }
+            res
+        })
+        .await
+        .map_err(|_| String::from("the store failed to read"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_type::{Card, ProcessResult, RequestOrder, Side};
+    use crate::pool::StorePool;
+    use crate::scheduler::Scheduler;
+    use crate::store::MemoryStore;
+    use async_std::task;
+    use chrono::Utc;
+    use futures_util::future;
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    #[test]
+    fn given_a_scheduler_locked_by_matching_when_histories_read_through_the_pool_then_served() {
+        let trader_id = 81;
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 1, Card::Charmander, trader_id);
+        let tables = MemoryStore::new();
+        let scheduler = Arc::new(Mutex::new(Scheduler::for_cards(&[Card::Charmander])));
+        scheduler.lock().unwrap().db = Box::new(tables.clone());
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.lock().unwrap().process(&req));
+
+        let pool = StorePool::with_opener(2, move || Box::new(tables.clone()));
+        let _matching = scheduler.lock().unwrap();
+        let date = Utc::now().format("%Y-%m-%d").to_string();
+        let history = task::block_on(pool.read(move |db| db.get_request_history(&trader_id, &date))).unwrap();
+        assert_eq!(vec![req.get_uuid()], history.iter().map(|req| req.get_uuid()).collect::<Vec<Uuid>>());
+
+        // reads outnumbering the stores wait for idle ones
+        let reads: Vec<_> = (0..5).map(|_| pool.read(move |db| db.request_exist(&req.get_uuid()))).collect();
+        assert_eq!(vec![Ok(true); 5], task::block_on(future::join_all(reads)));
+        assert_eq!(2, pool.get_size());
+
+        // a read panicking like a store on a lost connection fails alone
+        let failed: Result<bool, String> = task::block_on(pool.read(|_| panic!("[Database][get_request_history] connection closed")));
+        assert!(failed.is_err());
+        assert_eq!(Ok(true), task::block_on(pool.read(move |db| db.request_exist(&req.get_uuid()))));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::data_type::{Card, Price, Side};
+use schemars::JsonSchema;
+use tide::prelude::Serialize;
+
+/**
+ * Holding of a card by a trader, built from the trades in time order at
+ * average cost. A negative qty is a short position, whose cost is the
+ * proceeds of the sales. Unrealised P&L is marked at the last trade of
+ * the card; open orders are what the trader still rests on the board
+ **/
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Position {
+    card: Card,
+    qty: i32,
+    avg_cost: Option<Price>,
+    realised_pnl: Price,
+    mark_px: Option<Price>,
+    unrealised_pnl: Price,
+    open_buy_vol: i32,
+    open_buy_notional: Price,
+    open_sell_vol: i32,
+    open_sell_notional: Price,
+    // cost of the open qty in cents, signed as the qty
+    #[serde(skip)]
+    cost: i64,
+}
+
+impl Position {
+    fn new(card: Card) -> Self {
+        Self {
+            card,
+            qty: 0,
+            avg_cost: None,
+            realised_pnl: Price::default(),
+            mark_px: None,
+            unrealised_pnl: Price::default(),
+            open_buy_vol: 0,
+            open_buy_notional: Price::default(),
+            open_sell_vol: 0,
+            open_sell_notional: Price::default(),
+            cost: 0,
+        }
+    }
+
+    pub fn get_card(&self) -> &Card {
+        &self.card
+    }
+
+    pub fn get_qty(&self) -> &i32 {
+        &self.qty
+    }
+
+    pub fn get_avg_cost(&self) -> &Option<Price> {
+        &self.avg_cost
+    }
+
+    pub fn get_realised_pnl(&self) -> &Price {
+        &self.realised_pnl
+    }
+
+    pub fn get_mark_px(&self) -> &Option<Price> {
+        &self.mark_px
+    }
+
+    pub fn get_unrealised_pnl(&self) -> &Price {
+        &self.unrealised_pnl
+    }
+
+    pub fn get_open_buy_vol(&self) -> &i32 {
+        &self.open_buy_vol
+    }
+
+    pub fn get_open_buy_notional(&self) -> &Price {
+        &self.open_buy_notional
+    }
+
+    pub fn get_open_sell_vol(&self) -> &i32 {
+        &self.open_sell_vol
+    }
+
+    pub fn get_open_sell_notional(&self) -> &Price {
+        &self.open_sell_notional
+    }
+
+    /**
+     * A trade adding to the position raises its cost, while one reducing
+     * it realises the difference to the average cost of the closed part.
+     * The part beyond a flat position opens the other way at the price
+     **/
+    fn trade(&mut self, side: Side, px: &Price, vol: i32) {
+        let signed = match side {
+            Side::Buy => vol,
+            Side::Sell => -vol,
+        };
+        if self.qty == 0 || self.qty.signum() == signed.signum() {
+            self.cost += px.get_cents() * signed as i64;
+        } else {
+            let closed = vol.min(self.qty.abs());
+            let closed_cost = self.cost * closed as i64 / self.qty.abs() as i64;
+            let pnl = (px.get_cents() * closed as i64 - closed_cost.abs()) * self.qty.signum() as i64;
+            self.realised_pnl = Price::from_cents(self.realised_pnl.get_cents() + pnl);
+            self.cost -= closed_cost;
+            self.cost += px.get_cents() * (signed.abs() - closed) as i64 * signed.signum() as i64;
+        }
+        self.qty += signed;
+        if self.qty == 0 {
+            self.cost = 0;
+        }
+        self.revalue();
+    }
+
+    fn mark(&mut self, px: &Price) {
+        self.mark_px = Some(*px);
+        self.revalue();
+    }
+
+    fn revalue(&mut self) {
+        self.avg_cost = match self.qty {
+            0 => None,
+            qty => Some(Price::from_cents((self.cost as f64 / qty as f64).round() as i64)),
+        };
+        self.unrealised_pnl = match self.mark_px {
+            Some(px) => Price::from_cents(px.times(self.qty).get_cents() - self.cost),
+            None => Price::default(),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Portfolio {
+    trader_id: i32,
+    positions: Vec<Position>,
+    realised_pnl: Price,
+    unrealised_pnl: Price,
+}
+
+impl Portfolio {
+    pub fn new(trader_id: i32) -> Self {
+        Self {
+            trader_id,
+            positions: Vec::new(),
+            realised_pnl: Price::default(),
+            unrealised_pnl: Price::default(),
+        }
+    }
+
+    pub fn get_trader_id(&self) -> &i32 {
+        &self.trader_id
+    }
+
+    pub fn get_positions(&self) -> &Vec<Position> {
+        &self.positions
+    }
+
+    pub fn get_position(&self, card: &Card) -> Option<&Position> {
+        self.positions.iter().find(|position| &position.card == card)
+    }
+
+    pub fn get_realised_pnl(&self) -> &Price {
+        &self.realised_pnl
+    }
+
+    pub fn get_unrealised_pnl(&self) -> &Price {
+        &self.unrealised_pnl
+    }
+
+    fn get_position_mut(&mut self, card: &Card) -> &mut Position {
+        match self.positions.iter().position(|position| &position.card == card) {
+            Some(index) => &mut self.positions[index],
+            None => {
+                self.positions.push(Position::new(*card));
+                self.positions.last_mut().unwrap()
+            }
+        }
+    }
+
+    // trades have to be added in time order
+    pub fn add_trade(&mut self, card: &Card, side: Side, px: &Price, vol: i32) {
+        self.get_position_mut(card).trade(side, px, vol);
+        self.total();
+    }
+
+    pub fn mark(&mut self, card: &Card, px: &Price) {
+        if let Some(position) = self.positions.iter_mut().find(|position| &position.card == card) {
+            position.mark(px);
+            self.total();
+        }
+    }
+
+    pub fn add_open_orders(&mut self, card: &Card, side: Side, vol: i32, notional: &Price) {
+        if vol == 0 {
+            return;
+        }
+        let position = self.get_position_mut(card);
+        match side {
+            Side::Buy => {
+                position.open_buy_vol += vol;
+                position.open_buy_notional = Price::from_cents(position.open_buy_notional.get_cents() + notional.get_cents());
+            }
+            Side::Sell => {
+                position.open_sell_vol += vol;
+                position.open_sell_notional = Price::from_cents(position.open_sell_notional.get_cents() + notional.get_cents());
+            }
+        }
+    }
+
+    fn total(&mut self) {
+        self.realised_pnl = Price::from_cents(self.positions.iter().map(|position| position.realised_pnl.get_cents()).sum());
+        self.unrealised_pnl = Price::from_cents(self.positions.iter().map(|position| position.unrealised_pnl.get_cents()).sum());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_type::{Card, Price, Side};
+    use crate::portfolio::Portfolio;
+
+    #[test]
+    fn given_trades_of_a_trader_when_portfolio_built_then_average_cost_and_pnl_computed() {
+        let mut portfolio = Portfolio::new(1);
+        portfolio.add_trade(&Card::Pikachu, Side::Buy, &Price::from(2.00), 2);
+        portfolio.add_trade(&Card::Pikachu, Side::Buy, &Price::from(3.00), 2);
+        portfolio.add_trade(&Card::Pikachu, Side::Sell, &Price::from(4.00), 3);
+        portfolio.mark(&Card::Pikachu, &Price::from(5.00));
+
+        let pikachu = portfolio.get_position(&Card::Pikachu).unwrap();
+        assert_eq!((&1, &Some(Price::from(2.50))), (pikachu.get_qty(), pikachu.get_avg_cost()));
+        assert_eq!((&Price::from(4.50), &Price::from(2.50)), (pikachu.get_realised_pnl(), pikachu.get_unrealised_pnl()));
+
+        // selling through a flat position opens a short at the price
+        portfolio.add_trade(&Card::Pikachu, Side::Sell, &Price::from(6.00), 3);
+        let pikachu = portfolio.get_position(&Card::Pikachu).unwrap();
+        assert_eq!((&-2, &Some(Price::from(6.00))), (pikachu.get_qty(), pikachu.get_avg_cost()));
+        assert_eq!((&Price::from(8.00), &Price::from(2.00)), (pikachu.get_realised_pnl(), pikachu.get_unrealised_pnl()));
+
+        portfolio.add_trade(&Card::Squirtle, Side::Sell, &Price::from(1.00), 1);
+        portfolio.add_open_orders(&Card::Squirtle, Side::Buy, 2, &Price::from(3.00));
+        let squirtle = portfolio.get_position(&Card::Squirtle).unwrap();
+        assert_eq!((&None, &Price::from(0.00)), (squirtle.get_mark_px(), squirtle.get_unrealised_pnl()));
+        assert_eq!((&2, &Price::from(3.00)), (squirtle.get_open_buy_vol(), squirtle.get_open_buy_notional()));
+        assert_eq!((&Price::from(8.00), &Price::from(2.00)), (portfolio.get_realised_pnl(), portfolio.get_unrealised_pnl()));
+    }
+}
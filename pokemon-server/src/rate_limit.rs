@@ -0,0 +1,114 @@
+use crate::auth;
+use crate::data_type::{ProcessStatus, Rsp};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tide::{Body, Middleware, Next, Request, Response, StatusCode};
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/**
+ * Token buckets per trader. A bucket holds up to burst tokens and gains
+ * rate tokens per second; each request of the trader takes one token or
+ * is refused until the next token is due
+ **/
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Arc<Mutex<HashMap<i32, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            rate,
+            // a request takes a whole token
+            burst: burst.max(1.0),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn get_rate(&self) -> &f64 {
+        &self.rate
+    }
+
+    pub fn get_burst(&self) -> &f64 {
+        &self.burst
+    }
+
+    // the time to wait for the next token if the trader runs out of them
+    pub fn acquire(&self, trader_id: &i32) -> Result<(), Duration> {
+        self.acquire_at(trader_id, Instant::now())
+    }
+
+    pub fn acquire_at(&self, trader_id: &i32, now: Instant) -> Result<(), Duration> {
+        if self.rate <= 0.0 {
+            return Ok(());
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(*trader_id).or_insert(Bucket { tokens: self.burst, last: now });
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.last = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+// requests of the authenticated trader are counted, anonymous ones pass through
+#[tide::utils::async_trait]
+impl Middleware<()> for RateLimiter {
+    async fn handle(&self, req: Request<()>, next: Next<'_, ()>) -> tide::Result {
+        if let Some(trader_id) = auth::get_identity(&req) {
+            if let Err(wait) = self.acquire(&trader_id) {
+                // Retry-After takes whole seconds
+                let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                let mut res = Response::new(StatusCode::TooManyRequests);
+                res.insert_header("Retry-After", retry_after.to_string());
+                let msg = format!("trader {} exceeds {} requests per second, retry after {} ms", trader_id, self.rate, wait.as_millis());
+                let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}"));
+                res.set_body(Body::from_json(&rsp)?);
+                return Ok(res);
+            }
+        }
+        Ok(next.run(req).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rate_limit::RateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn given_a_bucket_with_burst_of_two_when_requests_flood_then_refused_until_refilled() {
+        let limiter = RateLimiter::new(4.0, 2.0);
+        let now = Instant::now();
+        assert_eq!(Ok(()), limiter.acquire_at(&1, now));
+        assert_eq!(Ok(()), limiter.acquire_at(&1, now));
+        assert_eq!(Err(Duration::from_millis(250)), limiter.acquire_at(&1, now));
+        // other traders own their buckets
+        assert_eq!(Ok(()), limiter.acquire_at(&2, now));
+
+        let later = now + Duration::from_millis(250);
+        assert_eq!(Ok(()), limiter.acquire_at(&1, later));
+        assert!(limiter.acquire_at(&1, later).is_err());
+
+        // tokens never pile up beyond the burst
+        let idle = later + Duration::from_secs(60);
+        assert_eq!(vec![true, true, false], (0..3).map(|_| limiter.acquire_at(&1, idle).is_ok()).collect::<Vec<bool>>());
+
+        let unlimited = RateLimiter::new(0.0, 0.0);
+        assert!((0..100).all(|_| unlimited.acquire_at(&1, now).is_ok()));
+    }
+}
@@ -1,134 +1,438 @@
-use crate::data_type::{Card, OrderStatus, ProcessResult, RequestOrder, Side};
-use crate::database;
-use crate::status_board::{Stats, StatusBoard, Status};
-use crate::trade_board::{Trade, TradeBoard, TradeHistory};
-use crate::tx_board::{Tag, TxBoard, CardBoard, Volume};
-
-use postgres::Row;
-use chrono::Utc;
-use database::Database;
-use std::collections::{LinkedList, VecDeque};
+use crate::account::Accounts;
+use crate::candle::get_intervals;
+use crate::data_type::{AmendOrder, Card, Fill, OrderAck, OrderStatus, OrderType, Price, ProcessResult, RejectReason, RequestOrder, Side, TimeInForce};
+use crate::market_data::{MarketData, MarketEvent};
+use crate::order_queue::OpenOrders;
+use crate::settings::Settings;
+use crate::store::{self, Store, Write};
+use crate::status_board::{Stats, StatusBoard};
+use crate::trade_board::{Trade, TradeBoard};
+use crate::tx_board::{OrderBook, PriceGrid, Tag, TxBoard, CardBoard, Volume};
+use crate::validator::Validator;
+
+use chrono::{Duration, Utc};
+use std::cmp;
+use std::collections::{HashMap, LinkedList};
+use std::env;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use tide::Request;
 use uuid::Uuid;
 
 pub struct Scheduler {
-    pub order_queue: VecDeque<RequestOrder>,
     pub tx_board: TxBoard,
     pub trade_board: TradeBoard,
     pub status_board: StatusBoard,
-    pub db: Database,
+    pub validator: Validator,
+    pub db: Box<dyn Store>,
+    cards: Vec<Card>,
+    seq: u64,
+    feed: MarketData,
+    accounts: Accounts,
+    open_orders: OpenOrders,
 }
 
 impl Scheduler {
     pub fn new() -> Self {
+        Self::for_cards(&[Card::Pikachu, Card::Bulbasaur, Card::Charmander, Card::Squirtle])
+    }
+
+    /**
+     * A scheduler matching the given cards only. Each matching engine
+     * owns the scheduler of its card, so orders of other cards are
+     * answered with UnknownCard
+     **/
+    pub fn for_cards(cards: &[Card]) -> Self {
         Self {
-            order_queue: VecDeque::<RequestOrder>::new(),
-            tx_board: TxBoard::new(),
-            trade_board: TradeBoard::new(),
+            tx_board: TxBoard::for_cards(cards, &get_price_grids()),
+            trade_board: TradeBoard::for_cards(cards),
             status_board: StatusBoard::new(),
-            db: Database::new(),
+            validator: get_validator(),
+            db: store::open(),
+            cards: cards.to_vec(),
+            seq: 0,
+            feed: MarketData::new(),
+            accounts: Accounts::default(),
+            open_orders: OpenOrders::new(),
+        }
+    }
+
+    // publish trades, book levels and order statuses to the feed
+    pub fn set_feed(&mut self, feed: MarketData) {
+        self.trade_board.set_feed(feed.clone());
+        self.status_board.set_feed(feed.clone());
+        self.feed = feed;
+    }
+
+    // keep balances of traders in the accounts shared with other schedulers
+    pub fn set_accounts(&mut self, accounts: Accounts) {
+        self.accounts = accounts;
+    }
+
+    pub fn get_accounts(&self) -> &Accounts {
+        &self.accounts
+    }
+
+    // resting orders are closed here as they leave the board, incoming ones by the engine
+    pub fn set_open_orders(&mut self, open_orders: OpenOrders) {
+        self.open_orders = open_orders;
+    }
+
+    pub fn get_cards(&self) -> &Vec<Card> {
+        &self.cards
+    }
+
+    // number of orders executed so far, the latest one carries it as its sequence number
+    pub fn get_seq(&self) -> &u64 {
+        &self.seq
+    }
+
+    /**
+     * Check an incoming order before it is queued for matching, so that
+     * the trader learns why it is refused instead of a silent drop
+     **/
+    pub fn validate(&self, req: &RequestOrder) -> Result<(), RejectReason> {
+        self.validator.validate(req, &self.tx_board)?;
+        self.accounts.check(req, &self.get_reserve_px(req))
+    }
+
+    /**
+     * Price the cash of a buy order is reserved at. A market order has no
+     * price limit, so it reserves enough to sweep up to the top of the grid
+     **/
+    pub fn get_reserve_px(&self, req: &RequestOrder) -> Price {
+        match (req.get_side(), req.get_order_type()) {
+            (Side::Buy, OrderType::Market) => self.tx_board.get_grid(&req.get_card()).map(|grid| *grid.get_max()).unwrap_or_else(|| req.get_order_px()),
+            _ => req.get_order_px(),
         }
-    }    
+    }
 
     pub fn process(&mut self, req: &RequestOrder) -> ProcessResult {
-        let mut proc_res: ProcessResult = ProcessResult::TxConfirmed;
+        self.execute(req).get_result().clone()
+    }
+
+    /**
+     * Match an order and report what happened to it, i.e. the result,
+     * the resulting status and the fills without their counterparties
+     **/
+    pub fn execute(&mut self, req: &RequestOrder) -> OrderAck {
+        let mut fills = Vec::<Fill>::new();
+        let result = self.match_order(req, &mut fills);
+        self.seq += 1;
+
+        // levels swept by the order and the one it rests on
+        let opposite = match req.get_side() {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let mut levels: Vec<(Side, Price)> = fills.iter().map(|fill| (opposite, *fill.get_px())).collect();
+        levels.dedup();
+        if matches!(result.to_order_status(), Some(OrderStatus::Confirmed) | Some(OrderStatus::PartiallyFilled)) {
+            levels.push((req.get_side(), req.get_order_px()));
+        }
+        self.publish_levels(&req.get_card(), &levels);
+        OrderAck::new(req, result, fills).with_seq(self.seq)
+    }
+
+    /**
+     * Match an order in two steps. The fills are planned on tx_board and
+     * stored along with the statuses and balances they change in a single
+     * write; the boards and the cached balances take the outcome only once
+     * it is stored, so a failed write leaves the order as never processed
+     **/
+    fn match_order(&mut self, req: &RequestOrder, fills: &mut Vec<Fill>) -> ProcessResult {
         let card = req.get_card();
-        self.db.insert_request_table(req);
-
-        if let Some(res) = self.tx_board.get_board_content().get_mut(&card) {
-            proc_res = match req.get_side() {
-                Side::Buy => {
-                    let mut px = 1;
-                    let sell_card_board = res.get_bs_board(Side::Sell);
-                    loop {
-                        if let Some(volume) = sell_card_board.get_mut(&px) {
-                            // Buy order traded
-                            if volume.get_vol() > &0 && req.get_order_px() >= (px as f64) {
-                                let mut uuid: Uuid = Uuid::new_v4();
-                                let mut sell_side_id: i32 = -1;
-
-                                // check if self-traded occurs
-                                if let Some(tag) = volume.get_front_trader() {
-                                    if tag.clone().get_id() == req.get_trade_id() {
-                                        // update status board
-                                        update_untraded_status_board(&mut self.status_board, &mut self.db, &req, OrderStatus::Dropped);
-                                        break ProcessResult::TxSelfTraded;
-                                    }
-                                } else {
-                                    break ProcessResult::TxBoardUpdateFail;
-                                }
-
-                                // update tx_board
-                                volume.set_vol(volume.get_vol() - 1);
-                                if let Some(tag) = volume.pop_trader() {
-                                    uuid = tag.clone().get_uuid();
-                                    sell_side_id = tag.clone().get_id();
-                                } else {
-                                    break ProcessResult::TxBoardUpdateFail;
-                                }
-
-                                update_traded_boards(&mut self.trade_board, &mut self.status_board, &mut self.db, &sell_side_id, &(px as f64), &req, Side::Buy, &uuid, &card);
-                                break ProcessResult::TxFilled;
-                            } else {
-                                px += 1;
-                            }
-                        }
-
-                        if px > 10 {
-                            update_untraded_boards(res, &mut self.status_board, &mut self.db, Side::Buy, req);
-                            break ProcessResult::TxConfirmed;
-                        }
-                    }
-                }
-                Side::Sell => {
-                    let mut px = 10;
-                    let buy_card_board = res.get_bs_board(Side::Buy);
-                    loop {
-                        if let Some(volume) = buy_card_board.get_mut(&px) {
-                            // Sell order traded
-                            if volume.get_vol() > &0 && req.get_order_px() <= (px as f64) {
-                                let mut uuid: Uuid = Uuid::new_v4();
-                                let mut buy_side_id: i32 = -1;
-
-                                // check if self-traded occurs
-                                if let Some(tag) = volume.get_front_trader() {
-                                    if tag.clone().get_id() == req.get_trade_id() {
-                                        // update status board
-                                        update_untraded_status_board(&mut self.status_board, &mut self.db, &req, OrderStatus::Dropped);
-                                        break ProcessResult::TxSelfTraded;
-                                    }
-                                } else {
-                                    break ProcessResult::TxBoardUpdateFail;
-                                }
-
-                                // update tx_board
-                                volume.set_vol(volume.get_vol() - 1);
-                                if let Some(tag) = volume.pop_trader() {
-                                    uuid = tag.clone().get_uuid();
-                                    buy_side_id = tag.clone().get_id();
-                                } else {
-                                    break ProcessResult::TxBoardUpdateFail;
-                                }
-
-                                update_traded_boards(&mut self.trade_board, &mut self.status_board, &mut self.db, &buy_side_id, &(px as f64), &req, Side::Sell, &uuid, &card);
-                                break ProcessResult::TxFilled;
-                            } else {
-                                px -= 1;
-                            }
-                        }
-                        if px < 1 {
-                            update_untraded_boards(res, &mut self.status_board, &mut self.db, Side::Sell, req);
-                            break ProcessResult::TxConfirmed;
-                        }
-                    }
-                }
+        // queued orders have been validated, the grid is checked again as the last line of defence
+        if let Err(reason) = self.tx_board.check_order_px(req) {
+            println!("[{:?}][REJECTED] Card: {:?}, OrderPx: {}, Reason: {:?}", req.get_side(), card, req.get_order_px(), reason);
+            return ProcessResult::TxRejected(reason);
+        }
+        // cash or cards of the order are held until it is filled or withdrawn
+        let reserve_px = self.get_reserve_px(req);
+        let mut changes = self.accounts.begin();
+        if let Err(reason) = changes.reserve(req, &reserve_px, req.get_vol()) {
+            println!("[{:?}][REJECTED] Card: {:?}, OrderPx: {}, Volume: {}, Reason: {:?}", req.get_side(), card, req.get_order_px(), req.get_vol(), reason);
+            return ProcessResult::TxRejected(reason);
+        }
+
+        let res = match self.tx_board.get_board_content().get_mut(&card) {
+            Some(res) => res,
+            None => return ProcessResult::UnknownCard,
+        };
+        let side = req.get_side();
+        let (opposite, pxs): (Side, Vec<Price>) = match side {
+            // a buy order sweeps sell orders from the lowest price
+            Side::Buy => (Side::Sell, res.get_bs_board(Side::Sell).keys().copied().collect()),
+            // a sell order sweeps buy orders from the highest price
+            Side::Sell => (Side::Buy, res.get_bs_board(Side::Buy).keys().rev().copied().collect()),
+        };
+        let (mut matches, self_traded) = get_matches(res, req, opposite, &pxs);
+        let mut remaining = req.get_vol() - matches.iter().map(|m| m.qty).sum::<i32>();
+
+        // a fill-or-kill order never trades unless it could be completely filled
+        let killed = req.get_time_in_force() == TimeInForce::Fok && remaining > 0;
+        if killed {
+            matches.clear();
+            remaining = req.get_vol();
+        }
+        let (result, status) = if killed {
+            (ProcessResult::TxKilled, OrderStatus::Killed)
+        } else if self_traded {
+            // the rest of the order is dropped, even though parts of it have been filled
+            (ProcessResult::TxSelfTraded, OrderStatus::Dropped)
+        } else if remaining == 0 {
+            (ProcessResult::TxFilled, OrderStatus::Filled)
+        } else if !req.is_resting() {
+            // immediate-or-cancel and market orders drop their residual quantity
+            (ProcessResult::TxExpired, OrderStatus::Expired)
+        } else if remaining < req.get_vol() {
+            (ProcessResult::TxPartiallyFilled, OrderStatus::PartiallyFilled)
+        } else {
+            (ProcessResult::TxConfirmed, OrderStatus::Confirmed)
+        };
+
+        let mut writes = vec![Write::Request(*req)];
+        let mut trades = Vec::<Trade>::new();
+        for m in matches.iter() {
+            let trade = get_trade(req, &m.resting, &m.px, &m.qty);
+            let (buy_uuid, sell_uuid) = match side {
+                Side::Buy => (req.get_uuid(), m.resting.clone().get_uuid()),
+                Side::Sell => (m.resting.clone().get_uuid(), req.get_uuid()),
             };
+            writes.push(Write::Trade(buy_uuid, sell_uuid, card, trade.clone()));
+            // transfer cash and cards between the counterparties
+            changes.transfer(req, &m.resting.clone().get_id(), &reserve_px, &m.px, m.qty);
+            writes.push(Write::UpdateStatus(m.resting.clone().get_uuid(), get_resting_status(&m.resting)));
+            trades.push(trade);
+        }
+        writes.push(Write::InsertStatus(req.get_uuid(), status));
+        // the reservation of the quantity which is neither traded nor resting is released
+        if (!req.is_resting() || killed || self_traded) && remaining > 0 {
+            changes.release(req, &reserve_px, remaining);
+        }
+        writes.extend(changes.get_writes());
+
+        if let Err(e) = self.db.write(&writes) {
+            println!("[{:?}][FAILED] Card: {:?}, OrderPx: {}, Volume: {}, TradeId: {}, Error: {}", side, card, req.get_order_px(), req.get_vol(), req.get_trade_id(), e);
+            return ProcessResult::TxStoreFailed;
+        }
+        changes.apply();
+
+        // the stored outcome goes to the boards
+        for (m, trade) in matches.iter().zip(trades) {
+            if res.get_bs_board(opposite).get_mut(&m.px).and_then(|volume| volume.fill_front(m.qty)).is_none() {
+                return ProcessResult::TxBoardUpdateFail;
+            }
+            update_traded_boards(&mut self.trade_board, &mut self.status_board, &m.resting, trade, req, &card);
+            if m.resting.clone().get_vol() == 0 {
+                self.open_orders.close(&m.resting.clone().get_id());
+            }
+            fills.push(Fill::new(m.px, m.qty));
+        }
+        match result {
+            ProcessResult::TxKilled => {
+                update_untraded_status_board(&mut self.status_board, req, OrderStatus::Killed);
+                println!("[{:?}][KILLED] Card: {:?}, OrderPx: {}, Volume: {}, TradeId: {}", side, card, req.get_order_px(), req.get_vol(), req.get_trade_id());
+            }
+            ProcessResult::TxSelfTraded => update_untraded_status_board(&mut self.status_board, req, OrderStatus::Dropped),
+            ProcessResult::TxFilled => update_traded_status_board(req, side, &mut self.status_board, OrderStatus::Filled),
+            ProcessResult::TxExpired => {
+                if remaining < req.get_vol() {
+                    update_traded_status_board(req, side, &mut self.status_board, OrderStatus::Expired);
+                } else {
+                    update_untraded_status_board(&mut self.status_board, req, OrderStatus::Expired);
+                }
+                println!("[{:?}][EXPIRED] Card: {:?}, OrderPx: {}, Volume: {}, Residual: {}, TradeId: {}", side, card, req.get_order_px(), req.get_vol(), remaining, req.get_trade_id());
+            }
+            ProcessResult::TxPartiallyFilled => {
+                // the residual quantity rests on tx_board for later matching
+                update_untraded_tx_board(res, req, side, remaining);
+                update_traded_status_board(req, side, &mut self.status_board, OrderStatus::PartiallyFilled);
+            }
+            _ => update_untraded_boards(res, &mut self.status_board, side, req),
+        }
+        result
+    }
+
+    fn publish_levels(&self, card: &Card, levels: &[(Side, Price)]) {
+        if let Some(card_board) = self.tx_board.get_board(card) {
+            for (side, px) in levels.iter() {
+                self.feed.publish(MarketEvent::Book { card: *card, side: *side, level: card_board.get_book_level(*side, *px) });
+            }
+        }
+    }
+
+    /**
+     * Withdraw a resting order from tx_board on behalf of its owner.
+     * Only orders still queued for matching (confirmed or partially
+     * filled) could be cancelled; the rest are left untouched
+     **/
+    pub fn cancel(&mut self, uuid: &Uuid, trader_id: &i32) -> ProcessResult {
+        let (req, status) = match self.db.find_order(uuid) {
+            Ok(Some(found)) => found,
+            Ok(None) => return ProcessResult::TxNotFound,
+            Err(e) => {
+                println!("[FAILED] Cancel: {}, Error: {}", uuid, e);
+                return ProcessResult::TxStoreFailed;
+            }
+        };
+
+        if req.get_trade_id() != *trader_id {
+            return ProcessResult::TxNotOwner;
+        }
+
+        match status {
+            Some(OrderStatus::Confirmed) | Some(OrderStatus::PartiallyFilled) => {}
+            _ => return ProcessResult::TxNotCancellable,
+        }
+
+        let tag = match self.tx_board.get_board_content().get_mut(&req.get_card()).and_then(|card_board| card_board.get_bs_board(req.get_side()).get(&req.get_order_px())).and_then(|volume| volume.find_trader(uuid)) {
+            Some(tag) => tag.clone(),
+            None => return ProcessResult::TxBoardUpdateFail,
+        };
+        let mut changes = self.accounts.begin();
+        changes.release(&req, &req.get_order_px(), tag.get_vol());
+        let mut writes = vec![Write::UpdateStatus(*uuid, OrderStatus::Cancelled)];
+        writes.extend(changes.get_writes());
+        if let Err(e) = self.db.write(&writes) {
+            println!("[{:?}][FAILED] Card: {:?}, Cancel: {}, Error: {}", req.get_side(), req.get_card(), uuid, e);
+            return ProcessResult::TxStoreFailed;
+        }
+        changes.apply();
+
+        if self.tx_board.remove_tx_req(&req).is_none() {
+            return ProcessResult::TxBoardUpdateFail;
+        }
+        self.status_board.update_status(*trader_id, *uuid, OrderStatus::Cancelled);
+        self.open_orders.close(trader_id);
+        self.publish_levels(&req.get_card(), &[(req.get_side(), req.get_order_px())]);
+        println!(
+            "[{:?}][CANCELLED] Card: {:?}, OrderPx: {}, Volume: {}, TradeId: {}",
+            req.get_side(),
+            req.get_card(),
+            req.get_order_px(),
+            req.get_vol(),
+            req.get_trade_id()
+        );
+        ProcessResult::TxCancelled
+    }
+
+    /**
+     * Replace price and/or quantity of a resting order on behalf of its owner,
+     * where vol is the new total quantity including the filled part. A pure
+     * quantity decrease keeps the queue priority of the order; a price change
+     * or a quantity increase queues it at the back of the (new) price level.
+     * An amendment crossing the opposite side is rejected instead of traded
+     **/
+    pub fn amend(&mut self, uuid: &Uuid, amend: &AmendOrder) -> ProcessResult {
+        self.amend_order(uuid, amend).0
+    }
+
+    // the result along with the stored order at the amended price and volume, once the order is found
+    pub fn amend_order(&mut self, uuid: &Uuid, amend: &AmendOrder) -> (ProcessResult, Option<RequestOrder>) {
+        let (req, status) = match self.db.find_order(uuid) {
+            Ok(Some(found)) => found,
+            Ok(None) => return (ProcessResult::TxNotFound, None),
+            Err(e) => {
+                println!("[FAILED] Amend: {}, Error: {}", uuid, e);
+                return (ProcessResult::TxStoreFailed, None);
+            }
+        };
+
+        let amended = RequestOrder::new(req.get_uuid(), req.get_tm(), req.get_side(), amend.get_order_px(), amend.get_vol(), req.get_card(), req.get_trade_id());
+        if req.get_trade_id() != amend.get_trade_id() {
+            return (ProcessResult::TxNotOwner, Some(amended));
+        }
+
+        match status {
+            Some(OrderStatus::Confirmed) | Some(OrderStatus::PartiallyFilled) => (self.amend_resting(&req, &amended, amend), Some(amended)),
+            _ => (ProcessResult::TxNotAmendable, Some(amended)),
+        }
+    }
+
+    fn amend_resting(&mut self, req: &RequestOrder, amended: &RequestOrder, amend: &AmendOrder) -> ProcessResult {
+        let (uuid, side, card) = (&req.get_uuid(), req.get_side(), req.get_card());
+        let opposite = match side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+
+        let validator = &self.validator;
+        let card_board = match self.tx_board.get_board_content().get_mut(&card) {
+            Some(card_board) => card_board,
+            None => return ProcessResult::UnknownCard,
+        };
+        if let Err(reason) = Validator::check_px(&amend.get_order_px())
+            .and_then(|_| card_board.get_grid().check(&amend.get_order_px()))
+            .and_then(|_| validator.check_vol(&amend.get_vol()))
+        {
+            return ProcessResult::TxRejected(reason);
+        }
+        let tag = match card_board
+            .get_bs_board(side)
+            .get(&req.get_order_px())
+            .and_then(|volume| volume.find_trader(uuid))
+        {
+            Some(tag) => tag.clone(),
+            None => return ProcessResult::TxBoardUpdateFail,
+        };
+
+        let remaining = amend.get_vol() - (req.get_vol() - tag.clone().get_vol());
+        let crossed = card_board
+            .get_bs_board(opposite)
+            .iter()
+            .any(|(px, volume)| volume.get_vol() > &0 && is_tradable(amended, px));
+        if remaining <= 0 || crossed {
+            return ProcessResult::TxAmendRejected;
+        }
+        // the reservation follows the remaining quantity at the new price
+        let mut changes = self.accounts.begin();
+        if let Err(reason) = changes.adjust(req, (req.get_order_px(), tag.clone().get_vol()), (amend.get_order_px(), remaining)) {
+            return ProcessResult::TxRejected(reason);
+        }
+
+        let keep_priority = amend.get_order_px() == req.get_order_px() && remaining <= tag.get_vol();
+        let mut writes = vec![Write::Amend(*uuid, Utc::now(), amend.get_order_px(), amend.get_vol(), keep_priority)];
+        writes.extend(changes.get_writes());
+        if let Err(e) = self.db.write(&writes) {
+            println!("[{:?}][FAILED] Card: {:?}, Amend: {}, Error: {}", side, card, uuid, e);
+            return ProcessResult::TxStoreFailed;
+        }
+        changes.apply();
+
+        if keep_priority {
+            if let Some(volume) = card_board.get_bs_board(side).get_mut(&req.get_order_px()) {
+                volume.resize_trader(uuid, remaining);
+            }
         } else {
-            proc_res = ProcessResult::UnknownCard;
+            if self.tx_board.remove_tx_req(req).is_none() {
+                return ProcessResult::TxBoardUpdateFail;
+            }
+            if let Some(card_board) = self.tx_board.get_board_content().get_mut(&card) {
+                update_untraded_tx_board(card_board, amended, side, remaining);
+            }
         }
-        proc_res
+
+        self.status_board.amend_status(req.get_trade_id(), *uuid, amend.get_order_px(), amend.get_vol());
+        let mut levels = vec![(side, req.get_order_px()), (side, amend.get_order_px())];
+        levels.dedup();
+        self.publish_levels(&card, &levels);
+        println!(
+            "[{:?}][AMENDED] Card: {:?}, OrderPx: {} -> {}, Volume: {} -> {}, TradeId: {}",
+            side,
+            card,
+            req.get_order_px(),
+            amend.get_order_px(),
+            req.get_vol(),
+            amend.get_vol(),
+            req.get_trade_id()
+        );
+        ProcessResult::TxAmended
+    }
+
+    pub fn get_book(&self, card: &Card, depth: usize) -> Option<OrderBook> {
+        self.tx_board.get_book(card, depth)
     }
 
     pub fn get_latest_trades(&self, card: &Card) -> Option<&LinkedList<Trade>> {
@@ -149,119 +453,155 @@ impl Scheduler {
         }
     }
 
-    pub fn get_trade_record(&mut self, id: &i32, date: &str) -> Option<LinkedList<TradeHistory>> {
-        let mut res = LinkedList::<TradeHistory>::new();
-        let history = self.db.get_trade_history(id, date);
-
-        if history.len() > 0 {
-            for row in history {
-                let elem = TradeHistory::new(row.get("buy_side_id"), row.get("sell_side_id"), row.get("tx_price"), row.get("tx_vol"), row.get("card"));
-                res.push_back(elem);
-            }
-            Some(res)
-        } else {
-            None
+    // a store whose connection is lost is opened again, failing while the database stays unreachable
+    pub fn reconnect(&mut self) -> Result<(), String> {
+        if !self.db.is_closed() {
+            return Ok(());
         }
+        self.db = panic::catch_unwind(store::open).map_err(|_| format!("the store of {:?} cannot be opened", self.cards))?;
+        Ok(())
     }
 
-    pub fn get_request_record(&mut self, id: &i32, date: &str) -> Option<LinkedList<RequestOrder>> {
-        let mut res = LinkedList::<RequestOrder>::new();
-        let history = self.db.get_request_history(id, date);
-
-        if history.len() > 0 {
-            for row in history {
-                let elem = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-                res.push_back(elem);
+    /**
+     * Rebuild tx_board from the store after a panic has left it in an
+     * unknown state. The orders resting on it are counted anew, while the
+     * board is kept as it is if the store cannot be read
+     **/
+    pub fn rebuild(&mut self) -> Result<(), String> {
+        let (cards, db) = (self.cards.clone(), &mut self.db);
+        let resting = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut resting = Vec::<RequestOrder>::new();
+            for card in cards.iter() {
+                for side in [Side::Buy, Side::Sell].iter() {
+                    resting.extend(db.get_realtime_tx_info(side, card));
+                }
             }
-            Some(res)
-        } else {
-            None
-        }
-    }
+            resting
+        }))
+        .map_err(|_| format!("the resting orders of {:?} cannot be read", self.cards))?;
 
-    pub fn get_status_record(&mut self, uuid: &Uuid) -> Option<LinkedList<Status>> {
-        let mut res = LinkedList::<Status>::new();
-        let record = self.db.get_status_history(uuid);
-        if record.len() > 0 {
-            for elem in record {
-                let status = Status::new(elem.get("uuid"), elem.get("status"));
-                res.push_back(status);
-            }
-            Some(res)
-        } else {
-            None
+        for trader_id in self.tx_board.get_resting_traders() {
+            self.open_orders.close(&trader_id);
         }
+        let grids: HashMap<Card, PriceGrid> = self.cards.iter().filter_map(|card| self.tx_board.get_grid(card).map(|grid| (*card, *grid))).collect();
+        self.tx_board = TxBoard::for_cards(&self.cards, &grids);
+        for req in resting.iter() {
+            self.tx_board.add_tx_req(req);
+            self.open_orders.open(&req.get_trade_id(), usize::MAX);
+        }
+        Ok(())
     }
 
     /**
      * Recover the untraded, realtime prices/volumes for tx_board
-     * The function is adopted when the program crashes suddenly 
-     * and needs disaster recovering in no time
+     * The function is adopted when the program crashes suddenly
+     * and needs disaster recovering in no time. Only confirmed or
+     * partially filled orders are restored with their remaining
+     * quantities, so cancelled ones never re-enter tx_board
      **/
     pub fn recover(&mut self) {
-        let mut res: Vec<Row>;
-        res = self.db.get_realtime_tx_info(&Side::Buy, &Card::Bulbasaur);
-        for row in res {
-            let req = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-            self.tx_board.add_tx_req(&req);
+        for card in self.cards.clone().iter() {
+            for side in [Side::Buy, Side::Sell].iter() {
+                for req in self.db.get_realtime_tx_info(side, card) {
+                    self.tx_board.add_tx_req(&req);
+                    self.open_orders.open(&req.get_trade_id(), usize::MAX);
+                }
+            }
+            // current candles carry on with the trades made before the crash
+            let now = Utc::now();
+            for interval in get_intervals().iter() {
+                let start = interval.get_start(&now);
+                let end = start + Duration::seconds(interval.get_secs());
+                if let Some(candle) = self.db.get_candles(card, &interval.get_secs(), &start, &end).first() {
+                    self.trade_board.get_candles_mut().set_latest(card, interval, *candle);
+                }
+            }
         }
+    }
+}
 
-        res = self.db.get_realtime_tx_info(&Side::Sell, &Card::Bulbasaur);
-        for row in res {
-            let req = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-            self.tx_board.add_tx_req(&req);
+/**
+ * Price grids of cards configured in the config file given by the
+ * command line, or the default ones when running without it
+ **/
+fn get_price_grids() -> HashMap<Card, PriceGrid> {
+    let mut grids = HashMap::new();
+    if let Some(config) = env::args().nth(1) {
+        let cfg = Settings::new(config);
+        for card in [Card::Pikachu, Card::Bulbasaur, Card::Charmander, Card::Squirtle].iter() {
+            grids.insert(*card, cfg.get_price_grid(card));
         }
+    }
+    grids
+}
 
-        res = self.db.get_realtime_tx_info(&Side::Buy, &Card::Charmander);
-        for row in res {
-            let req = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-            self.tx_board.add_tx_req(&req);
-        }
+// order limits configured in the config file, or the default ones
+fn get_validator() -> Validator {
+    match env::args().nth(1) {
+        Some(config) => Settings::new(config).get_validator(),
+        None => Validator::default(),
+    }
+}
 
-        res = self.db.get_realtime_tx_info(&Side::Sell, &Card::Charmander);
-        for row in res {
-            let req = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-            self.tx_board.add_tx_req(&req);
-        }
+fn is_tradable(req: &RequestOrder, px: &Price) -> bool {
+    if req.get_order_type() == OrderType::Market {
+        return true;
+    }
 
-        res = self.db.get_realtime_tx_info(&Side::Buy, &Card::Pikachu);
-        for row in res {
-            let req = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-            self.tx_board.add_tx_req(&req);
-        }
+    match req.get_side() {
+        Side::Buy => req.get_order_px() >= *px,
+        Side::Sell => req.get_order_px() <= *px,
+    }
+}
 
-        res = self.db.get_realtime_tx_info(&Side::Sell, &Card::Pikachu);
-        for row in res {
-            let req = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-            self.tx_board.add_tx_req(&req);
-        }
+// a fill planned against a resting order, where resting is the order after the fill
+struct Match {
+    px: Price,
+    resting: Tag,
+    qty: i32,
+}
 
-        res = self.db.get_realtime_tx_info(&Side::Buy, &Card::Squirtle);
-        for row in res {
-            let req = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-            self.tx_board.add_tx_req(&req);
+/**
+ * Fills the order would get against the opposite side right now, in the
+ * order they are taken off tx_board, and whether the sweep stopped at an
+ * order of the same trader, where the rest of the order is dropped
+ **/
+fn get_matches(board: &mut CardBoard, req: &RequestOrder, opposite: Side, pxs: &[Price]) -> (Vec<Match>, bool) {
+    let mut matches = Vec::<Match>::new();
+    let mut remaining = req.get_vol();
+    let opposite_board = board.get_bs_board(opposite);
+    for px in pxs {
+        if remaining == 0 || !is_tradable(req, px) {
+            break;
         }
 
-        res = self.db.get_realtime_tx_info(&Side::Sell, &Card::Squirtle);
-        for row in res {
-            let req = RequestOrder::new(row.get("uuid"), row.get("tm"), row.get("side"), row.get("order_px"), row.get("vol"), row.get("card"), row.get("trader_id"));
-            self.tx_board.add_tx_req(&req);
+        if let Some(volume) = opposite_board.get(px) {
+            for tag in volume.get_traders() {
+                if remaining == 0 {
+                    break;
+                }
+                // check if self-traded occurs
+                if tag.clone().get_id() == req.get_trade_id() {
+                    return (matches, true);
+                }
+                let qty = cmp::min(remaining, tag.clone().get_vol());
+                let resting = Tag::new(tag.clone().get_id(), tag.clone().get_uuid(), tag.clone().get_vol() - qty);
+                matches.push(Match { px: *px, resting, qty });
+                remaining -= qty;
+            }
         }
     }
+    (matches, false)
 }
 
-pub fn update_untraded_tx_board(board: &mut CardBoard, req: &RequestOrder, side: Side) {
-    let card_board = board.get_bs_board(side);
-    let tag = Tag::new(req.get_trade_id(), req.get_uuid());
-    if let Some(cur_vol) =
-        card_board.get_mut(&(req.get_order_px() as i32))
-    {
-        cur_vol.set_vol(cur_vol.get_vol() + req.get_vol());
-        cur_vol.push_trader(tag);
-    }          
+pub fn update_untraded_tx_board(board: &mut CardBoard, req: &RequestOrder, side: Side, vol: i32) {
+    let cur_vol = board.get_level(side, req.get_order_px());
+    let tag = Tag::new(req.get_trade_id(), req.get_uuid(), vol);
+    cur_vol.set_vol(cur_vol.get_vol() + vol);
+    cur_vol.push_trader(tag);
 }
 
-pub fn update_untraded_status_board(board: &mut StatusBoard, db: &mut Database, req: &RequestOrder, status: OrderStatus) {
+pub fn update_untraded_status_board(board: &mut StatusBoard, req: &RequestOrder, status: OrderStatus) {
     let stats = Stats::new(
         req.get_uuid(),
         req.get_tm(),
@@ -269,19 +609,19 @@ pub fn update_untraded_status_board(board: &mut StatusBoard, db: &mut Database,
         req.get_order_px(),
         req.get_vol(),
         req.get_card(),
-        status.clone(),
+        status,
     );
     board.add_status(req.get_trade_id(), req.get_uuid(), stats);
-    db.insert_order_status(&req.get_uuid(), &status);
 }
 
-pub fn update_untraded_boards(card_board: &mut CardBoard, status_board: &mut StatusBoard, db: &mut Database, side: Side, req: &RequestOrder) {
+pub fn update_untraded_boards(card_board: &mut CardBoard, status_board: &mut StatusBoard, side: Side, req: &RequestOrder) {
     // update tx_board
-    update_untraded_tx_board(card_board, req, side);
+    update_untraded_tx_board(card_board, req, side, req.get_vol());
     // update status board
-    update_untraded_status_board(status_board, db, req, OrderStatus::Confirmed);
+    update_untraded_status_board(status_board, req, OrderStatus::Confirmed);
     println!(
-        "[BUY][CONFIRMED] Card: {:?}, OrderPx: {}, Volume: {}, TradeId: {}",
+        "[{:?}][CONFIRMED] Card: {:?}, OrderPx: {}, Volume: {}, TradeId: {}",
+        side,
         req.get_card(),
         req.get_order_px(),
         req.get_vol(),
@@ -289,58 +629,44 @@ pub fn update_untraded_boards(card_board: &mut CardBoard, status_board: &mut Sta
     );
 }
 
-pub fn update_traded_trade_board(trader_id: &i32, traded_px: &f64, req: &RequestOrder, board: &mut TradeBoard, db: &mut Database, uuid: &Uuid, card: &Card) {
-    let trade = match req.get_side() {
-        Side::Buy => {
-            Trade::new(
-                Utc::now(),
-                req.get_trade_id(),
-                *trader_id,
-                *traded_px,
-                req.get_vol(),
-            )
-        },
-        Side::Sell => {
-            Trade::new(
-                Utc::now(),
-                *trader_id,
-                req.get_trade_id(),
-                *traded_px,
-                req.get_vol(),
-            )
-        },
-        _ => {
-            Trade::new(
-                Utc::now(),
-                req.get_trade_id(),
-                *trader_id,
-                *traded_px,
-                req.get_vol(),
-            )
-        }
+fn get_trade(req: &RequestOrder, resting: &Tag, traded_px: &Price, traded_vol: &i32) -> Trade {
+    let (buy_side_id, sell_side_id) = match req.get_side() {
+        Side::Buy => (req.get_trade_id(), resting.clone().get_id()),
+        Side::Sell => (resting.clone().get_id(), req.get_trade_id()),
     };
-    board.add_trade(&req.get_card(), trade);
-    db.insert_trade_table(
-        &uuid,
-        &req.get_uuid(),
-        trader_id,
-        &req.get_trade_id(),
-        &traded_px,
-        &req.get_vol(),
-        &card,
-    );
+    Trade::new(
+        Utc::now(),
+        buy_side_id,
+        sell_side_id,
+        *traded_px,
+        *traded_vol,
+    )
+}
+
+pub fn update_traded_trade_board(board: &mut TradeBoard, trade: Trade, card: &Card) {
+    // the current candles of the card go live with the trade
+    board.get_candles_mut().add_trade(card, &trade);
+    board.add_trade(card, trade);
 }
 
-pub fn update_opposite_status_board(board: &mut StatusBoard, db: &mut Database, opposite: &i32, uuid: &Uuid) {
+// the resting order keeps queued on tx_board until its quantity is exhausted
+fn get_resting_status(resting: &Tag) -> OrderStatus {
+    if resting.clone().get_vol() == 0 {
+        OrderStatus::Filled
+    } else {
+        OrderStatus::PartiallyFilled
+    }
+}
+
+pub fn update_opposite_status_board(board: &mut StatusBoard, resting: &Tag) {
     board.update_status(
-        *opposite,
-        *uuid,
-        OrderStatus::Filled,
+        resting.clone().get_id(),
+        resting.clone().get_uuid(),
+        get_resting_status(resting),
     );
-    db.update_order_status(&uuid, &OrderStatus::Filled);
 }
 
-pub fn update_traded_status_board(req: &RequestOrder, side: Side, board: &mut StatusBoard, db: &mut Database) {
+pub fn update_traded_status_board(req: &RequestOrder, side: Side, board: &mut StatusBoard, status: OrderStatus) {
     let stats = Stats::new(
         req.get_uuid(),
         Utc::now(),
@@ -348,38 +674,41 @@ pub fn update_traded_status_board(req: &RequestOrder, side: Side, board: &mut St
         req.get_order_px(),
         req.get_vol(),
         req.get_card(),
-        OrderStatus::Filled,
+        status,
     );
     board.add_status(
         req.get_trade_id(),
         req.get_uuid(),
         stats,
     );
-    db.insert_order_status(&req.get_uuid(), &OrderStatus::Filled);
 }
 
-pub fn update_traded_boards(trade_board: &mut TradeBoard, status_board: &mut StatusBoard, db: &mut Database, id: &i32, traded_px: &f64, req: &RequestOrder, side: Side, uuid: &Uuid, card: &Card) {
+pub fn update_traded_boards(trade_board: &mut TradeBoard, status_board: &mut StatusBoard, resting: &Tag, trade: Trade, req: &RequestOrder, card: &Card) {
+    let (traded_px, traded_vol) = (*trade.get_tx_price(), *trade.get_tx_vol());
     // update trade_board
-    update_traded_trade_board(id, traded_px, req, trade_board, db, uuid, card);
-    // update status board
+    update_traded_trade_board(trade_board, trade, card);
     // update opposite-side's status_board (update)
-    update_opposite_status_board(status_board, db, id, uuid);
-    // update self-side's status board (add)
-    update_traded_status_board(req, side, status_board, db);
+    update_opposite_status_board(status_board, resting);
     println!(
-        "[SELL][FILLED] Card: {:?}, TxPrice: {}, TxVol: {}",
+        "[{:?}][FILLED] Card: {:?}, TxPrice: {}, TxVol: {}",
+        req.get_side(),
         &card,
         &traded_px,
-        req.get_vol()
+        traded_vol
     );    
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::data_type::{Card, OrderStatus, ProcessResult, RequestOrder, Side};
+    use crate::account::Accounts;
+    use crate::data_type::{AmendOrder, Card, CardTransfer, CashTransfer, Fill, LedgerEntry, OrderStatus, OrderType, Price, ProcessResult, RejectReason, RequestOrder, Side, TimeInForce};
     use crate::status_board::Stats;
+    use crate::store::MemoryStore;
     use crate::trade_board::Trade;
+    use crate::tx_board::{PriceGrid, TxBoard};
+    use crate::validator::Validator;
     use crate::Scheduler;
+    use std::collections::HashMap;
     use chrono::Utc;
     use uuid::Uuid;
 
@@ -422,7 +751,7 @@ mod tests {
         {
             if let Some(volume) = card_board
                 .get_bs_board(req.get_side())
-                .get_mut(&(req.get_order_px() as i32))
+                .get_mut(&req.get_order_px())
             {
                 assert_eq!(true, volume.get_vol() > &0);
                 assert_eq!(1, volume.get_trader_nums());
@@ -575,7 +904,7 @@ mod tests {
         {
             if let Some(volume) = card_board
                 .get_bs_board(req.get_side())
-                .get_mut(&(req.get_order_px() as i32))
+                .get_mut(&req.get_order_px())
             {
                 assert_eq!(true, volume.get_vol() > &0);
                 assert_eq!(1, volume.get_trader_nums());
@@ -764,7 +1093,7 @@ mod tests {
             .get_board_content()
             .get_mut(&Card::Bulbasaur)
         {
-            if let Some(volume) = board.get_bs_board(Side::Sell).get_mut(&6) {
+            if let Some(volume) = board.get_bs_board(Side::Sell).get_mut(&Price::from(6.00)) {
                 prev_vol = *volume.get_vol();
                 prev_trader_cnt = volume.get_trader_nums();
             } else {
@@ -785,7 +1114,7 @@ mod tests {
             .get_board_content()
             .get_mut(&Card::Bulbasaur)
         {
-            if let Some(volume) = board.get_bs_board(Side::Sell).get_mut(&6) {
+            if let Some(volume) = board.get_bs_board(Side::Sell).get_mut(&Price::from(6.00)) {
                 cur_vol = *volume.get_vol();
                 cur_trader_cnt = volume.get_trader_nums();
             } else {
@@ -869,7 +1198,7 @@ mod tests {
         assert_ne!(cur_trade, prev_trade);
         assert_eq!(&6, cur_trade.get_buy_side_id());
         assert_eq!(&5, cur_trade.get_sell_side_id());
-        assert_eq!(&6.00, cur_trade.get_tx_price());
+        assert_eq!(&Price::from(6.00), cur_trade.get_tx_price());
         assert_eq!(&1, cur_trade.get_tx_vol());
     }
 
@@ -1006,7 +1335,7 @@ mod tests {
         {
             if let Some(volume) = card_board
                 .get_bs_board(req.get_side())
-                .get_mut(&(req.get_order_px() as i32))
+                .get_mut(&req.get_order_px())
             {
                 assert_eq!(true, volume.get_vol() > &0);
                 assert_eq!(1, volume.get_trader_nums());
@@ -1159,7 +1488,7 @@ mod tests {
         {
             if let Some(volume) = card_board
                 .get_bs_board(req.get_side())
-                .get_mut(&(req.get_order_px() as i32))
+                .get_mut(&req.get_order_px())
             {
                 assert_eq!(true, volume.get_vol() > &0);
                 assert_eq!(1, volume.get_trader_nums());
@@ -1348,7 +1677,7 @@ mod tests {
             .get_board_content()
             .get_mut(&Card::Bulbasaur)
         {
-            if let Some(volume) = board.get_bs_board(Side::Buy).get_mut(&5) {
+            if let Some(volume) = board.get_bs_board(Side::Buy).get_mut(&Price::from(5.00)) {
                 prev_vol = *volume.get_vol();
                 prev_trader_cnt = volume.get_trader_nums();
             } else {
@@ -1369,7 +1698,7 @@ mod tests {
             .get_board_content()
             .get_mut(&Card::Bulbasaur)
         {
-            if let Some(volume) = board.get_bs_board(Side::Buy).get_mut(&5) {
+            if let Some(volume) = board.get_bs_board(Side::Buy).get_mut(&Price::from(5.00)) {
                 cur_vol = *volume.get_vol();
                 cur_trader_cnt = volume.get_trader_nums();
             } else {
@@ -1453,7 +1782,7 @@ mod tests {
         assert_ne!(cur_trade, prev_trade);
         assert_eq!(&5, cur_trade.get_buy_side_id());
         assert_eq!(&6, cur_trade.get_sell_side_id());
-        assert_eq!(&5.00, cur_trade.get_tx_price());
+        assert_eq!(&Price::from(5.00), cur_trade.get_tx_price());
         assert_eq!(&1, cur_trade.get_tx_vol());
     }
 
@@ -1622,4 +1951,553 @@ mod tests {
         assert_eq!(true, scheduler.db.request_exist(&uuid));
         assert_eq!(OrderStatus::Dropped, scheduler.db.get_order_status(&uuid));
     }
+
+    #[test]
+    fn given_a_confirmed_order_when_cancelled_by_its_owner_then_removed_from_tx_board_and_status_cancelled() {
+        let mut scheduler = Scheduler::new();
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.00, 1, Card::Squirtle, 11);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&req));
+
+        assert_eq!(ProcessResult::TxCancelled, scheduler.cancel(&req.get_uuid(), &req.get_trade_id()));
+        assert_eq!(OrderStatus::Cancelled, scheduler.db.get_order_status(&req.get_uuid()));
+        if let Some(res) = scheduler.status_board.get_stat(&req.get_trade_id(), &req.get_uuid()) {
+            assert_eq!(&OrderStatus::Cancelled, res.get_status());
+        } else {
+            panic!("[ERROR] Test Failed: stat does not exist in status_board");
+        }
+
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&req.get_card()) {
+            if let Some(volume) = card_board.get_bs_board(req.get_side()).get_mut(&Price::from(3.00)) {
+                assert_eq!(None, volume.find_trader(&req.get_uuid()));
+            } else {
+                panic!("[ERROR] Test Failed: Volume does not exist in card board.");
+            }
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+
+        // a cancelled order could not be cancelled again
+        assert_eq!(ProcessResult::TxNotCancellable, scheduler.cancel(&req.get_uuid(), &req.get_trade_id()));
+    }
+
+    #[test]
+    fn given_a_confirmed_order_when_cancelled_by_another_trader_then_rejected_and_kept_in_tx_board() {
+        let mut scheduler = Scheduler::new();
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 9.00, 1, Card::Squirtle, 12);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&req));
+
+        assert_eq!(ProcessResult::TxNotOwner, scheduler.cancel(&req.get_uuid(), &13));
+        assert_eq!(OrderStatus::Confirmed, scheduler.db.get_order_status(&req.get_uuid()));
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&req.get_card()) {
+            if let Some(volume) = card_board.get_bs_board(req.get_side()).get_mut(&Price::from(9.00)) {
+                assert!(volume.find_trader(&req.get_uuid()).is_some());
+            } else {
+                panic!("[ERROR] Test Failed: Volume does not exist in card board.");
+            }
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+    }
+
+    #[test]
+    fn given_an_unknown_uuid_when_cancelled_then_not_found() {
+        let mut scheduler = Scheduler::new();
+        assert_eq!(ProcessResult::TxNotFound, scheduler.cancel(&Uuid::new_v4(), &1));
+    }
+
+    #[test]
+    fn given_a_filled_order_when_cancelled_then_not_cancellable() {
+        let mut scheduler = Scheduler::new();
+        let sell_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 1.00, 1, Card::Charmander, 14);
+        let buy_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 1.00, 1, Card::Charmander, 15);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&sell_req));
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&buy_req));
+
+        assert_eq!(ProcessResult::TxNotCancellable, scheduler.cancel(&sell_req.get_uuid(), &14));
+        assert_eq!(ProcessResult::TxNotCancellable, scheduler.cancel(&buy_req.get_uuid(), &15));
+    }
+
+    #[test]
+    fn given_a_cancelled_order_when_scheduler_recovered_then_it_is_not_queued_in_tx_board() {
+        // the recovered scheduler reads the tables the first one writes
+        let tables = MemoryStore::new();
+        let mut scheduler = Scheduler::new();
+        scheduler.db = Box::new(tables.clone());
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, 16);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&req));
+        assert_eq!(ProcessResult::TxCancelled, scheduler.cancel(&req.get_uuid(), &req.get_trade_id()));
+
+        let mut recovered = Scheduler::new();
+        recovered.db = Box::new(tables);
+        recovered.recover();
+        if let Some(card_board) = recovered.tx_board.get_board_content().get_mut(&req.get_card()) {
+            assert_eq!(None, card_board.get_bs_board(req.get_side()).get(&Price::from(2.00)).and_then(|volume| volume.find_trader(&req.get_uuid())));
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+    }
+
+    #[test]
+    fn given_there_are_sell_orders_on_several_prices_when_a_multi_unit_buy_order_received_then_filled_across_levels() {
+        let mut scheduler = Scheduler::new();
+        for i in 6..9 {
+            let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, i as f64, 1, Card::Pikachu, i);
+            assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&req));
+        }
+
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 8.00, 3, Card::Pikachu, 20);
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&req));
+        assert_eq!(OrderStatus::Filled, scheduler.db.get_order_status(&req.get_uuid()));
+
+        // one trade per fill, swept from the lowest sell price
+        if let Some(trades) = scheduler.get_latest_trades(&Card::Pikachu) {
+            let prices: Vec<Price> = trades.iter().map(|trade| *trade.get_tx_price()).collect();
+            assert_eq!(vec![Price::from(6.00), Price::from(7.00), Price::from(8.00)], prices);
+            assert!(trades.iter().all(|trade| trade.get_tx_vol() == &1 && trade.get_buy_side_id() == &20));
+        } else {
+            panic!("[ERROR] Test Failed: Trade list does not exist");
+        }
+
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&Card::Pikachu) {
+            for px in 6..9 {
+                if let Some(volume) = card_board.get_bs_board(Side::Sell).get_mut(&Price::from(px as f64)) {
+                    assert_eq!(&0, volume.get_vol());
+                    assert_eq!(0, volume.get_trader_nums());
+                } else {
+                    panic!("[ERROR] Test Failed: Volume does not exist in card board.");
+                }
+            }
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+    }
+
+    #[test]
+    fn given_sell_orders_cannot_cover_a_buy_order_when_received_then_residual_rests_as_partially_filled() {
+        let mut scheduler = Scheduler::new();
+        let first = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 6.00, 2, Card::Pikachu, 21);
+        let second = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 8.00, 1, Card::Pikachu, 22);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&first));
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&second));
+
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 7.00, 5, Card::Pikachu, 23);
+        assert_eq!(ProcessResult::TxPartiallyFilled, scheduler.process(&req));
+        assert_eq!(OrderStatus::PartiallyFilled, scheduler.db.get_order_status(&req.get_uuid()));
+        assert_eq!(OrderStatus::Filled, scheduler.db.get_order_status(&first.get_uuid()));
+        assert_eq!(OrderStatus::Confirmed, scheduler.db.get_order_status(&second.get_uuid()));
+
+        if let Some(trade) = scheduler.trade_board.get_back_trade(&Card::Pikachu) {
+            assert_eq!(&Price::from(6.00), trade.get_tx_price());
+            assert_eq!(&2, trade.get_tx_vol());
+        } else {
+            panic!("[ERROR] Test Failed: Trade does not exist");
+        }
+
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&Card::Pikachu) {
+            if let Some(volume) = card_board.get_bs_board(Side::Buy).get_mut(&Price::from(7.00)) {
+                assert_eq!(&3, volume.get_vol());
+                if let Some(tag) = volume.find_trader(&req.get_uuid()) {
+                    assert_eq!(3, tag.clone().get_vol());
+                } else {
+                    panic!("[ERROR] Test Failed: Tag does not exist.");
+                }
+            } else {
+                panic!("[ERROR] Test Failed: Volume does not exist in card board.");
+            }
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+    }
+
+    #[test]
+    fn given_a_multi_unit_buy_order_rests_when_a_smaller_sell_order_received_then_resting_order_partially_filled_and_recoverable() {
+        // the recovered scheduler reads the tables the first one writes
+        let tables = MemoryStore::new();
+        let mut scheduler = Scheduler::new();
+        scheduler.db = Box::new(tables.clone());
+        let buy_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 4.00, 3, Card::Charmander, 24);
+        let sell_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 4.00, 1, Card::Charmander, 25);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&buy_req));
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&sell_req));
+
+        assert_eq!(OrderStatus::PartiallyFilled, scheduler.db.get_order_status(&buy_req.get_uuid()));
+        if let Some(res) = scheduler.status_board.get_stat(&24, &buy_req.get_uuid()) {
+            assert_eq!(&OrderStatus::PartiallyFilled, res.get_status());
+        } else {
+            panic!("[ERROR] Test Failed: stat does not exist in status_board");
+        }
+
+        // the remaining quantity is restored after recovery
+        let mut recovered = Scheduler::new();
+        recovered.db = Box::new(tables);
+        recovered.recover();
+        if let Some(card_board) = recovered.tx_board.get_board_content().get_mut(&Card::Charmander) {
+            if let Some(volume) = card_board.get_bs_board(Side::Buy).get_mut(&Price::from(4.00)) {
+                if let Some(tag) = volume.find_trader(&buy_req.get_uuid()) {
+                    assert_eq!(2, tag.clone().get_vol());
+                } else {
+                    panic!("[ERROR] Test Failed: partially filled order is not recovered.");
+                }
+            } else {
+                panic!("[ERROR] Test Failed: Volume does not exist in card board.");
+            }
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+
+        // the rest of a partially filled order could still be cancelled
+        assert_eq!(ProcessResult::TxCancelled, scheduler.cancel(&buy_req.get_uuid(), &24));
+    }
+
+    #[test]
+    fn given_two_buy_orders_on_a_price_when_the_front_one_decreases_quantity_then_its_priority_kept() {
+        let mut scheduler = Scheduler::new();
+        let first = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.00, 3, Card::Squirtle, 30);
+        let second = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.00, 1, Card::Squirtle, 31);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&first));
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&second));
+
+        let amend = AmendOrder::new(30, 5.00, 2);
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&first.get_uuid(), &amend));
+
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&Card::Squirtle) {
+            if let Some(volume) = card_board.get_bs_board(Side::Buy).get_mut(&Price::from(5.00)) {
+                assert_eq!(&3, volume.get_vol());
+                if let Some(tag) = volume.pop_trader() {
+                    assert_eq!(first.get_uuid(), tag.clone().get_uuid());
+                    assert_eq!(2, tag.get_vol());
+                } else {
+                    panic!("[ERROR] Test Failed: Tag does not exist.");
+                }
+            } else {
+                panic!("[ERROR] Test Failed: Volume does not exist in card board.");
+            }
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+
+        if let Some(res) = scheduler.status_board.get_stat(&30, &first.get_uuid()) {
+            assert_eq!(&2, res.get_vol());
+        } else {
+            panic!("[ERROR] Test Failed: stat does not exist in status_board");
+        }
+    }
+
+    #[test]
+    fn given_a_buy_order_when_its_price_amended_then_queued_at_the_back_of_new_price_and_recoverable() {
+        // the recovered scheduler reads the tables the first one writes
+        let tables = MemoryStore::new();
+        let mut scheduler = Scheduler::new();
+        scheduler.db = Box::new(tables.clone());
+        let first = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.00, 1, Card::Squirtle, 32);
+        let second = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 1, Card::Squirtle, 33);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&first));
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&second));
+
+        let amend = AmendOrder::new(32, 2.00, 1);
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&first.get_uuid(), &amend));
+
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&Card::Squirtle) {
+            if let Some(volume) = card_board.get_bs_board(Side::Buy).get_mut(&Price::from(3.00)) {
+                assert_eq!(None, volume.find_trader(&first.get_uuid()));
+            } else {
+                panic!("[ERROR] Test Failed: Volume does not exist in card board.");
+            }
+            if let Some(volume) = card_board.get_bs_board(Side::Buy).get_mut(&Price::from(2.00)) {
+                assert_eq!(2, volume.get_trader_nums());
+                assert_eq!(Some(second.get_uuid()), volume.get_front_trader().map(|tag| tag.clone().get_uuid()));
+                assert!(volume.find_trader(&first.get_uuid()).is_some());
+            } else {
+                panic!("[ERROR] Test Failed: Volume does not exist in card board.");
+            }
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+
+        // recovery restores the amended terms of the order
+        let mut recovered = Scheduler::new();
+        recovered.db = Box::new(tables);
+        recovered.recover();
+        if let Some(card_board) = recovered.tx_board.get_board_content().get_mut(&Card::Squirtle) {
+            assert_eq!(None, card_board.get_bs_board(Side::Buy).get(&Price::from(3.00)).and_then(|volume| volume.find_trader(&first.get_uuid())));
+            assert!(card_board.get_bs_board(Side::Buy).get(&Price::from(2.00)).and_then(|volume| volume.find_trader(&first.get_uuid())).is_some());
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+
+        // the cancellation follows the amended price
+        assert_eq!(ProcessResult::TxCancelled, scheduler.cancel(&first.get_uuid(), &32));
+    }
+
+    #[test]
+    fn given_a_sell_order_when_amended_to_cross_buy_orders_or_by_another_trader_then_rejected() {
+        let mut scheduler = Scheduler::new();
+        let buy_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 4.00, 1, Card::Bulbasaur, 34);
+        let sell_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 6.00, 1, Card::Bulbasaur, 35);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&buy_req));
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&sell_req));
+
+        assert_eq!(ProcessResult::TxAmendRejected, scheduler.amend(&sell_req.get_uuid(), &AmendOrder::new(35, 4.00, 1)));
+        assert_eq!(ProcessResult::TxRejected(RejectReason::InvalidVolume), scheduler.amend(&sell_req.get_uuid(), &AmendOrder::new(35, 6.00, 0)));
+        assert_eq!(ProcessResult::TxNotOwner, scheduler.amend(&sell_req.get_uuid(), &AmendOrder::new(34, 5.00, 1)));
+        assert_eq!(ProcessResult::TxNotFound, scheduler.amend(&Uuid::new_v4(), &AmendOrder::new(35, 5.00, 1)));
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&sell_req.get_uuid(), &AmendOrder::new(35, 5.00, 1)));
+    }
+
+    #[test]
+    fn given_an_order_amended_when_request_history_queried_then_the_amendment_chain_shown() {
+        let mut scheduler = Scheduler::new();
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 9.00, 2, Card::Charmander, 36);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&req));
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&req.get_uuid(), &AmendOrder::new(36, 9.00, 1)));
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&req.get_uuid(), &AmendOrder::new(36, 8.00, 1)));
+
+        let date = req.get_tm().format("%Y-%m-%d").to_string();
+        let chain: Vec<(Price, i32)> = scheduler
+            .db
+            .get_request_history(&36, &date)
+            .iter()
+            .filter(|elem| elem.get_uuid() == req.get_uuid())
+            .map(|elem| (elem.get_order_px(), elem.get_vol()))
+            .collect();
+        assert_eq!(vec![(Price::from(9.00), 2), (Price::from(9.00), 1), (Price::from(8.00), 1)], chain);
+    }
+
+    #[test]
+    fn given_a_sell_order_when_a_larger_ioc_buy_order_received_then_residual_expired_instead_of_queued() {
+        let mut scheduler = Scheduler::new();
+        let sell_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 5.00, 1, Card::Bulbasaur, 40);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&sell_req));
+
+        let buy_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.00, 3, Card::Bulbasaur, 41).with_time_in_force(TimeInForce::Ioc);
+        assert_eq!(ProcessResult::TxExpired, scheduler.process(&buy_req));
+        assert_eq!(OrderStatus::Expired, scheduler.db.get_order_status(&buy_req.get_uuid()));
+        assert_eq!(OrderStatus::Filled, scheduler.db.get_order_status(&sell_req.get_uuid()));
+
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&Card::Bulbasaur) {
+            assert_eq!(None, card_board.get_bs_board(Side::Buy).get(&Price::from(5.00)).and_then(|volume| volume.find_trader(&buy_req.get_uuid())));
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+
+        // an unmatched immediate-or-cancel order expires as a whole
+        let ioc_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 9.00, 1, Card::Bulbasaur, 41).with_time_in_force(TimeInForce::Ioc);
+        assert_eq!(ProcessResult::TxExpired, scheduler.process(&ioc_req));
+        assert_eq!(OrderStatus::Expired, scheduler.db.get_order_status(&ioc_req.get_uuid()));
+    }
+
+    #[test]
+    fn given_sell_orders_when_fok_buy_orders_received_then_killed_unless_fully_fillable() {
+        let mut scheduler = Scheduler::new();
+        let first = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 5.00, 1, Card::Bulbasaur, 42);
+        let second = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 6.00, 1, Card::Bulbasaur, 43);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&first));
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&second));
+
+        let killed = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 6.00, 3, Card::Bulbasaur, 44).with_time_in_force(TimeInForce::Fok);
+        assert_eq!(ProcessResult::TxKilled, scheduler.process(&killed));
+        assert_eq!(OrderStatus::Killed, scheduler.db.get_order_status(&killed.get_uuid()));
+        assert_eq!(OrderStatus::Confirmed, scheduler.db.get_order_status(&first.get_uuid()));
+        assert_eq!(None, scheduler.trade_board.get_back_trade(&Card::Bulbasaur));
+
+        let filled = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 6.00, 2, Card::Bulbasaur, 44).with_time_in_force(TimeInForce::Fok);
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&filled));
+        assert_eq!(OrderStatus::Filled, scheduler.db.get_order_status(&second.get_uuid()));
+    }
+
+    #[test]
+    fn given_sell_orders_when_a_market_buy_order_received_then_best_levels_swept_regardless_of_order_px() {
+        let mut scheduler = Scheduler::new();
+        for px in 8..11 {
+            let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, px as f64, 1, Card::Charmander, 45);
+            assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&req));
+        }
+
+        let buy_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 1.00, 2, Card::Charmander, 46).with_order_type(OrderType::Market);
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&buy_req));
+        if let Some(trades) = scheduler.get_latest_trades(&Card::Charmander) {
+            let prices: Vec<Price> = trades.iter().map(|trade| *trade.get_tx_price()).collect();
+            assert_eq!(vec![Price::from(8.00), Price::from(9.00)], prices);
+        } else {
+            panic!("[ERROR] Test Failed: Trade list does not exist");
+        }
+
+        // a market order never rests, even without any opposite orders
+        let sell_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 1.00, 1, Card::Charmander, 46).with_order_type(OrderType::Market);
+        assert_eq!(ProcessResult::TxExpired, scheduler.process(&sell_req));
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&Card::Charmander) {
+            assert_eq!(None, card_board.get_bs_board(Side::Sell).get(&Price::from(1.00)).and_then(|volume| volume.find_trader(&sell_req.get_uuid())));
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+    }
+
+    #[test]
+    fn given_a_decimal_buy_order_when_received_then_queued_at_its_exact_price_and_traded_there() {
+        let mut scheduler = Scheduler::new();
+        let buy_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.75, 1, Card::Pikachu, 47);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&buy_req));
+
+        if let Some(card_board) = scheduler.tx_board.get_board_content().get_mut(&Card::Pikachu) {
+            assert!(card_board.get_bs_board(Side::Buy).get(&Price::from(3.75)).and_then(|volume| volume.find_trader(&buy_req.get_uuid())).is_some());
+            assert_eq!(None, card_board.get_bs_board(Side::Buy).get(&Price::from(3.00)).and_then(|volume| volume.find_trader(&buy_req.get_uuid())));
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+
+        let sell_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 3.70, 1, Card::Pikachu, 48);
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&sell_req));
+        if let Some(trade) = scheduler.trade_board.get_back_trade(&Card::Pikachu) {
+            assert_eq!(&Price::from(3.75), trade.get_tx_price());
+        } else {
+            panic!("[ERROR] Test Failed: Trade does not exist");
+        }
+    }
+
+    #[test]
+    fn given_a_price_grid_when_orders_off_the_grid_received_or_amended_then_rejected_without_being_queued() {
+        let mut scheduler = Scheduler::new();
+        let mut grids = HashMap::new();
+        grids.insert(Card::Squirtle, PriceGrid::new(Price::from(0.25), Price::from(1.00), Price::from(10.00)));
+        scheduler.tx_board = TxBoard::with_grids(&grids);
+
+        let off_tick = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.10, 1, Card::Squirtle, 49);
+        assert_eq!(ProcessResult::TxRejected(RejectReason::OffTick), scheduler.process(&off_tick));
+        assert!(!scheduler.db.request_exist(&off_tick.get_uuid()));
+
+        let out_of_range = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 10.25, 1, Card::Squirtle, 49);
+        assert_eq!(ProcessResult::TxRejected(RejectReason::PxOutOfRange), scheduler.process(&out_of_range));
+
+        let on_tick = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.25, 1, Card::Squirtle, 49);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&on_tick));
+        // the rejection comes with the stored order at the amended terms, to be explained without reading it again
+        let (result, amended) = scheduler.amend_order(&on_tick.get_uuid(), &AmendOrder::new(49, 3.30, 1));
+        assert_eq!(ProcessResult::TxRejected(RejectReason::OffTick), result);
+        assert_eq!(Some((Side::Buy, Price::from(3.30), Card::Squirtle)), amended.map(|req| (req.get_side(), req.get_order_px(), req.get_card())));
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&on_tick.get_uuid(), &AmendOrder::new(49, 3.50, 1)));
+    }
+
+    #[test]
+    fn given_a_resting_order_when_amended_beyond_max_vol_or_to_no_volume_then_rejected_and_kept() {
+        let mut scheduler = Scheduler::new();
+        scheduler.validator = Validator::new(10, 10000, 5000);
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 6.00, 4, Card::Bulbasaur, 52);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&req));
+
+        for vol in [11, 0, -2].iter() {
+            assert_eq!(ProcessResult::TxRejected(RejectReason::InvalidVolume), scheduler.amend(&req.get_uuid(), &AmendOrder::new(52, 6.00, *vol)));
+        }
+        assert_eq!(Some((Price::from(6.00), 4)), scheduler.db.get_request(&req.get_uuid()).map(|req| (req.get_order_px(), req.get_vol())));
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&req.get_uuid(), &AmendOrder::new(52, 6.00, 10)));
+    }
+
+    #[test]
+    fn given_sell_orders_when_a_buy_order_executed_then_acknowledged_with_fills_and_status() {
+        let mut scheduler = Scheduler::new();
+        for px in [4.00, 4.50].iter() {
+            let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, *px, 1, Card::Charmander, 50);
+            assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&req));
+        }
+
+        let buy_req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 4.50, 3, Card::Charmander, 51);
+        let ack = scheduler.execute(&buy_req);
+        assert_eq!(&buy_req.get_uuid(), ack.get_uuid());
+        assert_eq!(&ProcessResult::TxPartiallyFilled, ack.get_result());
+        assert_eq!(&Some(OrderStatus::PartiallyFilled), ack.get_status());
+        assert_eq!(&vec![Fill::new(Price::from(4.00), 1), Fill::new(Price::from(4.50), 1)], ack.get_fills());
+        assert_eq!(&2, ack.get_filled_vol());
+        assert_eq!(&1, ack.get_resting_vol());
+        assert_eq!(&Some(Price::from(4.25)), ack.get_avg_px());
+
+        let self_traded = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 4.50, 1, Card::Charmander, 51);
+        let ack = scheduler.execute(&self_traded);
+        assert_eq!(&Some(OrderStatus::Dropped), ack.get_status());
+        assert_eq!(&0, ack.get_resting_vol());
+        assert_eq!(&None, ack.get_avg_px());
+    }
+
+    #[test]
+    fn given_accounts_when_orders_exceed_cash_or_cards_then_rejected_without_being_queued() {
+        let mut scheduler = Scheduler::new();
+        let accounts = Accounts::new(true);
+        scheduler.set_accounts(accounts.clone());
+        accounts.book(&mut *scheduler.db, &LedgerEntry::for_cash(111, 0, &CashTransfer::new(5.00, "seed"))).unwrap();
+        accounts.book(&mut *scheduler.db, &LedgerEntry::for_cards(112, 0, &CardTransfer::new(Card::Bulbasaur, 1, "seed"))).unwrap();
+
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.00, 2, Card::Bulbasaur, 111);
+        let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 3.00, 2, Card::Bulbasaur, 112);
+        assert_eq!(Err(RejectReason::InsufficientCash), scheduler.validate(&buy));
+        assert_eq!(Err(RejectReason::InsufficientCards), scheduler.validate(&sell));
+        assert_eq!(ProcessResult::TxRejected(RejectReason::InsufficientCash), scheduler.process(&buy));
+        assert_eq!(ProcessResult::TxRejected(RejectReason::InsufficientCards), scheduler.process(&sell));
+        assert!(!scheduler.db.request_exist(&buy.get_uuid()));
+        assert_eq!(0, scheduler.tx_board.get_open_orders(&111) + scheduler.tx_board.get_open_orders(&112));
+
+        // a market order reserves up to the top of the price grid
+        let market = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 1.00, 1, Card::Bulbasaur, 111).with_order_type(OrderType::Market);
+        assert_eq!(Price::from(10.00), scheduler.get_reserve_px(&market));
+        assert_eq!(Err(RejectReason::InsufficientCash), scheduler.validate(&market));
+    }
+
+    #[test]
+    fn given_accounts_when_orders_rest_amended_and_cancelled_then_reservations_follow() {
+        let mut scheduler = Scheduler::new();
+        let accounts = Accounts::new(true);
+        scheduler.set_accounts(accounts.clone());
+        accounts.book(&mut *scheduler.db, &LedgerEntry::for_cash(113, 0, &CashTransfer::new(10.00, "seed"))).unwrap();
+        accounts.book(&mut *scheduler.db, &LedgerEntry::for_cards(114, 0, &CardTransfer::new(Card::Squirtle, 3, "seed"))).unwrap();
+
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 3, Card::Squirtle, 113);
+        let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 9.00, 2, Card::Squirtle, 114);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&buy));
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&sell));
+        assert_eq!(Price::from(4.00), accounts.get_account(&113).get_available_cash());
+        assert_eq!(1, accounts.get_account(&114).get_holding(&Card::Squirtle).get_available());
+
+        // an amendment beyond the cash is refused and the former reservation kept
+        assert_eq!(ProcessResult::TxRejected(RejectReason::InsufficientCash), scheduler.amend(&buy.get_uuid(), &AmendOrder::new(113, 4.00, 3)));
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&buy.get_uuid(), &AmendOrder::new(113, 3.00, 2)));
+        assert_eq!(&Price::from(6.00), accounts.get_account(&113).get_reserved_cash());
+        assert_eq!(ProcessResult::TxRejected(RejectReason::InsufficientCards), scheduler.amend(&sell.get_uuid(), &AmendOrder::new(114, 9.00, 4)));
+
+        assert_eq!(ProcessResult::TxCancelled, scheduler.cancel(&buy.get_uuid(), &113));
+        assert_eq!(ProcessResult::TxCancelled, scheduler.cancel(&sell.get_uuid(), &114));
+        assert_eq!(&Price::from(0.00), accounts.get_account(&113).get_reserved_cash());
+        assert_eq!(&0, accounts.get_account(&114).get_holding(&Card::Squirtle).get_reserved());
+    }
+
+    #[test]
+    fn given_accounts_when_orders_filled_then_cash_and_cards_transferred_and_residuals_released() {
+        let mut scheduler = Scheduler::new();
+        let accounts = Accounts::new(true);
+        scheduler.set_accounts(accounts.clone());
+        accounts.book(&mut *scheduler.db, &LedgerEntry::for_cash(115, 0, &CashTransfer::new(50.00, "seed"))).unwrap();
+        accounts.book(&mut *scheduler.db, &LedgerEntry::for_cards(116, 0, &CardTransfer::new(Card::Charmander, 2, "seed"))).unwrap();
+
+        for px in [4.00, 5.00].iter() {
+            let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, *px, 1, Card::Charmander, 116);
+            assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&sell));
+        }
+
+        // the market order reserves 3 x 10.00, pays 9.00 for two cards and releases the rest
+        let market = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 1.00, 3, Card::Charmander, 115).with_order_type(OrderType::Market);
+        assert_eq!(ProcessResult::TxExpired, scheduler.process(&market));
+        let (buyer, seller) = (accounts.get_account(&115), accounts.get_account(&116));
+        assert_eq!((&Price::from(41.00), &Price::from(0.00)), (buyer.get_cash(), buyer.get_reserved_cash()));
+        assert_eq!(&2, buyer.get_holding(&Card::Charmander).get_qty());
+        assert_eq!((&Price::from(9.00), &0), (seller.get_cash(), seller.get_holding(&Card::Charmander).get_qty()));
+
+        // a resting buy order is paid at its own price when a sell order arrives
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 6.00, 2, Card::Charmander, 116);
+        let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 5.50, 1, Card::Charmander, 115);
+        assert_eq!(ProcessResult::TxRejected(RejectReason::InsufficientCash), scheduler.process(&buy));
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 4.50, 2, Card::Charmander, 116);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&buy));
+        let sell = RequestOrder::new(sell.get_uuid(), sell.get_tm(), Side::Sell, 4.50, 1, Card::Charmander, 115);
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&sell));
+        let (buyer, seller) = (accounts.get_account(&116), accounts.get_account(&115));
+        assert_eq!((&Price::from(4.50), &Price::from(4.50)), (buyer.get_cash(), buyer.get_reserved_cash()));
+        assert_eq!(&1, buyer.get_holding(&Card::Charmander).get_qty());
+        assert_eq!((&Price::from(45.50), 1), (seller.get_cash(), seller.get_holding(&Card::Charmander).get_available()));
+    }
 }
@@ -1,3 +1,7 @@
+use crate::data_type::{Card, Price};
+use crate::tx_board::PriceGrid;
+use crate::rate_limit::RateLimiter;
+use crate::validator::Validator;
 use ini;
 
 pub struct Settings {
@@ -34,6 +38,60 @@ impl Settings {
         ip     
     }
 
+    // how long an order post waits for its acknowledgement, 3000 ms by default
+    pub fn get_ack_timeout_ms(&self) -> u64 {
+        let config = ini!(self.path.as_str());
+        match config["server"].get("ack_timeout_ms").and_then(|value| value.clone()) {
+            Some(value) => value.trim().parse::<u64>().unwrap(),
+            None => 3000,
+        }
+    }
+
+    // bound of the order queue feeding the matching thread, 10000 by default
+    pub fn get_queue_capacity(&self) -> usize {
+        let config = ini!(self.path.as_str());
+        match config["server"].get("queue_capacity").and_then(|value| value.clone()) {
+            Some(value) => value.trim().parse::<usize>().unwrap(),
+            None => 10000,
+        }
+    }
+
+    // how far the timestamp of a signed request may drift from the server clock, 30000 ms by default
+    pub fn get_auth_skew_ms(&self) -> i64 {
+        let config = ini!(self.path.as_str());
+        match config.get("auth").and_then(|section| section.get("max_skew_ms")).and_then(|value| value.clone()) {
+            Some(value) => value.trim().parse::<i64>().unwrap(),
+            None => 30000,
+        }
+    }
+
+    // whether cash and cards of traders are checked before trading, off by default
+    pub fn get_account_enabled(&self) -> bool {
+        let config = ini!(self.path.as_str());
+        match config.get("account").and_then(|section| section.get("enabled")).and_then(|value| value.clone()) {
+            Some(value) => value.trim().parse::<bool>().unwrap(),
+            None => false,
+        }
+    }
+
+    // traders allowed to book balances through the admin endpoints, none by default
+    pub fn get_operators(&self) -> Vec<i32> {
+        let config = ini!(self.path.as_str());
+        match config.get("admin").and_then(|section| section.get("operators")).and_then(|value| value.clone()) {
+            Some(value) => value.split(',').filter(|id| !id.trim().is_empty()).map(|id| id.trim().parse::<i32>().unwrap()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // stores kept open for reading histories, apart from the ones of the matching engines, 4 by default
+    pub fn get_pool_size(&self) -> usize {
+        let config = ini!(self.path.as_str());
+        match config["database"].get("pool_size").and_then(|value| value.clone()) {
+            Some(value) => value.trim().parse::<usize>().unwrap(),
+            None => 4,
+        }
+    }
+
     pub fn get_database_url(&self) -> String {
         let (mut database, mut prefix, mut user, mut pwd, mut ip, mut port, mut db) = (String::new(), String::new(), String::new(), String::new(), String::new(), String::new(), String::new());
         let config = ini!(self.path.as_str());
@@ -50,7 +108,7 @@ impl Settings {
     pub fn get_prefix(&self) -> String {
         let mut prefix = String::new();
         let config = ini!(self.path.as_str());
-        prefix = config["database"]["port"].clone().unwrap();
+        prefix = config["database"]["prefix"].clone().unwrap();
         prefix        
     }
 
@@ -88,4 +146,77 @@ impl Settings {
         db = config["database"]["db"].clone().unwrap();
         db        
     }     
-}
\ No newline at end of file
+
+    /**
+     * Price grid of a card read from the section named after the card,
+     * e.g. [pikachu] with tick, min_px and max_px. Missing entries fall
+     * back to the default grid, while a malformed one aborts the start-up
+     **/
+    pub fn get_price_grid(&self, card: &Card) -> PriceGrid {
+        let default = PriceGrid::default();
+        let config = ini!(self.path.as_str());
+        let section = match config.get(&format!("{:?}", card).to_lowercase()) {
+            Some(section) => section,
+            None => return default,
+        };
+
+        let read = |key: &str, fallback: &Price| -> Price {
+            match section.get(key).and_then(|value| value.as_ref()) {
+                Some(value) => match value.trim().parse::<f64>().ok().and_then(Price::parse) {
+                    Some(px) => px,
+                    None => panic!("[Settings][get_price_grid] {} of {:?} is not a price: {}", key, card, value),
+                },
+                None => *fallback,
+            }
+        };
+        let (tick, min, max) = (read("tick", default.get_tick()), read("min_px", default.get_min()), read("max_px", default.get_max()));
+        if tick.get_cents() <= 0 || min > max || !min.is_multiple_of(&tick) || !max.is_multiple_of(&tick) {
+            panic!("[Settings][get_price_grid] invalid price grid of {:?}: tick {}, min_px {}, max_px {}", card, tick, min, max);
+        }
+        PriceGrid::new(tick, min, max)
+    }
+
+    /**
+     * Order limits read from the [order] section with max_vol, max_traders,
+     * max_tm_skew_ms and max_open_orders. Missing entries fall back to the
+     * default limits
+     **/
+    pub fn get_validator(&self) -> Validator {
+        let default = Validator::default();
+        let config = ini!(self.path.as_str());
+        let read = |key: &str, fallback: i64| -> i64 {
+            match config.get("order").and_then(|section| section.get(key)).and_then(|value| value.as_ref()) {
+                Some(value) => match value.trim().parse::<i64>() {
+                    Ok(limit) if limit >= 0 => limit,
+                    _ => panic!("[Settings][get_validator] {} is not a non-negative integer: {}", key, value),
+                },
+                None => fallback,
+            }
+        };
+        Validator::new(
+            read("max_vol", *default.get_max_vol() as i64) as i32,
+            read("max_traders", *default.get_max_traders() as i64) as i32,
+            read("max_tm_skew_ms", default.get_max_tm_skew().num_milliseconds()),
+        )
+        .with_max_open_orders(read("max_open_orders", *default.get_max_open_orders() as i64) as usize)
+    }
+
+    /**
+     * Token bucket of a kind of requests read from the [limit] section,
+     * i.e. {kind}_per_sec tokens refilled per second up to {kind}_burst.
+     * A rate of 0 leaves the requests unlimited
+     **/
+    pub fn get_rate_limiter(&self, kind: &str, default: &RateLimiter) -> RateLimiter {
+        let config = ini!(self.path.as_str());
+        let read = |key: String, fallback: f64| -> f64 {
+            match config.get("limit").and_then(|section| section.get(&key)).and_then(|value| value.as_ref()) {
+                Some(value) => match value.trim().parse::<f64>() {
+                    Ok(limit) if limit >= 0.0 => limit,
+                    _ => panic!("[Settings][get_rate_limiter] {} is not a non-negative number: {}", key, value),
+                },
+                None => fallback,
+            }
+        };
+        RateLimiter::new(read(format!("{}_per_sec", kind), *default.get_rate()), read(format!("{}_burst", kind), *default.get_burst()))
+    }
+}
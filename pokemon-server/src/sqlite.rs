@@ -0,0 +1,565 @@
+use crate::candle::{self, Candle};
+use crate::data_type::{CardTransfer, CashTransfer, Card, LedgerEntry, OrderStatus, Price, RequestOrder, Side};
+use crate::migration::{self, Migration, Migrator, SQLITE};
+use crate::status_board::Status;
+use crate::store::{Store, Write};
+use crate::trade_board::{Trade, TradeHistory};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Params, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
+use uuid::Uuid;
+
+/**
+ * Store kept in a SQLite file, for demos and single-node deployments
+ * without Postgres. The tables follow the ones of Postgres, where the
+ * enum types become TEXT columns checked against their values, prices
+ * are counted in cents and times are kept as RFC 3339 text in UTC. The
+ * schema is brought up to date by the migrations under migrations/sqlite
+ **/
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn new(path: &str) -> Self {
+        let mut store = SqliteStore::connect(path);
+        store.init_tables();
+        store
+    }
+
+    // opened without migrating its schema, as the migrate command does
+    pub fn connect(path: &str) -> Self {
+        let conn = match Connection::open(path) {
+            Ok(conn) => conn,
+            Err(e) => panic!("[Sqlite][connect] {}: {}", path, e),
+        };
+        // every engine owns a connection of its own, writers wait for each other
+        conn.busy_timeout(Duration::from_secs(5)).unwrap();
+        conn.pragma_update(None, "journal_mode", "wal").unwrap();
+        conn.execute_batch(
+            "create table if not exists schema_version(
+                version INTEGER primary key,
+                name TEXT not null,
+                applied_at TEXT not null
+            );",
+        )
+        .unwrap();
+        SqliteStore { conn }
+    }
+
+    pub fn init_tables(&mut self) {
+        migration::up(self);
+    }
+
+    fn execute<P: Params>(&self, name: &str, sql: &str, params: P) {
+        if let Err(e) = self.conn.execute(sql, params) {
+            panic!("[Sqlite][{}] {}", name, e);
+        }
+    }
+
+    fn store(&mut self, name: &str, write: Write) {
+        if let Err(e) = execute_write(&self.conn, &write) {
+            panic!("[Sqlite][{}] {}", name, e);
+        }
+    }
+
+    fn try_query<T, P: Params, F: FnMut(&Row) -> rusqlite::Result<T>>(&self, sql: &str, params: P, read: F) -> rusqlite::Result<Vec<T>> {
+        self.conn.prepare(sql).and_then(|mut stmt| stmt.query_map(params, read)?.collect::<rusqlite::Result<Vec<T>>>())
+    }
+
+    fn query<T, P: Params, F: FnMut(&Row) -> rusqlite::Result<T>>(&self, name: &str, sql: &str, params: P, read: F) -> Vec<T> {
+        match self.try_query(sql, params, read) {
+            Ok(rows) => rows,
+            Err(e) => panic!("[Sqlite][{}] {}", name, e),
+        }
+    }
+}
+
+// enums are stored by the names they are serialized with, which are the ones of Postgres
+fn to_text<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(text)) => text,
+        _ => panic!("[Sqlite][to_text] not stored as text"),
+    }
+}
+
+fn from_text<T: DeserializeOwned>(row: &Row, column: &str) -> rusqlite::Result<T> {
+    let text: String = row.get(column)?;
+    serde_json::from_value(serde_json::Value::String(text)).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn get_uuid(row: &Row, column: &str) -> rusqlite::Result<Uuid> {
+    let text: String = row.get(column)?;
+    Uuid::parse_str(&text).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn get_price(row: &Row, column: &str) -> rusqlite::Result<Price> {
+    row.get(column).map(Price::from_cents)
+}
+
+fn get_request(row: &Row) -> rusqlite::Result<RequestOrder> {
+    Ok(RequestOrder::new(get_uuid(row, "uuid")?, row.get("tm")?, from_text(row, "side")?, get_price(row, "order_px")?, row.get("vol")?, from_text(row, "card")?, row.get("trader_id")?))
+}
+
+// a write executed on the connection, or within a transaction of it
+fn execute_write(conn: &Connection, write: &Write) -> rusqlite::Result<usize> {
+    match write {
+        Write::Request(req) => conn.execute(
+            "insert into request_table(uuid, tm, side, order_px, vol, card, trader_id, time_in_force, order_type) values(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                req.get_uuid().to_string(),
+                req.get_tm(),
+                to_text(&req.get_side()),
+                req.get_order_px().get_cents(),
+                req.get_vol(),
+                to_text(&req.get_card()),
+                req.get_trade_id(),
+                to_text(&req.get_time_in_force()),
+                to_text(&req.get_order_type())
+            ],
+        ),
+        Write::Amend(uuid, tm, order_px, vol, keep_priority) => conn.execute(
+            "insert into amend_table(uuid, tm, order_px, vol, keep_priority) values(?1, ?2, ?3, ?4, ?5)",
+            params![uuid.to_string(), tm, order_px.get_cents(), vol, keep_priority],
+        ),
+        Write::InsertStatus(uuid, status) => conn.execute("insert into status_table(uuid, status) values(?1, ?2)", params![uuid.to_string(), to_text(status)]),
+        Write::UpdateStatus(uuid, status) => conn.execute("update status_table set status = ?1 where uuid = ?2", params![to_text(status), uuid.to_string()]),
+        Write::Trade(buy_side_uuid, sell_side_uuid, card, trade) => conn.execute(
+            "insert into trade_table(buy_uuid, sell_uuid, buy_side_id, sell_side_id, tx_price, tx_vol, card, tm) values(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                buy_side_uuid.to_string(),
+                sell_side_uuid.to_string(),
+                trade.get_buy_side_id(),
+                trade.get_sell_side_id(),
+                trade.get_tx_price().get_cents(),
+                trade.get_tx_vol(),
+                to_text(card),
+                trade.get_tx_time()
+            ],
+        ),
+        Write::Account(trader_id, cash, reserved_cash) => conn.execute(
+            "insert into account_table(trader_id, cash, reserved_cash) values(?1, ?2, ?3)
+             on conflict (trader_id) do update set cash = excluded.cash, reserved_cash = excluded.reserved_cash",
+            params![trader_id, cash.get_cents(), reserved_cash.get_cents()],
+        ),
+        Write::Inventory(trader_id, card, qty, reserved_qty) => conn.execute(
+            "insert into inventory_table(trader_id, card, qty, reserved_qty) values(?1, ?2, ?3, ?4)
+             on conflict (trader_id, card) do update set qty = excluded.qty, reserved_qty = excluded.reserved_qty",
+            params![trader_id, to_text(card), qty, reserved_qty],
+        ),
+        Write::Ledger(entry) => conn.execute(
+            "insert into ledger_table(tm, trader_id, operator_id, kind, card, amount, qty, reason) values(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![entry.get_tm(), entry.get_trader_id(), entry.get_operator_id(), to_text(entry.get_kind()), entry.get_card().map(|card| to_text(&card)), entry.get_amount().get_cents(), entry.get_qty(), entry.get_reason()],
+        ),
+    }
+}
+
+impl Store for SqliteStore {
+    fn upsert_trader(&mut self, trader_id: &i32, api_key: &str, secret: &str) {
+        self.execute(
+            "upsert_trader",
+            "insert into trader_table(trader_id, api_key, secret) values(?1, ?2, ?3)
+             on conflict (trader_id) do update set api_key = excluded.api_key, secret = excluded.secret, created_at = current_timestamp",
+            params![trader_id, api_key, secret],
+        );
+    }
+
+    fn get_trader(&mut self, api_key: &str) -> Option<(i32, String)> {
+        self.query("get_trader", "select trader_id, secret from trader_table where api_key = ?1", params![api_key], |row| Ok((row.get("trader_id")?, row.get("secret")?)))
+            .pop()
+    }
+
+    fn upsert_account(&mut self, trader_id: &i32, cash: &Price, reserved_cash: &Price) {
+        self.store("upsert_account", Write::Account(*trader_id, *cash, *reserved_cash));
+    }
+
+    fn upsert_inventory(&mut self, trader_id: &i32, card: &Card, qty: &i32, reserved_qty: &i32) {
+        self.store("upsert_inventory", Write::Inventory(*trader_id, *card, *qty, *reserved_qty));
+    }
+
+    fn get_accounts(&mut self) -> Vec<(i32, Price, Price)> {
+        self.query("get_accounts", "select trader_id, cash, reserved_cash from account_table", [], |row| {
+            Ok((row.get("trader_id")?, get_price(row, "cash")?, get_price(row, "reserved_cash")?))
+        })
+    }
+
+    fn get_inventories(&mut self) -> Vec<(i32, Card, i32, i32)> {
+        self.query("get_inventories", "select trader_id, card, qty, reserved_qty from inventory_table", [], |row| {
+            Ok((row.get("trader_id")?, from_text(row, "card")?, row.get("qty")?, row.get("reserved_qty")?))
+        })
+    }
+
+    fn get_ledger(&mut self, trader_id: &i32) -> Vec<LedgerEntry> {
+        self.query(
+            "get_ledger",
+            "select seq, tm, trader_id, operator_id, kind, card, amount, qty, reason from ledger_table where trader_id = ?1 order by seq",
+            params![trader_id],
+            |row| {
+                let (trader_id, operator_id, reason): (i32, i32, String) = (row.get("trader_id")?, row.get("operator_id")?, row.get("reason")?);
+                let entry = match row.get::<_, Option<String>>("card")? {
+                    Some(_) => LedgerEntry::for_cards(trader_id, operator_id, &CardTransfer::new(from_text(row, "card")?, row.get("qty")?, &reason)),
+                    None => LedgerEntry::for_cash(trader_id, operator_id, &CashTransfer::new(get_price(row, "amount")?, &reason)),
+                };
+                Ok(entry.with_seq(row.get("seq")?, row.get("tm")?))
+            },
+        )
+    }
+
+    fn get_expected_cash(&mut self) -> Vec<(i32, i64)> {
+        self.query(
+            "get_expected_cash",
+            "select trader_id, sum(amount) as cash
+             from ( select trader_id, amount from ledger_table where card is null
+                    union all
+                    select sell_side_id, tx_price * tx_vol from trade_table
+                    union all
+                    select buy_side_id, -tx_price * tx_vol from trade_table )
+             group by trader_id",
+            [],
+            |row| Ok((row.get("trader_id")?, row.get("cash")?)),
+        )
+    }
+
+    fn get_expected_cards(&mut self) -> Vec<(i32, Card, i32)> {
+        self.query(
+            "get_expected_cards",
+            "select trader_id, card, sum(qty) as qty
+             from ( select trader_id, card, qty from ledger_table where card is not null
+                    union all
+                    select buy_side_id, card, tx_vol from trade_table
+                    union all
+                    select sell_side_id, card, -tx_vol from trade_table )
+             group by trader_id, card",
+            [],
+            |row| Ok((row.get("trader_id")?, from_text(row, "card")?, row.get("qty")?)),
+        )
+    }
+
+    fn insert_request_table(&mut self, req: &RequestOrder) {
+        self.store("insert_request_table", Write::Request(*req));
+    }
+
+    fn request_exist(&mut self, uuid: &Uuid) -> bool {
+        !self.query("request_exist", "select uuid from request_table where uuid = ?1", params![uuid.to_string()], |row| row.get::<_, String>("uuid")).is_empty()
+    }
+
+    fn get_request(&mut self, uuid: &Uuid) -> Option<RequestOrder> {
+        // price and volume reflect the latest amendment of the order
+        self.query(
+            "get_request",
+            "select rt.uuid, rt.tm, rt.side,
+                    coalesce((select at.order_px from amend_table at where at.uuid = rt.uuid order by at.tm desc limit 1), rt.order_px) as order_px,
+                    coalesce((select at.vol from amend_table at where at.uuid = rt.uuid order by at.tm desc limit 1), rt.vol) as vol,
+                    rt.card, rt.trader_id
+             from request_table rt where rt.uuid = ?1",
+            params![uuid.to_string()],
+            get_request,
+        )
+        .pop()
+    }
+
+    fn insert_amend_table(&mut self, uuid: &Uuid, tm: &DateTime<Utc>, order_px: &Price, vol: &i32, keep_priority: &bool) {
+        self.store("insert_amend_table", Write::Amend(*uuid, *tm, *order_px, *vol, *keep_priority));
+    }
+
+    fn insert_order_status(&mut self, uuid: &Uuid, status: &OrderStatus) {
+        self.store("insert_order_status", Write::InsertStatus(*uuid, status.clone()));
+    }
+
+    fn update_order_status(&mut self, uuid: &Uuid, status: &OrderStatus) {
+        self.store("update_order_status", Write::UpdateStatus(*uuid, status.clone()));
+    }
+
+    fn order_status_exist(&mut self, uuid: &Uuid) -> bool {
+        !self.get_status_history(uuid).is_empty()
+    }
+
+    fn get_order_status(&mut self, uuid: &Uuid) -> OrderStatus {
+        match self.query("get_order_status", "select status from status_table where uuid = ?1", params![uuid.to_string()], |row| from_text(row, "status")).pop() {
+            Some(status) => status,
+            None => panic!("[Sqlite][get_order_status] status of {} not found", uuid),
+        }
+    }
+
+    fn find_order(&mut self, uuid: &Uuid) -> Result<Option<(RequestOrder, Option<OrderStatus>)>, String> {
+        let rows = self.try_query(
+            "select rt.uuid, rt.tm, rt.side,
+                    coalesce((select at.order_px from amend_table at where at.uuid = rt.uuid order by at.tm desc limit 1), rt.order_px) as order_px,
+                    coalesce((select at.vol from amend_table at where at.uuid = rt.uuid order by at.tm desc limit 1), rt.vol) as vol,
+                    rt.card, rt.trader_id, st.status
+             from request_table rt left join status_table st on st.uuid = rt.uuid where rt.uuid = ?1",
+            params![uuid.to_string()],
+            |row| {
+                let status = match row.get::<_, Option<String>>("status")? {
+                    Some(_) => Some(from_text(row, "status")?),
+                    None => None,
+                };
+                Ok((get_request(row)?, status))
+            },
+        );
+        rows.map(|mut rows| rows.pop()).map_err(|e| e.to_string())
+    }
+
+    fn insert_trade_table(&mut self, buy_side_uuid: &Uuid, sell_side_uuid: &Uuid, card: &Card, trade: &Trade) {
+        self.store("insert_trade_table", Write::Trade(*buy_side_uuid, *sell_side_uuid, *card, trade.clone()));
+    }
+
+    fn get_trades(&mut self, trader_id: &i32) -> Vec<(Card, Trade)> {
+        self.query(
+            "get_trades",
+            "select buy_side_id, sell_side_id, tx_price, tx_vol, card, tm from trade_table where buy_side_id = ?1 or sell_side_id = ?1 order by tm",
+            params![trader_id],
+            |row| Ok((from_text(row, "card")?, Trade::new(row.get("tm")?, row.get("buy_side_id")?, row.get("sell_side_id")?, get_price(row, "tx_price")?, row.get("tx_vol")?))),
+        )
+    }
+
+    fn get_candles(&mut self, card: &Card, secs: &i64, from: &DateTime<Utc>, to: &DateTime<Utc>) -> Vec<Candle> {
+        let trades = self.query(
+            "get_candles",
+            "select buy_side_id, sell_side_id, tx_price, tx_vol, tm from trade_table where card = ?1 and tm >= ?2 and tm < ?3 order by tm",
+            params![to_text(card), from, to],
+            |row| Ok(Trade::new(row.get("tm")?, row.get("buy_side_id")?, row.get("sell_side_id")?, get_price(row, "tx_price")?, row.get("tx_vol")?)),
+        );
+        candle::aggregate(trades.iter(), *secs)
+    }
+
+    fn get_realtime_tx_info(&mut self, side: &Side, card: &Card) -> Vec<RequestOrder> {
+        // amended orders are restored with their latest terms; re-priced or enlarged ones queue from the amendment time
+        self.query(
+            "get_realtime_tx_info",
+            "select rt.uuid, rt.tm, rt.side, rt.card, rt.trader_id,
+                    coalesce((select at.order_px from amend_table at where at.uuid = rt.uuid order by at.tm desc limit 1), rt.order_px) as order_px,
+                    coalesce((select at.vol from amend_table at where at.uuid = rt.uuid order by at.tm desc limit 1), rt.vol)
+                    - coalesce((select sum(tt.tx_vol) from trade_table tt where tt.buy_uuid = rt.uuid or tt.sell_uuid = rt.uuid), 0) as vol
+             from request_table rt inner join status_table st
+             on (st.status in ('Confirmed', 'PartiallyFilled') and
+                 rt.uuid = st.uuid and
+                 date(rt.tm) = date('now') and
+                 rt.side = ?1 and
+                 rt.card = ?2)
+             order by coalesce((select max(at.tm)
+                                from amend_table at
+                                where at.uuid = rt.uuid and not at.keep_priority), rt.tm)",
+            params![to_text(side), to_text(card)],
+            get_request,
+        )
+    }
+
+    fn get_trade_history(&mut self, id: &i32, date: &str) -> Vec<TradeHistory> {
+        // a single select keeps fills alike apart and a trade of a trader with itself once
+        self.query(
+            "get_trade_history",
+            "select tt.buy_side_id, tt.sell_side_id, tt.tx_price, tt.tx_vol, tt.card
+             from trade_table tt
+             where tt.buy_uuid in ( select rt.uuid
+                                    from request_table rt
+                                    where rt.trader_id = ?1 and strftime('%Y-%m-%d', rt.tm) like ?2 )
+                or tt.sell_uuid in ( select rt.uuid
+                                     from request_table rt
+                                     where rt.trader_id = ?1 and strftime('%Y-%m-%d', rt.tm) like ?2 )",
+            params![id, date],
+            |row| Ok(TradeHistory::new(row.get("buy_side_id")?, row.get("sell_side_id")?, get_price(row, "tx_price")?, row.get("tx_vol")?, from_text(row, "card")?)),
+        )
+    }
+
+    fn get_request_history(&mut self, id: &i32, date: &str) -> Vec<RequestOrder> {
+        // amendments share the uuid of the original request so the chain could be followed in time order
+        self.query(
+            "get_request_history",
+            "select rt.uuid, rt.tm, rt.side, rt.order_px, rt.vol, rt.card, rt.trader_id, rt.time_in_force, rt.order_type
+             from request_table rt
+             where rt.trader_id = ?1 and strftime('%Y-%m-%d', rt.tm) like ?2
+             union all
+             select at.uuid, at.tm, rt.side, at.order_px, at.vol, rt.card, rt.trader_id, rt.time_in_force, rt.order_type
+             from amend_table at inner join request_table rt on at.uuid = rt.uuid
+             where rt.trader_id = ?1 and strftime('%Y-%m-%d', rt.tm) like ?2
+             order by tm",
+            params![id, date],
+            |row| Ok(get_request(row)?.with_time_in_force(from_text(row, "time_in_force")?).with_order_type(from_text(row, "order_type")?)),
+        )
+    }
+
+    fn get_status_history(&mut self, uuid: &Uuid) -> Vec<Status> {
+        self.query("get_status_history", "select uuid, status from status_table where uuid = ?1", params![uuid.to_string()], |row| {
+            Ok(Status::new(get_uuid(row, "uuid")?, from_text(row, "status")?))
+        })
+    }
+
+    fn insert_run_table(&mut self) -> i64 {
+        self.execute("insert_run_table", "insert into run_table(started_at) values(?1)", params![Utc::now()]);
+        self.conn.last_insert_rowid()
+    }
+
+    fn write(&mut self, writes: &[Write]) -> Result<(), String> {
+        // dropping the transaction without committing rolls it back
+        let stored = self.conn.transaction().and_then(|tx| {
+            for write in writes.iter() {
+                execute_write(&tx, write)?;
+            }
+            tx.commit()
+        });
+        stored.map_err(|e| e.to_string())
+    }
+
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+impl Migrator for SqliteStore {
+    fn get_migrations(&self) -> &'static [Migration] {
+        &SQLITE
+    }
+
+    fn get_applied(&mut self) -> Vec<(i32, DateTime<Utc>)> {
+        self.query("get_applied", "select version, applied_at from schema_version order by version", [], |row| Ok((row.get("version")?, row.get("applied_at")?)))
+    }
+
+    fn apply(&mut self, migration: &Migration) {
+        let applied = self.conn.transaction().and_then(|tx| {
+            tx.execute_batch(migration.get_up())?;
+            tx.execute("insert into schema_version(version, name, applied_at) values(?1, ?2, ?3)", params![migration.get_version(), migration.get_name(), Utc::now()])?;
+            tx.commit()
+        });
+        if let Err(e) = applied {
+            panic!("[Sqlite][apply] migration {} {}: {}", migration.get_version(), migration.get_name(), e);
+        }
+    }
+
+    fn revert(&mut self, migration: &Migration) {
+        let reverted = self.conn.transaction().and_then(|tx| {
+            tx.execute_batch(migration.get_down())?;
+            tx.execute("delete from schema_version where version = ?1", params![migration.get_version()])?;
+            tx.commit()
+        });
+        if let Err(e) = reverted {
+            panic!("[Sqlite][revert] migration {} {}: {}", migration.get_version(), migration.get_name(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::{Accounts, BookingError};
+    use crate::data_type::{AmendOrder, Card, CardTransfer, CashTransfer, LedgerEntry, OrderStatus, Price, ProcessResult, RequestOrder, Side};
+    use crate::scheduler::Scheduler;
+    use crate::sqlite::SqliteStore;
+    use crate::store::Store;
+    use chrono::Utc;
+    use rusqlite::Connection;
+    use std::env;
+    use uuid::Uuid;
+
+    #[test]
+    fn given_orders_stored_in_sqlite_when_scheduler_restarted_then_board_recovered_and_history_served() {
+        let path = env::temp_dir().join(format!("pokemon-{}.db", Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let mut scheduler = Scheduler::for_cards(&[Card::Bulbasaur]);
+        scheduler.db = Box::new(SqliteStore::new(path));
+
+        let resting = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 4.00, 3, Card::Bulbasaur, 21);
+        let amended = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 4.50, 2, Card::Bulbasaur, 21);
+        let cancelled = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 5.00, 1, Card::Bulbasaur, 21);
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 4.00, 1, Card::Bulbasaur, 22);
+        for req in [resting, amended, cancelled].iter() {
+            assert_eq!(ProcessResult::TxConfirmed, scheduler.process(req));
+        }
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&buy));
+        assert_eq!(ProcessResult::TxCancelled, scheduler.cancel(&cancelled.get_uuid(), &21));
+        assert_eq!(ProcessResult::TxAmended, scheduler.amend(&amended.get_uuid(), &AmendOrder::new(21, 4.25, 4)));
+
+        // a scheduler restarted on the same file finds the remaining orders resting again
+        let mut recovered = Scheduler::for_cards(&[Card::Bulbasaur]);
+        recovered.db = Box::new(SqliteStore::new(path));
+        recovered.recover();
+        let book = recovered.get_book(&Card::Bulbasaur, 10).unwrap();
+        let asks: Vec<(Price, i32)> = book.get_asks().iter().map(|level| (*level.get_px(), *level.get_vol())).collect();
+        assert_eq!(vec![(Price::from(4.00), 2), (Price::from(4.25), 4)], asks);
+        assert_eq!(OrderStatus::PartiallyFilled, recovered.db.get_order_status(&resting.get_uuid()));
+
+        let date = Utc::now().format("%Y-%m-%d").to_string();
+        assert_eq!(4, recovered.db.get_request_history(&21, &date).len());
+        assert_eq!(1, recovered.db.get_trade_history(&22, &date).len());
+        assert_eq!(1, recovered.db.get_status_history(&cancelled.get_uuid()).len());
+        assert_eq!(Some(Price::from(4.25)), recovered.db.get_request(&amended.get_uuid()).map(|req| req.get_order_px()));
+    }
+
+    #[test]
+    fn given_a_failing_trade_table_when_a_crossing_order_received_then_nothing_stored_or_matched() {
+        let path = env::temp_dir().join(format!("pokemon-{}.db", Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let mut scheduler = Scheduler::for_cards(&[Card::Squirtle]);
+        scheduler.db = Box::new(SqliteStore::new(path));
+        let accounts = Accounts::new(true);
+        scheduler.set_accounts(accounts.clone());
+        accounts.book(&mut *scheduler.db, &LedgerEntry::for_cash(23, 0, &CashTransfer::new(10.00, "seed"))).unwrap();
+        accounts.book(&mut *scheduler.db, &LedgerEntry::for_cards(24, 0, &CardTransfer::new(Card::Squirtle, 2, "seed"))).unwrap();
+
+        let sell = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 3.00, 2, Card::Squirtle, 24);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&sell));
+        let (buyer, seller) = (accounts.get_account(&23), accounts.get_account(&24));
+
+        // the trade cannot be stored, so neither is the order nor anything it changes
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch("create trigger no_trade before insert on trade_table begin select raise(abort, 'disk full'); end;").unwrap();
+        let buy = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.00, 1, Card::Squirtle, 23);
+        assert_eq!(ProcessResult::TxStoreFailed, scheduler.process(&buy));
+        let book = scheduler.get_book(&Card::Squirtle, 10).unwrap();
+        assert_eq!(vec![(Price::from(3.00), 2)], book.get_asks().iter().map(|level| (*level.get_px(), *level.get_vol())).collect::<Vec<(Price, i32)>>());
+        assert!(book.get_bids().is_empty());
+        assert_eq!(OrderStatus::Confirmed, scheduler.db.get_order_status(&sell.get_uuid()));
+        assert!(scheduler.db.get_request(&buy.get_uuid()).is_none());
+        assert_eq!((buyer, seller), (accounts.get_account(&23), accounts.get_account(&24)));
+
+        // the same order goes through once the table accepts trades again
+        conn.execute_batch("drop trigger no_trade;").unwrap();
+        assert_eq!(ProcessResult::TxFilled, scheduler.process(&buy));
+        assert_eq!(OrderStatus::PartiallyFilled, scheduler.db.get_order_status(&sell.get_uuid()));
+        assert_eq!(&Price::from(7.00), accounts.get_account(&23).get_cash());
+    }
+
+    #[test]
+    fn given_a_failing_status_table_when_an_order_cancelled_or_amended_then_answered_as_store_failed() {
+        let path = env::temp_dir().join(format!("pokemon-{}.db", Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let mut scheduler = Scheduler::for_cards(&[Card::Pikachu]);
+        scheduler.db = Box::new(SqliteStore::new(path));
+        let resting = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 2, Card::Pikachu, 26);
+        assert_eq!(ProcessResult::TxConfirmed, scheduler.process(&resting));
+
+        // the order cannot be looked up, which is answered rather than panicking the engine
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch("alter table status_table rename to lost_table;").unwrap();
+        assert_eq!(ProcessResult::TxStoreFailed, scheduler.cancel(&resting.get_uuid(), &26));
+        assert_eq!(ProcessResult::TxStoreFailed, scheduler.amend(&resting.get_uuid(), &AmendOrder::new(26, 2.00, 1)));
+        let book = scheduler.get_book(&Card::Pikachu, 10).unwrap();
+        assert_eq!(vec![(Price::from(2.00), 2)], book.get_bids().iter().map(|level| (*level.get_px(), *level.get_vol())).collect::<Vec<(Price, i32)>>());
+
+        conn.execute_batch("alter table lost_table rename to status_table;").unwrap();
+        assert_eq!(ProcessResult::TxCancelled, scheduler.cancel(&resting.get_uuid(), &26));
+    }
+
+    #[test]
+    fn given_a_failing_ledger_table_when_a_deposit_booked_then_neither_balance_nor_entry_kept() {
+        let path = env::temp_dir().join(format!("pokemon-{}.db", Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let mut db = SqliteStore::new(path);
+        let accounts = Accounts::new(true);
+        let deposit = LedgerEntry::for_cash(25, 1, &CashTransfer::new(10.00, "seed"));
+        accounts.book(&mut db, &deposit).unwrap();
+
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch("create trigger no_ledger before insert on ledger_table begin select raise(abort, 'disk full'); end;").unwrap();
+        assert!(matches!(accounts.book(&mut db, &deposit), Err(BookingError::StoreFailed(_))));
+        assert_eq!(&Price::from(10.00), accounts.get_account(&25).get_cash());
+        assert_eq!(1, db.get_ledger(&25).len());
+        let stored = Accounts::new(true);
+        stored.load(&mut db);
+        assert_eq!(accounts.get_account(&25), stored.get_account(&25));
+
+        conn.execute_batch("drop trigger no_ledger;").unwrap();
+        assert_eq!(Ok(Price::from(20.00)), accounts.book(&mut db, &deposit).map(|account| *account.get_cash()));
+    }
+}
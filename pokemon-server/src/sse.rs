@@ -0,0 +1,111 @@
+use crate::data_type::{ProcessStatus, Rsp};
+use crate::engine::Engines;
+use crate::market_data::{MarketEvent, Topic};
+use crate::status_board::Stats;
+use crate::trade_board::Trade;
+use tide::sse::Sender;
+use tide::Request;
+
+// events after the one a reconnecting client has received last, if any
+fn get_last_event_id(req: &Request<()>) -> Option<u64> {
+    req.header("Last-Event-ID").and_then(|value| value.as_str().trim().parse::<u64>().ok())
+}
+
+async fn send_error(sender: &Sender, msg: String) -> tide::Result<()> {
+    let rsp = Rsp::<String>::new(ProcessStatus::Failed, msg, String::from("{}"));
+    sender.send("error", serde_json::to_string(&rsp)?, None).await?;
+    Ok(())
+}
+
+/**
+ * Push each new trade of a card as a "trade" event identified by its
+ * feed sequence number. With Last-Event-ID, the trades printed after it
+ * and still kept in trade_board are replayed before the live ones
+ **/
+pub async fn stream_trades(req: Request<()>, sender: Sender, engines: Engines) -> tide::Result<()> {
+    let card = match req.param("card").ok().and_then(crate::get_card) {
+        Some(card) => card,
+        None => return send_error(&sender, String::from("Invalid card")).await,
+    };
+    let engine = match engines.get(&card) {
+        Some(engine) => engine,
+        None => return send_error(&sender, format!("no matching engine for card {:?}", card)).await,
+    };
+
+    // subscribe before reading the backlog, so no trade slips in between;
+    // a new client without Last-Event-ID only receives the trades from now on
+    let feed = engines.get_feed();
+    let mut last = get_last_event_id(&req).unwrap_or_else(|| feed.get_seq(&Topic::Card(card)));
+    let (id, events) = feed.connect();
+    feed.subscribe(&id, Topic::Card(card));
+    let backlog = engine.lock().map(|scheduler| scheduler.trade_board.get_trades_since(&card, last));
+    let backlog: Vec<(u64, Trade)> = match backlog {
+        Ok(backlog) => backlog,
+        Err(e) => {
+            feed.disconnect(&id);
+            return send_error(&sender, e).await;
+        }
+    };
+    let result: tide::Result<()> = async {
+        for (seq, trade) in backlog {
+            sender.send("trade", serde_json::to_string(&trade)?, Some(&seq.to_string())).await?;
+            last = seq;
+        }
+        while let Ok(msg) = events.recv().await {
+            if let MarketEvent::Trade { trade, .. } = msg.get_event() {
+                if *msg.get_seq() > last {
+                    sender.send("trade", serde_json::to_string(trade)?, Some(&msg.get_seq().to_string())).await?;
+                    last = *msg.get_seq();
+                }
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    feed.disconnect(&id);
+    result
+}
+
+/**
+ * Push each status change of the orders of a trader as an "order" event
+ * carrying the Stats. With Last-Event-ID, the latest orders changed after
+ * it are replayed from the status boards of all cards
+ **/
+pub async fn stream_orders(req: Request<()>, sender: Sender, engines: Engines) -> tide::Result<()> {
+    let trader_id = match req.param("id").map(|id| id.parse::<i32>()) {
+        Ok(Ok(trader_id)) => trader_id,
+        _ => return send_error(&sender, String::from("Invalid trader id")).await,
+    };
+
+    let feed = engines.get_feed();
+    let mut last = get_last_event_id(&req).unwrap_or_else(|| feed.get_seq(&Topic::Trader(trader_id)));
+    let (id, events) = feed.connect();
+    feed.subscribe(&id, Topic::Trader(trader_id));
+    let backlog: Vec<(u64, Stats)> = match engines.get_changes_since(&trader_id, last) {
+        Ok(backlog) => backlog,
+        Err(e) => {
+            feed.disconnect(&id);
+            return send_error(&sender, e).await;
+        }
+    };
+    let result: tide::Result<()> = async {
+        for (seq, stats) in backlog {
+            sender.send("order", serde_json::to_string(&stats)?, Some(&seq.to_string())).await?;
+            last = seq;
+        }
+        while let Ok(msg) = events.recv().await {
+            if let MarketEvent::Order { stats, .. } = msg.get_event() {
+                if *msg.get_seq() > last {
+                    sender.send("order", serde_json::to_string(stats)?, Some(&msg.get_seq().to_string())).await?;
+                    last = *msg.get_seq();
+                }
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    feed.disconnect(&id);
+    result
+}
@@ -1,16 +1,18 @@
-use crate::data_type::{Card, OrderStatus, Side};
+use crate::data_type::{Card, OrderStatus, Price, Side};
+use crate::market_data::{MarketData, MarketEvent};
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use tide::prelude::{Deserialize, Serialize};
 use std::collections::{HashMap, LinkedList};
 use std::option::Option;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Stats {
     uuid: Uuid,
     tm: DateTime<Utc>,
     side: Side,
-    order_px: f64,
+    order_px: Price,
     vol: i32,
     card: Card,
     status: OrderStatus,
@@ -21,7 +23,7 @@ impl Stats {
         uuid: Uuid,
         tm: DateTime<Utc>,
         side: Side,
-        order_px: f64,
+        order_px: impl Into<Price>,
         vol: i32,
         card: Card,
         status: OrderStatus,
@@ -30,7 +32,7 @@ impl Stats {
             uuid,
             tm,
             side,
-            order_px,
+            order_px: order_px.into(),
             vol,
             card,
             status,
@@ -49,7 +51,7 @@ impl Stats {
         &self.side
     }
 
-    pub fn get_order_px(&self) -> &f64 {
+    pub fn get_order_px(&self) -> &Price {
         &self.order_px
     }
 
@@ -80,7 +82,10 @@ pub struct StatusBoard {
     // Hash: id -> latest 50 orders
     status_board: HashMap<i32, HashMap<Uuid, Stats>>,
     status_list: HashMap<i32, LinkedList<Uuid>>,
+    // feed sequence number of the latest change of each order
+    seqs: HashMap<Uuid, u64>,
     limit: usize,
+    feed: MarketData,
 }
 
 impl StatusBoard {
@@ -90,11 +95,20 @@ impl StatusBoard {
         Self {
             status_board: board,
             status_list: list,
+            seqs: HashMap::new(),
             limit: 50,
+            feed: MarketData::new(),
         }
     }
 
+    // status changes from now on are published to the feed
+    pub fn set_feed(&mut self, feed: MarketData) {
+        self.feed = feed;
+    }
+
     pub fn add_status(&mut self, id: i32, uuid: Uuid, stats: Stats) {
+        let seq = self.feed.publish(MarketEvent::Order { trader_id: id, stats: stats.clone() });
+        self.seqs.insert(uuid, seq);
         // add new one into status_board
         if let Some(res) = self.status_board.get_mut(&id) {
             res.insert(uuid, stats);
@@ -110,8 +124,11 @@ impl StatusBoard {
             if res.len() < self.limit {
                 res.push_back(uuid);
             } else {
-                res.pop_front();
+                let evicted = res.pop_front();
                 res.push_back(uuid);
+                if let Some(evicted) = evicted {
+                    self.prune(id, &evicted);
+                }
             }
         } else {
             self.status_list.insert(id, LinkedList::<Uuid>::new());
@@ -125,6 +142,37 @@ impl StatusBoard {
         if let Some(res) = self.status_board.get_mut(&id) {
             if let Some(stats) = res.get_mut(&uuid) {
                 stats.status = status;
+                let seq = self.feed.publish(MarketEvent::Order { trader_id: id, stats: stats.clone() });
+                self.seqs.insert(uuid, seq);
+            }
+        }
+        self.prune(id, &uuid);
+    }
+
+    /**
+     * Forget an order evicted from the latest ones of its trader once it
+     * is finalized, as no change of it is left to be published or replayed.
+     * An evicted order still open is kept until its final status
+     **/
+    fn prune(&mut self, id: i32, uuid: &Uuid) {
+        if self.status_list.get(&id).is_some_and(|list| list.contains(uuid)) {
+            return;
+        }
+        if let Some(res) = self.status_board.get_mut(&id) {
+            if res.get(uuid).is_some_and(|stats| is_final(&stats.status)) {
+                res.remove(uuid);
+                self.seqs.remove(uuid);
+            }
+        }
+    }
+
+    pub fn amend_status(&mut self, id: i32, uuid: Uuid, order_px: Price, vol: i32) {
+        if let Some(res) = self.status_board.get_mut(&id) {
+            if let Some(stats) = res.get_mut(&uuid) {
+                stats.order_px = order_px;
+                stats.vol = vol;
+                let seq = self.feed.publish(MarketEvent::Order { trader_id: id, stats: stats.clone() });
+                self.seqs.insert(uuid, seq);
             }
         }
     }
@@ -178,12 +226,37 @@ impl StatusBoard {
         res
     }
 
+    /**
+     * Latest orders of a trader changed after the given sequence number,
+     * ordered by their changes. Only the latest state of an order is kept,
+     * so its former changes are not replayed
+     **/
+    pub fn get_changes_since(&self, id: &i32, seq: u64) -> Vec<(u64, Stats)> {
+        let mut res = Vec::<(u64, Stats)>::new();
+        if let Some(uuids) = self.status_list.get(id) {
+            for uuid in uuids {
+                if let (Some(change), Some(stats)) = (self.seqs.get(uuid), self.get_stat(id, uuid)) {
+                    if *change > seq {
+                        res.push((*change, stats));
+                    }
+                }
+            }
+        }
+        res.sort_by_key(|(change, _)| *change);
+        res
+    }
+
     pub fn get_latest_uuids(&self, id: &i32) -> Option<&LinkedList<Uuid>> {
         self.status_list.get(id)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// no status follows these ones
+fn is_final(status: &OrderStatus) -> bool {
+    !matches!(status, OrderStatus::Confirmed | OrderStatus::PartiallyFilled)
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Status {
     uuid: Uuid,
     status: OrderStatus,
@@ -217,7 +290,7 @@ impl Status {
 
 #[cfg(test)]
 mod tests {
-    use crate::data_type::{Card, OrderStatus, Side};
+    use crate::data_type::{Card, OrderStatus, Price, Side};
     use crate::status_board::{Stats, StatusBoard};
     use chrono::Utc;
     use rand::Rng;
@@ -229,7 +302,7 @@ mod tests {
             Uuid::new_v4(),
             Utc::now(),
             Side::Buy,
-            1.00,
+            Price::from(1.00),
             1,
             Card::Bulbasaur,
             OrderStatus::Confirmed,
@@ -369,4 +442,58 @@ mod tests {
             panic!("[ERROR] Test Failed: Stat should be found but none.");
         }
     }
+
+    #[test]
+    fn given_status_is_amended_when_read_then_price_and_volume_replaced() {
+        let mut board = StatusBoard::new();
+        let uuid = Uuid::new_v4();
+        let stat = Stats::new(uuid, Utc::now(), Side::Sell, 7.00, 3, Card::Squirtle, OrderStatus::Confirmed);
+        board.add_status(1, uuid, stat);
+
+        board.amend_status(1, uuid, Price::from(8.00), 2);
+
+        if let Some(res) = board.get_stat(&1, &uuid) {
+            assert_eq!(&Price::from(8.00), res.get_order_px());
+            assert_eq!(&2, res.get_vol());
+            assert_eq!(&OrderStatus::Confirmed, res.get_status());
+        } else {
+            panic!("[ERROR] Test Failed: Stat should be found but none.");
+        }
+    }
+
+    #[test]
+    fn given_orders_changed_when_changes_read_since_a_sequence_then_latest_states_replayed_in_order() {
+        let mut board = StatusBoard::new();
+        let (first, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        board.add_status(4, first, Stats::new(first, Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, OrderStatus::Confirmed));
+        board.add_status(4, second, Stats::new(second, Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, OrderStatus::Confirmed));
+        board.add_status(5, third, Stats::new(third, Utc::now(), Side::Sell, 2.00, 1, Card::Pikachu, OrderStatus::Confirmed));
+        board.update_status(4, first, OrderStatus::Filled);
+
+        let changes: Vec<(u64, Uuid, OrderStatus)> = board.get_changes_since(&4, 0).into_iter().map(|(seq, stats)| (seq, *stats.get_uuid(), stats.get_status().clone())).collect();
+        assert_eq!(vec![(2, second, OrderStatus::Confirmed), (3, first, OrderStatus::Filled)], changes);
+        assert_eq!(1, board.get_changes_since(&4, 2).len());
+        assert!(board.get_changes_since(&4, 3).is_empty());
+        assert_eq!(1, board.get_changes_since(&5, 0).len());
+    }
+
+    #[test]
+    fn given_orders_evicted_from_the_latest_ones_when_finalized_then_forgotten() {
+        let mut board = StatusBoard::new();
+        let (filled, resting) = (Uuid::new_v4(), Uuid::new_v4());
+        board.add_status(7, filled, Stats::new(filled, Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, OrderStatus::Filled));
+        board.add_status(7, resting, Stats::new(resting, Utc::now(), Side::Buy, 2.00, 1, Card::Pikachu, OrderStatus::Confirmed));
+        for _ in 0..*board.get_limit() {
+            let uuid = Uuid::new_v4();
+            board.add_status(7, uuid, Stats::new(uuid, Utc::now(), Side::Sell, 3.00, 1, Card::Pikachu, OrderStatus::Confirmed));
+        }
+
+        // a finalized order goes once evicted, while a resting one waits for its final status
+        assert_eq!(None, board.get_stat(&7, &filled));
+        assert!(!board.seqs.contains_key(&filled));
+        assert_eq!(Some(OrderStatus::Confirmed), board.get_stat(&7, &resting).map(|stats| stats.get_status().clone()));
+        board.update_status(7, resting, OrderStatus::Cancelled);
+        assert_eq!(None, board.get_stat(&7, &resting));
+        assert_eq!(*board.get_limit(), board.seqs.len());
+    }
 }
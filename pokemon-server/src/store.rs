@@ -0,0 +1,541 @@
+use crate::candle::{self, Candle};
+use crate::data_type::{Card, LedgerEntry, OrderStatus, Price, RequestOrder, Side};
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::sqlite::SqliteStore;
+use crate::status_board::Status;
+use crate::trade_board::{Trade, TradeHistory};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex, OnceLock};
+use uuid::Uuid;
+
+/**
+ * Persistence of orders, statuses, trades, balances and keys. Rows come
+ * back as the types of the engine, so a backend is free to keep them in
+ * any form. Writes are expected to succeed; a backend which could not
+ * store them panics like the matching would with a lost record
+ **/
+pub trait Store: Send {
+    fn upsert_trader(&mut self, trader_id: &i32, api_key: &str, secret: &str);
+    // trader id and secret of an api key
+    fn get_trader(&mut self, api_key: &str) -> Option<(i32, String)>;
+
+    fn upsert_account(&mut self, trader_id: &i32, cash: &Price, reserved_cash: &Price);
+    fn upsert_inventory(&mut self, trader_id: &i32, card: &Card, qty: &i32, reserved_qty: &i32);
+    // trader id, cash and reserved cash of every account
+    fn get_accounts(&mut self) -> Vec<(i32, Price, Price)>;
+    // trader id, card, qty and reserved qty of every holding
+    fn get_inventories(&mut self) -> Vec<(i32, Card, i32, i32)>;
+    fn get_ledger(&mut self, trader_id: &i32) -> Vec<LedgerEntry>;
+    // cash of each trader in cents implied by the ledger and the trades
+    fn get_expected_cash(&mut self) -> Vec<(i32, i64)>;
+    // cards of each trader implied by the ledger and the trades
+    fn get_expected_cards(&mut self) -> Vec<(i32, Card, i32)>;
+
+    fn insert_request_table(&mut self, req: &RequestOrder);
+    fn request_exist(&mut self, uuid: &Uuid) -> bool;
+    // the request with the price and volume of its latest amendment
+    fn get_request(&mut self, uuid: &Uuid) -> Option<RequestOrder>;
+    fn insert_amend_table(&mut self, uuid: &Uuid, tm: &DateTime<Utc>, order_px: &Price, vol: &i32, keep_priority: &bool);
+
+    fn insert_order_status(&mut self, uuid: &Uuid, status: &OrderStatus);
+    fn update_order_status(&mut self, uuid: &Uuid, status: &OrderStatus);
+    fn order_status_exist(&mut self, uuid: &Uuid) -> bool;
+    fn get_order_status(&mut self, uuid: &Uuid) -> OrderStatus;
+    // the request as get_request with its status if any, an error instead of a panic if the store fails
+    fn find_order(&mut self, uuid: &Uuid) -> Result<Option<(RequestOrder, Option<OrderStatus>)>, String>;
+
+    fn insert_trade_table(&mut self, buy_side_uuid: &Uuid, sell_side_uuid: &Uuid, card: &Card, trade: &Trade);
+    // every trade of the trader in time order
+    fn get_trades(&mut self, trader_id: &i32) -> Vec<(Card, Trade)>;
+    // trades of the card in [from, to) bucketed by secs since the epoch
+    fn get_candles(&mut self, card: &Card, secs: &i64, from: &DateTime<Utc>, to: &DateTime<Utc>) -> Vec<Candle>;
+
+    // resting orders of today with their remaining volumes, in the order they queue
+    fn get_realtime_tx_info(&mut self, side: &Side, card: &Card) -> Vec<RequestOrder>;
+    fn get_trade_history(&mut self, id: &i32, date: &str) -> Vec<TradeHistory>;
+    // requests of the date followed by their amendments in time order
+    fn get_request_history(&mut self, id: &i32, date: &str) -> Vec<RequestOrder>;
+    fn get_status_history(&mut self, uuid: &Uuid) -> Vec<Status>;
+
+    // number given to the run of the server starting now, counted from 1
+    fn insert_run_table(&mut self) -> i64;
+
+    // writes of an order stored in one transaction, none of them is kept if one fails
+    fn write(&mut self, writes: &[Write]) -> Result<(), String>;
+
+    // whether the connection is lost, so a pool opens another store instead
+    fn is_closed(&self) -> bool;
+}
+
+/**
+ * A change of the tables made while an order is processed. The writes of
+ * an order are stored together by Store::write, so that a trade is never
+ * kept without the statuses and balances it changes
+ **/
+#[derive(Debug, Clone)]
+pub enum Write {
+    Request(RequestOrder),
+    // uuid, time, price, volume and whether the order keeps its priority
+    Amend(Uuid, DateTime<Utc>, Price, i32, bool),
+    InsertStatus(Uuid, OrderStatus),
+    UpdateStatus(Uuid, OrderStatus),
+    // buy side uuid, sell side uuid, card and the trade
+    Trade(Uuid, Uuid, Card, Trade),
+    // trader id, cash and reserved cash
+    Account(i32, Price, Price),
+    // trader id, card, qty and reserved qty
+    Inventory(i32, Card, i32, i32),
+    // an entry appended to the ledger along with the balance it books
+    Ledger(LedgerEntry),
+}
+
+/**
+ * Store named by the prefix of the [database] section in the config file
+ * given by the command line, i.e. postgresql, sqlite or memory; sqlite
+ * keeps its tables in the file named by db. Without a config
+ * file the server connects to the local Postgres, while unit tests run
+ * in memory on tables of their own
+ **/
+pub fn open() -> Box<dyn Store> {
+    match env::args().nth(1).map(Settings::new) {
+        Some(cfg) => match cfg.get_prefix().as_str() {
+            "memory" => Box::new(MemoryStore::shared()),
+            "sqlite" => Box::new(SqliteStore::new(&cfg.get_database_name())),
+            _ => Box::new(Database::new()),
+        },
+        // every unit test works on tables of its own
+        None if cfg!(test) => Box::new(MemoryStore::new()),
+        None => Box::new(Database::new()),
+    }
+}
+
+struct Amendment {
+    uuid: Uuid,
+    tm: DateTime<Utc>,
+    order_px: Price,
+    vol: i32,
+    keep_priority: bool,
+}
+
+struct TradeRecord {
+    buy_uuid: Uuid,
+    sell_uuid: Uuid,
+    card: Card,
+    trade: Trade,
+}
+
+#[derive(Default)]
+struct Tables {
+    traders: HashMap<i32, (String, String)>,
+    accounts: HashMap<i32, (Price, Price)>,
+    inventories: HashMap<(i32, Card), (i32, i32)>,
+    ledger: Vec<LedgerEntry>,
+    requests: Vec<RequestOrder>,
+    amends: Vec<Amendment>,
+    statuses: HashMap<Uuid, OrderStatus>,
+    trades: Vec<TradeRecord>,
+    runs: i64,
+}
+
+impl Tables {
+    fn get_request(&self, uuid: &Uuid) -> Option<&RequestOrder> {
+        self.requests.iter().find(|req| &req.get_uuid() == uuid)
+    }
+
+    fn get_latest_amendment(&self, uuid: &Uuid) -> Option<&Amendment> {
+        self.amends.iter().filter(|amend| &amend.uuid == uuid).max_by_key(|amend| amend.tm)
+    }
+
+    // price and volume of the request after its latest amendment
+    fn get_amended(&self, req: &RequestOrder) -> (Price, i32) {
+        match self.get_latest_amendment(&req.get_uuid()) {
+            Some(amend) => (amend.order_px, amend.vol),
+            None => (req.get_order_px(), req.get_vol()),
+        }
+    }
+
+    fn get_traded_vol(&self, uuid: &Uuid) -> i32 {
+        self.trades.iter().filter(|record| &record.buy_uuid == uuid || &record.sell_uuid == uuid).map(|record| *record.trade.get_tx_vol()).sum()
+    }
+
+    fn is_requested_by(&self, uuid: &Uuid, id: &i32, date: &str) -> bool {
+        self.get_request(uuid).is_some_and(|req| &req.get_trade_id() == id && req.get_tm().format("%Y-%m-%d").to_string() == date)
+    }
+
+    // refuse the writes a database would, before any of them is applied
+    fn check(&self, writes: &[Write]) -> Result<(), String> {
+        for (i, write) in writes.iter().enumerate() {
+            match write {
+                Write::Request(req) if self.get_request(&req.get_uuid()).is_some() => return Err(format!("request {} exists", req.get_uuid())),
+                Write::UpdateStatus(uuid, _) if !self.statuses.contains_key(uuid) && !writes[..i].iter().any(|w| matches!(w, Write::InsertStatus(inserted, _) if inserted == uuid)) => {
+                    return Err(format!("status of {} not found", uuid))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, write: &Write) {
+        match write {
+            Write::Request(req) => self.requests.push(*req),
+            Write::Amend(uuid, tm, order_px, vol, keep_priority) => self.amends.push(Amendment { uuid: *uuid, tm: *tm, order_px: *order_px, vol: *vol, keep_priority: *keep_priority }),
+            Write::InsertStatus(uuid, status) | Write::UpdateStatus(uuid, status) => {
+                self.statuses.insert(*uuid, status.clone());
+            }
+            Write::Trade(buy_uuid, sell_uuid, card, trade) => self.trades.push(TradeRecord { buy_uuid: *buy_uuid, sell_uuid: *sell_uuid, card: *card, trade: trade.clone() }),
+            Write::Account(trader_id, cash, reserved_cash) => {
+                self.accounts.insert(*trader_id, (*cash, *reserved_cash));
+            }
+            Write::Inventory(trader_id, card, qty, reserved_qty) => {
+                self.inventories.insert((*trader_id, *card), (*qty, *reserved_qty));
+            }
+            Write::Ledger(entry) => {
+                let seq = self.ledger.len() as i64 + 1;
+                self.ledger.push(entry.clone().with_seq(seq, *entry.get_tm()));
+            }
+        }
+    }
+}
+
+/**
+ * Tables kept in memory, for the unit tests and for trying the server out
+ * without Postgres. A store has tables of its own, shared by its clones
+ * only, so schedulers see the writes of each other as with a database
+ * just when they are handed the same tables; nothing survives a restart
+ **/
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    tables: Arc<Mutex<Tables>>,
+}
+
+// tables of the server run with prefix = memory, opened by every store of the process
+static TABLES: OnceLock<Arc<Mutex<Tables>>> = OnceLock::new();
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // tables of the process
+    pub fn shared() -> Self {
+        Self { tables: TABLES.get_or_init(|| Arc::new(Mutex::new(Tables::default()))).clone() }
+    }
+
+    fn store(&mut self, name: &str, write: Write) {
+        if let Err(e) = self.write(&[write]) {
+            panic!("[MemoryStore][{}] {}", name, e);
+        }
+    }
+}
+
+impl Store for MemoryStore {
+    fn upsert_trader(&mut self, trader_id: &i32, api_key: &str, secret: &str) {
+        self.tables.lock().unwrap().traders.insert(*trader_id, (api_key.to_owned(), secret.to_owned()));
+    }
+
+    fn get_trader(&mut self, api_key: &str) -> Option<(i32, String)> {
+        let tables = self.tables.lock().unwrap();
+        tables.traders.iter().find(|(_, (key, _))| key == api_key).map(|(trader_id, (_, secret))| (*trader_id, secret.clone()))
+    }
+
+    fn upsert_account(&mut self, trader_id: &i32, cash: &Price, reserved_cash: &Price) {
+        self.store("upsert_account", Write::Account(*trader_id, *cash, *reserved_cash));
+    }
+
+    fn upsert_inventory(&mut self, trader_id: &i32, card: &Card, qty: &i32, reserved_qty: &i32) {
+        self.store("upsert_inventory", Write::Inventory(*trader_id, *card, *qty, *reserved_qty));
+    }
+
+    fn get_accounts(&mut self) -> Vec<(i32, Price, Price)> {
+        self.tables.lock().unwrap().accounts.iter().map(|(trader_id, (cash, reserved_cash))| (*trader_id, *cash, *reserved_cash)).collect()
+    }
+
+    fn get_inventories(&mut self) -> Vec<(i32, Card, i32, i32)> {
+        self.tables.lock().unwrap().inventories.iter().map(|((trader_id, card), (qty, reserved_qty))| (*trader_id, *card, *qty, *reserved_qty)).collect()
+    }
+
+    fn get_ledger(&mut self, trader_id: &i32) -> Vec<LedgerEntry> {
+        self.tables.lock().unwrap().ledger.iter().filter(|entry| entry.get_trader_id() == trader_id).cloned().collect()
+    }
+
+    fn get_expected_cash(&mut self) -> Vec<(i32, i64)> {
+        let tables = self.tables.lock().unwrap();
+        let mut cash: HashMap<i32, i64> = HashMap::new();
+        for entry in tables.ledger.iter().filter(|entry| entry.get_card().is_none()) {
+            *cash.entry(*entry.get_trader_id()).or_default() += entry.get_amount().get_cents();
+        }
+        for record in tables.trades.iter() {
+            let amount = record.trade.get_tx_price().times(*record.trade.get_tx_vol()).get_cents();
+            *cash.entry(*record.trade.get_sell_side_id()).or_default() += amount;
+            *cash.entry(*record.trade.get_buy_side_id()).or_default() -= amount;
+        }
+        cash.into_iter().collect()
+    }
+
+    fn get_expected_cards(&mut self) -> Vec<(i32, Card, i32)> {
+        let tables = self.tables.lock().unwrap();
+        let mut cards: HashMap<(i32, Card), i32> = HashMap::new();
+        for entry in tables.ledger.iter() {
+            if let Some(card) = entry.get_card() {
+                *cards.entry((*entry.get_trader_id(), *card)).or_default() += entry.get_qty();
+            }
+        }
+        for record in tables.trades.iter() {
+            *cards.entry((*record.trade.get_buy_side_id(), record.card)).or_default() += record.trade.get_tx_vol();
+            *cards.entry((*record.trade.get_sell_side_id(), record.card)).or_default() -= record.trade.get_tx_vol();
+        }
+        cards.into_iter().map(|((trader_id, card), qty)| (trader_id, card, qty)).collect()
+    }
+
+    fn insert_request_table(&mut self, req: &RequestOrder) {
+        self.store("insert_request_table", Write::Request(*req));
+    }
+
+    fn request_exist(&mut self, uuid: &Uuid) -> bool {
+        self.tables.lock().unwrap().get_request(uuid).is_some()
+    }
+
+    fn get_request(&mut self, uuid: &Uuid) -> Option<RequestOrder> {
+        let tables = self.tables.lock().unwrap();
+        tables.get_request(uuid).map(|req| {
+            let (order_px, vol) = tables.get_amended(req);
+            RequestOrder::new(req.get_uuid(), req.get_tm(), req.get_side(), order_px, vol, req.get_card(), req.get_trade_id())
+        })
+    }
+
+    fn insert_amend_table(&mut self, uuid: &Uuid, tm: &DateTime<Utc>, order_px: &Price, vol: &i32, keep_priority: &bool) {
+        self.store("insert_amend_table", Write::Amend(*uuid, *tm, *order_px, *vol, *keep_priority));
+    }
+
+    fn insert_order_status(&mut self, uuid: &Uuid, status: &OrderStatus) {
+        self.store("insert_order_status", Write::InsertStatus(*uuid, status.clone()));
+    }
+
+    fn update_order_status(&mut self, uuid: &Uuid, status: &OrderStatus) {
+        self.store("update_order_status", Write::UpdateStatus(*uuid, status.clone()));
+    }
+
+    fn order_status_exist(&mut self, uuid: &Uuid) -> bool {
+        self.tables.lock().unwrap().statuses.contains_key(uuid)
+    }
+
+    fn get_order_status(&mut self, uuid: &Uuid) -> OrderStatus {
+        match self.tables.lock().unwrap().statuses.get(uuid) {
+            Some(status) => status.clone(),
+            None => panic!("[MemoryStore][get_order_status] status of {} not found", uuid),
+        }
+    }
+
+    fn find_order(&mut self, uuid: &Uuid) -> Result<Option<(RequestOrder, Option<OrderStatus>)>, String> {
+        let status = self.tables.lock().unwrap().statuses.get(uuid).cloned();
+        Ok(self.get_request(uuid).map(|req| (req, status)))
+    }
+
+    fn insert_trade_table(&mut self, buy_side_uuid: &Uuid, sell_side_uuid: &Uuid, card: &Card, trade: &Trade) {
+        self.store("insert_trade_table", Write::Trade(*buy_side_uuid, *sell_side_uuid, *card, trade.clone()));
+    }
+
+    fn get_trades(&mut self, trader_id: &i32) -> Vec<(Card, Trade)> {
+        let tables = self.tables.lock().unwrap();
+        let mut trades: Vec<(Card, Trade)> = tables
+            .trades
+            .iter()
+            .filter(|record| record.trade.get_buy_side_id() == trader_id || record.trade.get_sell_side_id() == trader_id)
+            .map(|record| (record.card, record.trade.clone()))
+            .collect();
+        trades.sort_by_key(|(_, trade)| *trade.get_tx_time());
+        trades
+    }
+
+    fn get_candles(&mut self, card: &Card, secs: &i64, from: &DateTime<Utc>, to: &DateTime<Utc>) -> Vec<Candle> {
+        let tables = self.tables.lock().unwrap();
+        let mut trades: Vec<&Trade> = tables
+            .trades
+            .iter()
+            .filter(|record| &record.card == card && record.trade.get_tx_time() >= from && record.trade.get_tx_time() < to)
+            .map(|record| &record.trade)
+            .collect();
+        trades.sort_by_key(|trade| *trade.get_tx_time());
+        candle::aggregate(trades.into_iter(), *secs)
+    }
+
+    fn get_realtime_tx_info(&mut self, side: &Side, card: &Card) -> Vec<RequestOrder> {
+        let tables = self.tables.lock().unwrap();
+        let today = Utc::now().naive_utc().date();
+        let mut resting: Vec<(DateTime<Utc>, RequestOrder)> = Vec::new();
+        for req in tables.requests.iter() {
+            let status = tables.statuses.get(&req.get_uuid());
+            if &req.get_side() != side || &req.get_card() != card || req.get_tm().naive_utc().date() != today || !matches!(status, Some(OrderStatus::Confirmed) | Some(OrderStatus::PartiallyFilled)) {
+                continue;
+            }
+            // re-priced or enlarged orders queue from their latest amendment
+            let queued = tables.amends.iter().filter(|amend| amend.uuid == req.get_uuid() && !amend.keep_priority).map(|amend| amend.tm).max().unwrap_or_else(|| req.get_tm());
+            let (order_px, vol) = tables.get_amended(req);
+            let vol = vol - tables.get_traded_vol(&req.get_uuid());
+            resting.push((queued, RequestOrder::new(req.get_uuid(), req.get_tm(), req.get_side(), order_px, vol, req.get_card(), req.get_trade_id())));
+        }
+        resting.sort_by_key(|(queued, _)| *queued);
+        resting.into_iter().map(|(_, req)| req).collect()
+    }
+
+    fn get_trade_history(&mut self, id: &i32, date: &str) -> Vec<TradeHistory> {
+        let tables = self.tables.lock().unwrap();
+        tables
+            .trades
+            .iter()
+            .filter(|record| tables.is_requested_by(&record.buy_uuid, id, date) || tables.is_requested_by(&record.sell_uuid, id, date))
+            .map(|record| TradeHistory::new(*record.trade.get_buy_side_id(), *record.trade.get_sell_side_id(), *record.trade.get_tx_price(), *record.trade.get_tx_vol(), record.card))
+            .collect()
+    }
+
+    fn get_request_history(&mut self, id: &i32, date: &str) -> Vec<RequestOrder> {
+        let tables = self.tables.lock().unwrap();
+        let mut history: Vec<RequestOrder> = tables.requests.iter().filter(|req| tables.is_requested_by(&req.get_uuid(), id, date)).copied().collect();
+        for amend in tables.amends.iter() {
+            if let Some(req) = tables.get_request(&amend.uuid).filter(|req| tables.is_requested_by(&req.get_uuid(), id, date)) {
+                history.push(
+                    RequestOrder::new(amend.uuid, amend.tm, req.get_side(), amend.order_px, amend.vol, req.get_card(), req.get_trade_id())
+                        .with_time_in_force(req.get_time_in_force())
+                        .with_order_type(req.get_order_type()),
+                );
+            }
+        }
+        history.sort_by_key(|req| req.get_tm());
+        history
+    }
+
+    fn get_status_history(&mut self, uuid: &Uuid) -> Vec<Status> {
+        self.tables.lock().unwrap().statuses.get(uuid).map(|status| Status::new(*uuid, status.clone())).into_iter().collect()
+    }
+
+    fn insert_run_table(&mut self) -> i64 {
+        let mut tables = self.tables.lock().unwrap();
+        tables.runs += 1;
+        tables.runs
+    }
+
+    fn write(&mut self, writes: &[Write]) -> Result<(), String> {
+        let mut tables = self.tables.lock().unwrap();
+        tables.check(writes)?;
+        for write in writes.iter() {
+            tables.apply(write);
+        }
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_type::{Card, OrderStatus, Price, RequestOrder, Side};
+    use crate::database::Database;
+    use crate::sqlite::SqliteStore;
+    use crate::store::{MemoryStore, Store, Write};
+    use crate::trade_board::Trade;
+    use chrono::{Duration, Utc};
+    use postgres::{Client, NoTls};
+    use std::env;
+    use uuid::Uuid;
+
+    #[test]
+    fn given_orders_amended_and_traded_when_recovered_from_memory_then_remaining_orders_queued_by_priority() {
+        recover_amended_and_traded_orders(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn given_orders_amended_and_traded_when_recovered_from_sqlite_then_remaining_orders_queued_by_priority() {
+        let path = env::temp_dir().join(format!("pokemon-{}.db", Uuid::new_v4()));
+        recover_amended_and_traded_orders(&mut SqliteStore::new(path.to_str().unwrap()));
+    }
+
+    #[test]
+    fn given_identical_fills_when_trade_history_read_from_memory_then_every_fill_served_once() {
+        serve_every_fill_once(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn given_identical_fills_when_trade_history_read_from_sqlite_then_every_fill_served_once() {
+        let path = env::temp_dir().join(format!("pokemon-{}.db", Uuid::new_v4()));
+        serve_every_fill_once(&mut SqliteStore::new(path.to_str().unwrap()));
+    }
+
+    // run only given a server by DATABASE_URL, on a database of its own dropped afterwards
+    #[test]
+    fn given_orders_amended_and_traded_when_recovered_from_postgres_then_remaining_orders_queued_by_priority() {
+        let url = match env::var("DATABASE_URL") {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        let name = format!("pokemon_{}", Uuid::new_v4().to_simple());
+        let mut admin = Client::connect(&url, NoTls).unwrap();
+        admin.batch_execute(&format!("create database {}", name)).unwrap();
+
+        let (server, _) = url.rsplit_once('/').unwrap();
+        let mut db = Database::open(&format!("{}/{}", server, name));
+        db.init_tables();
+        recover_amended_and_traded_orders(&mut db);
+        serve_every_fill_once(&mut db);
+
+        // a lost connection fails the writes and lookups of the engine instead of panicking it
+        admin.execute("select pg_terminate_backend(pid) from pg_stat_activity where datname = $1", &[&name]).unwrap();
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.00, 1, Card::Pikachu, 1);
+        assert!(db.write(&[Write::Request(req), Write::InsertStatus(req.get_uuid(), OrderStatus::Confirmed)]).is_err());
+        assert!(db.find_order(&req.get_uuid()).is_err());
+        drop(db);
+        admin.batch_execute(&format!("drop database {}", name)).unwrap();
+    }
+
+    fn recover_amended_and_traded_orders(store: &mut dyn Store) {
+        let now = Utc::now();
+        let first = RequestOrder::new(Uuid::new_v4(), now, Side::Buy, 3.00, 5, Card::Pikachu, 1);
+        let second = RequestOrder::new(Uuid::new_v4(), now + Duration::milliseconds(1), Side::Buy, 3.00, 2, Card::Pikachu, 2);
+        let cancelled = RequestOrder::new(Uuid::new_v4(), now + Duration::milliseconds(2), Side::Buy, 3.00, 1, Card::Pikachu, 3);
+        for (req, status) in [(first, OrderStatus::PartiallyFilled), (second, OrderStatus::Confirmed), (cancelled, OrderStatus::Cancelled)].iter() {
+            store.insert_request_table(req);
+            store.insert_order_status(&req.get_uuid(), status);
+        }
+        store.insert_trade_table(&first.get_uuid(), &Uuid::new_v4(), &Card::Pikachu, &Trade::new(now, 1, 4, 3.00, 2));
+        // re-pricing the first order sends it behind the second one
+        store.insert_amend_table(&first.get_uuid(), &(now + Duration::milliseconds(3)), &Price::from(3.50), &6, &false);
+
+        let resting = store.get_realtime_tx_info(&Side::Buy, &Card::Pikachu);
+        assert_eq!(vec![(second.get_uuid(), Price::from(3.00), 2), (first.get_uuid(), Price::from(3.50), 4)], resting.iter().map(|req| (req.get_uuid(), req.get_order_px(), req.get_vol())).collect::<Vec<_>>());
+        assert_eq!(Some((Price::from(3.50), 6)), store.get_request(&first.get_uuid()).map(|req| (req.get_order_px(), req.get_vol())));
+        assert_eq!(Ok(Some(Some(OrderStatus::Cancelled))), store.find_order(&cancelled.get_uuid()).map(|found| found.map(|(_, status)| status)));
+        assert_eq!(Ok(None), store.find_order(&Uuid::new_v4()).map(|found| found.map(|(req, _)| req.get_uuid())));
+
+        let date = now.format("%Y-%m-%d").to_string();
+        assert_eq!(2, store.get_request_history(&1, &date).len());
+        assert_eq!(1, store.get_trade_history(&1, &date).len());
+        assert!(store.get_trade_history(&2, &date).is_empty());
+
+        let mut cash = store.get_expected_cash();
+        cash.sort();
+        assert_eq!(vec![(1, -600), (4, 600)], cash);
+    }
+
+    fn serve_every_fill_once(store: &mut dyn Store) {
+        let now = Utc::now();
+        let buy = RequestOrder::new(Uuid::new_v4(), now, Side::Buy, 4.00, 2, Card::Bulbasaur, 7);
+        let sell = RequestOrder::new(Uuid::new_v4(), now, Side::Sell, 4.00, 3, Card::Bulbasaur, 8);
+        let crossed = RequestOrder::new(Uuid::new_v4(), now, Side::Buy, 4.00, 1, Card::Bulbasaur, 8);
+        for req in [buy, sell, crossed].iter() {
+            store.insert_request_table(req);
+        }
+        // two fills alike at the same price and volume are two trades, while a trade with itself is a single one
+        store.insert_trade_table(&buy.get_uuid(), &sell.get_uuid(), &Card::Bulbasaur, &Trade::new(now, 7, 8, 4.00, 1));
+        store.insert_trade_table(&buy.get_uuid(), &sell.get_uuid(), &Card::Bulbasaur, &Trade::new(now, 7, 8, 4.00, 1));
+        store.insert_trade_table(&crossed.get_uuid(), &sell.get_uuid(), &Card::Bulbasaur, &Trade::new(now, 8, 8, 4.00, 1));
+
+        let date = now.format("%Y-%m-%d").to_string();
+        assert_eq!(2, store.get_trade_history(&7, &date).len());
+        assert_eq!(3, store.get_trade_history(&8, &date).len());
+    }
+}
@@ -1,15 +1,22 @@
-use crate::data_type::Card;
+use crate::candle::CandleBoard;
+use crate::data_type::{Card, Price};
+use crate::market_data::{MarketData, MarketEvent};
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use std::collections::HashMap;
 use std::collections::LinkedList;
 use std::option::Option;
+use tide::prelude::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq)]
+// trader ids of both sides are named after trader_id of an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Trade {
     tx_time: DateTime<Utc>,
+    #[serde(rename = "buy_trader_id")]
     buy_side: i32,
+    #[serde(rename = "sell_trader_id")]
     sell_side: i32,
-    tx_price: f64,
+    tx_price: Price,
     tx_vol: i32,
 }
 
@@ -18,14 +25,14 @@ impl Trade {
         tx_time: DateTime<Utc>,
         buy_side: i32,
         sell_side: i32,
-        tx_price: f64,
+        tx_price: impl Into<Price>,
         tx_vol: i32,
     ) -> Self {
         Self {
             tx_time,
             buy_side,
             sell_side,
-            tx_price,
+            tx_price: tx_price.into(),
             tx_vol,
         }
     }
@@ -42,7 +49,7 @@ impl Trade {
         &self.sell_side
     }
 
-    pub fn get_tx_price(&self) -> &f64 {
+    pub fn get_tx_price(&self) -> &Price {
         &self.tx_price
     }
 
@@ -60,11 +67,13 @@ impl Trade {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct TradeHistory {
+    #[serde(rename = "buy_trader_id")]
     buy_side: i32,
+    #[serde(rename = "sell_trader_id")]
     sell_side: i32,
-    tx_price: f64,
+    tx_price: Price,
     tx_vol: i32,
     card: Card,
 }
@@ -73,14 +82,14 @@ impl TradeHistory {
     pub fn new(
         buy_side: i32,
         sell_side: i32,
-        tx_price: f64,
+        tx_price: impl Into<Price>,
         tx_vol: i32,
         card: Card
     ) -> Self {
         Self {
             buy_side,
             sell_side,
-            tx_price,
+            tx_price: tx_price.into(),
             tx_vol,
             card
         }
@@ -94,7 +103,7 @@ impl TradeHistory {
         &self.sell_side
     }
 
-    pub fn get_tx_price(&self) -> &f64 {
+    pub fn get_tx_price(&self) -> &Price {
         &self.tx_price
     }
 
@@ -119,33 +128,70 @@ impl TradeHistory {
 #[derive(Debug, Clone)]
 pub struct TradeBoard {
     board: HashMap<Card, LinkedList<Trade>>,
+    // feed sequence numbers of the trades kept in board
+    seqs: HashMap<Card, LinkedList<u64>>,
     limit: usize,
+    feed: MarketData,
+    candles: CandleBoard,
 }
 
 impl TradeBoard {
     pub fn new() -> Self {
+        Self::for_cards(&[Card::Pikachu, Card::Bulbasaur, Card::Charmander, Card::Squirtle])
+    }
+
+    pub fn for_cards(cards: &[Card]) -> Self {
         let mut board = HashMap::new();
-        board.insert(Card::Pikachu, LinkedList::<Trade>::new());
-        board.insert(Card::Bulbasaur, LinkedList::<Trade>::new());
-        board.insert(Card::Charmander, LinkedList::<Trade>::new());
-        board.insert(Card::Squirtle, LinkedList::<Trade>::new());
+        let mut seqs = HashMap::new();
+        for card in cards.iter() {
+            board.insert(*card, LinkedList::<Trade>::new());
+            seqs.insert(*card, LinkedList::<u64>::new());
+        }
         Self {
             board: board,
+            seqs,
             limit: 50,
+            feed: MarketData::new(),
+            candles: CandleBoard::new(),
         }
     }
 
+    // trades added from now on are printed to the feed
+    pub fn set_feed(&mut self, feed: MarketData) {
+        self.feed = feed;
+    }
+
     pub fn add_trade(&mut self, card: &Card, trade: Trade) {
-        if let Some(res) = &mut self.board.get_mut(card) {
+        let seq = self.feed.publish(MarketEvent::Trade { card: *card, trade: trade.clone() });
+        if let (Some(res), Some(seqs)) = (self.board.get_mut(card), self.seqs.get_mut(card)) {
             if res.len() < self.limit {
                 res.push_back(trade);
+                seqs.push_back(seq);
             } else {
                 res.pop_front();
                 res.push_back(trade);
+                seqs.pop_front();
+                seqs.push_back(seq);
             }
         }
     }
 
+    // trades kept in board printed after the given sequence number
+    pub fn get_trades_since(&self, card: &Card, seq: u64) -> Vec<(u64, Trade)> {
+        match (self.board.get(card), self.seqs.get(card)) {
+            (Some(res), Some(seqs)) => seqs.iter().copied().zip(res.iter().cloned()).filter(|(trade_seq, _)| *trade_seq > seq).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn get_candles(&self) -> &CandleBoard {
+        &self.candles
+    }
+
+    pub fn get_candles_mut(&mut self) -> &mut CandleBoard {
+        &mut self.candles
+    }
+
     fn get_board_content_mutable(&mut self) -> &mut HashMap<Card, LinkedList<Trade>> {
         &mut self.board
     }
@@ -191,8 +237,8 @@ impl TradeBoard {
 
 #[cfg(test)]
 mod tests {
-    use crate::data_type::Card;
-    use crate::trade_board::{Trade, TradeBoard};
+    use crate::data_type::{Card, Price};
+    use crate::trade_board::{Trade, TradeBoard, TradeHistory};
     use chrono::Utc;
     use rand::Rng;
     use std::cell::RefCell;
@@ -200,7 +246,7 @@ mod tests {
 
     #[test]
     fn given_a_trade_is_made_when_fields_are_accessed_then_each_should_exists() {
-        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 1, 2, 1.00, 1);
+        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 1, 2, Price::from(1.00), 1);
         let trade = Trade::new(tx_time, buyer, seller, tx_price, tx_vol);
         assert_eq!(&tx_time, trade.get_tx_time());
         assert_eq!(&buyer, trade.get_buy_side_id());
@@ -246,7 +292,7 @@ mod tests {
         // initalize a trade board
         let mut trade_board = TradeBoard::new();
         // initilize a trade component for Bulbasaur
-        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 1, 2, 1.00, 1);
+        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 1, 2, Price::from(1.00), 1);
         let trade = Trade::new(tx_time, buyer, seller, tx_price, tx_vol);
         // add a trade into bulbasaur trade board
         trade_board.add_trade(&Card::Bulbasaur, trade);
@@ -268,7 +314,7 @@ mod tests {
         }
 
         // initilize a trade component for Charmander
-        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 2, 3, 4.00, 1);
+        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 2, 3, Price::from(4.00), 1);
         let trade = Trade::new(tx_time, buyer, seller, tx_price, tx_vol);
         // add a trade into Charmander trade board
         trade_board.add_trade(&Card::Charmander, trade);
@@ -290,7 +336,7 @@ mod tests {
         }
 
         // initilize a trade component for Pikachu
-        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 3, 4, 6.00, 1);
+        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 3, 4, Price::from(6.00), 1);
         let trade = Trade::new(tx_time, buyer, seller, tx_price, tx_vol);
         // add a trade into Pikachu trade board
         trade_board.add_trade(&Card::Pikachu, trade);
@@ -312,7 +358,7 @@ mod tests {
         }
 
         // initilize a trade component for Squirtle
-        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 4, 5, 3.00, 1);
+        let (tx_time, buyer, seller, tx_price, tx_vol) = (Utc::now(), 4, 5, Price::from(3.00), 1);
         let trade = Trade::new(tx_time, buyer, seller, tx_price, tx_vol);
         // add a trade into Squirtle trade board
         trade_board.add_trade(&Card::Squirtle, trade);
@@ -388,4 +434,34 @@ mod tests {
 
         assert_ne!(back.unwrap(), new_back.unwrap());
     }
+
+    #[test]
+    fn given_trades_made_beyond_limit_when_read_since_a_sequence_then_only_kept_later_ones_returned() {
+        let mut trade_board = TradeBoard::new();
+        for buyer in 1..=52 {
+            trade_board.add_trade(&Card::Charmander, Trade::new(Utc::now(), buyer, 100, 5.00, 1));
+        }
+        trade_board.add_trade(&Card::Squirtle, Trade::new(Utc::now(), 53, 100, 5.00, 1));
+
+        // the first two trades have been popped out
+        let kept: Vec<(u64, i32)> = trade_board.get_trades_since(&Card::Charmander, 0).into_iter().map(|(seq, trade)| (seq, *trade.get_buy_side_id())).collect();
+        assert_eq!(50, kept.len());
+        assert_eq!((3, 3), kept[0]);
+        assert_eq!(vec![(51, 51), (52, 52)], trade_board.get_trades_since(&Card::Charmander, 50).into_iter().map(|(seq, trade)| (seq, *trade.get_buy_side_id())).collect::<Vec<(u64, i32)>>());
+        assert!(trade_board.get_trades_since(&Card::Charmander, 52).is_empty());
+        assert_eq!(1, trade_board.get_trades_since(&Card::Squirtle, 0).len());
+    }
+
+    #[test]
+    fn given_trades_when_serialised_then_valid_json_with_trader_id_fields() {
+        let trade = Trade::new(Utc::now(), 3, 4, 2.55, 2);
+        let history = TradeHistory::new(3, 4, 2.55, 2, Card::Pikachu);
+        let list = serde_json::to_value(vec![trade.clone(), trade]).unwrap();
+        let elem = &list.as_array().unwrap()[0];
+        assert_eq!((3, 4, 2.55, 2), (elem["buy_trader_id"].as_i64().unwrap(), elem["sell_trader_id"].as_i64().unwrap(), elem["tx_price"].as_f64().unwrap(), elem["tx_vol"].as_i64().unwrap()));
+
+        let elem = serde_json::to_value(&history).unwrap();
+        assert_eq!("Pikachu", elem["card"]);
+        assert_eq!(history, serde_json::from_value::<TradeHistory>(elem).unwrap());
+    }
 }
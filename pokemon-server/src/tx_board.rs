@@ -1,17 +1,21 @@
-use crate::data_type::{Card, RequestOrder, Side};
-use std::collections::HashMap;
+use crate::data_type::{Card, OrderType, Price, RejectReason, RequestOrder, Side};
+use schemars::JsonSchema;
+use std::collections::{BTreeMap, HashMap};
 use std::collections::LinkedList;
+use tide::prelude::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Tag {
     uuid: Uuid,
     id: i32,
+    // remaining quantity of the order
+    vol: i32,
 }
 
 impl Tag {
-    pub fn new(id: i32, uuid: Uuid) -> Self {
-        Self { id, uuid }
+    pub fn new(id: i32, uuid: Uuid, vol: i32) -> Self {
+        Self { id, uuid, vol }
     }
 
     pub fn get_uuid(self) -> Uuid {
@@ -21,6 +25,10 @@ impl Tag {
     pub fn get_id(self) -> i32 {
         self.id
     }
+
+    pub fn get_vol(self) -> i32 {
+        self.vol
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -57,40 +65,234 @@ impl Volume {
         self.traders.push_back(tag);
     }
 
+    /**
+     * Trade qty units against the front order. The front order is
+     * popped once it has been completely filled. Returns the front
+     * order with its remaining quantity after the fill
+     **/
+    pub fn fill_front(&mut self, qty: i32) -> Option<Tag> {
+        let tag = self.traders.front_mut()?;
+        if qty <= 0 || qty > tag.vol {
+            return None;
+        }
+        tag.vol -= qty;
+        self.vol -= qty;
+
+        let filled = tag.clone();
+        if filled.vol == 0 {
+            self.traders.pop_front();
+        }
+        Some(filled)
+    }
+
     pub fn get_trader_nums(&self) -> usize {
         self.traders.len()
     }
+
+    pub fn get_traders(&self) -> &LinkedList<Tag> {
+        &self.traders
+    }
+
+    pub fn find_trader(&self, uuid: &Uuid) -> Option<&Tag> {
+        self.traders.iter().find(|tag| &tag.uuid == uuid)
+    }
+
+    pub fn resize_trader(&mut self, uuid: &Uuid, vol: i32) -> Option<Tag> {
+        let tag = self.traders.iter_mut().find(|tag| &tag.uuid == uuid)?;
+        self.vol += vol - tag.vol;
+        tag.vol = vol;
+        Some(tag.clone())
+    }
+
+    pub fn remove_trader(&mut self, uuid: &Uuid) -> Option<Tag> {
+        let pos = self.traders.iter().position(|tag| &tag.uuid == uuid)?;
+        let mut tail = self.traders.split_off(pos);
+        let tag = tail.pop_front();
+        self.traders.append(&mut tail);
+        tag
+    }
+}
+
+/**
+ * Prices accepted on a card: from min to max, both inclusive, in steps
+ * of tick. Prices off the grid are rejected instead of being rounded
+ **/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PriceGrid {
+    tick: Price,
+    min: Price,
+    max: Price,
+}
+
+impl PriceGrid {
+    pub fn new(tick: Price, min: Price, max: Price) -> Self {
+        Self { tick, min, max }
+    }
+
+    pub fn get_tick(&self) -> &Price {
+        &self.tick
+    }
+
+    pub fn get_min(&self) -> &Price {
+        &self.min
+    }
+
+    pub fn get_max(&self) -> &Price {
+        &self.max
+    }
+
+    pub fn check(&self, px: &Price) -> Result<(), RejectReason> {
+        if px < &self.min || px > &self.max {
+            Err(RejectReason::PxOutOfRange)
+        } else if !px.is_multiple_of(&self.tick) {
+            Err(RejectReason::OffTick)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for PriceGrid {
+    // one-cent ticks over the [1.00, 10.00] price range
+    fn default() -> Self {
+        Self::new(Price::from_cents(1), Price::from_cents(100), Price::from_cents(1000))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CardBoard {
-    // Hash: price -> volume
-    buy: HashMap<i32, Volume>,
-    sell: HashMap<i32, Volume>,
+    grid: PriceGrid,
+    // Ordered: price -> volume, a level is created once an order rests on it
+    buy: BTreeMap<Price, Volume>,
+    sell: BTreeMap<Price, Volume>,
 }
 
 impl CardBoard {
-    fn new() -> Self {
-        let mut buy_map = HashMap::new();
-        let mut sell_map = HashMap::new();
-        let mut px: i32 = 1;
-        while px <= 10 {
-            buy_map.insert(px, Volume::new());
-            sell_map.insert(px, Volume::new());
-            px += 1;
-        }
+    fn new(grid: PriceGrid) -> Self {
         Self {
-            buy: buy_map,
-            sell: sell_map,
+            grid,
+            buy: BTreeMap::new(),
+            sell: BTreeMap::new(),
         }
     }
 
-    pub fn get_bs_board(&mut self, property: Side) -> &mut HashMap<i32, Volume> {
+    pub fn get_grid(&self) -> &PriceGrid {
+        &self.grid
+    }
+
+    pub fn get_bs_board(&mut self, property: Side) -> &mut BTreeMap<Price, Volume> {
         match property {
             Side::Buy => &mut self.buy,
             Side::Sell => &mut self.sell,
         }
     }
+
+    pub fn get_level(&mut self, side: Side, px: Price) -> &mut Volume {
+        self.get_bs_board(side).entry(px).or_insert_with(Volume::new)
+    }
+
+    // state of a single level, an absent level is reported as emptied
+    pub fn get_book_level(&self, side: Side, px: Price) -> BookLevel {
+        let levels = match side {
+            Side::Buy => &self.buy,
+            Side::Sell => &self.sell,
+        };
+        match levels.get(&px) {
+            Some(volume) => BookLevel::new(px, *volume.get_vol(), volume.get_trader_nums()),
+            None => BookLevel::new(px, 0, 0),
+        }
+    }
+
+    /**
+     * Aggregate the best depth levels of each side, best price first.
+     * Levels without resting orders are left out of the snapshot
+     **/
+    pub fn get_book(&self, card: Card, depth: usize) -> OrderBook {
+        let to_level = |(px, volume): (&Price, &Volume)| BookLevel::new(*px, *volume.get_vol(), volume.get_trader_nums());
+        let bids: Vec<BookLevel> = self.buy.iter().rev().filter(|(_, volume)| volume.get_vol() > &0).take(depth).map(to_level).collect();
+        let asks: Vec<BookLevel> = self.sell.iter().filter(|(_, volume)| volume.get_vol() > &0).take(depth).map(to_level).collect();
+        OrderBook::new(card, bids, asks)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BookLevel {
+    px: Price,
+    vol: i32,
+    // number of orders resting on the level
+    orders: usize,
+}
+
+impl BookLevel {
+    pub fn new(px: Price, vol: i32, orders: usize) -> Self {
+        Self { px, vol, orders }
+    }
+
+    pub fn get_px(&self) -> &Price {
+        &self.px
+    }
+
+    pub fn get_vol(&self) -> &i32 {
+        &self.vol
+    }
+
+    pub fn get_orders(&self) -> &usize {
+        &self.orders
+    }
+}
+
+// snapshot of the resting book of a card
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct OrderBook {
+    card: Card,
+    bids: Vec<BookLevel>,
+    asks: Vec<BookLevel>,
+    best_bid: Option<Price>,
+    best_ask: Option<Price>,
+    spread: Option<Price>,
+}
+
+impl OrderBook {
+    pub fn new(card: Card, bids: Vec<BookLevel>, asks: Vec<BookLevel>) -> Self {
+        let best_bid = bids.first().map(|level| level.px);
+        let best_ask = asks.first().map(|level| level.px);
+        let spread = match (best_bid, best_ask) {
+            (Some(bid), Some(ask)) => Some(Price::from_cents(ask.get_cents() - bid.get_cents())),
+            _ => None,
+        };
+        Self {
+            card,
+            bids,
+            asks,
+            best_bid,
+            best_ask,
+            spread,
+        }
+    }
+
+    pub fn get_card(&self) -> &Card {
+        &self.card
+    }
+
+    pub fn get_bids(&self) -> &Vec<BookLevel> {
+        &self.bids
+    }
+
+    pub fn get_asks(&self) -> &Vec<BookLevel> {
+        &self.asks
+    }
+
+    pub fn get_best_bid(&self) -> &Option<Price> {
+        &self.best_bid
+    }
+
+    pub fn get_best_ask(&self) -> &Option<Price> {
+        &self.best_ask
+    }
+
+    pub fn get_spread(&self) -> &Option<Price> {
+        &self.spread
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -100,11 +302,21 @@ pub struct TxBoard {
 
 impl TxBoard {
     pub fn new() -> Self {
+        Self::with_grids(&HashMap::new())
+    }
+
+    // cards absent from grids fall back to the default price grid
+    pub fn with_grids(grids: &HashMap<Card, PriceGrid>) -> Self {
+        Self::for_cards(&[Card::Pikachu, Card::Bulbasaur, Card::Charmander, Card::Squirtle], grids)
+    }
+
+    // a board holding the given cards only, e.g. the one of a matching engine
+    pub fn for_cards(cards: &[Card], grids: &HashMap<Card, PriceGrid>) -> Self {
         let mut board = HashMap::new();
-        board.insert(Card::Pikachu, CardBoard::new());
-        board.insert(Card::Bulbasaur, CardBoard::new());
-        board.insert(Card::Charmander, CardBoard::new());
-        board.insert(Card::Squirtle, CardBoard::new());
+        for card in cards.iter() {
+            let grid = grids.get(card).copied().unwrap_or_default();
+            board.insert(*card, CardBoard::new(grid));
+        }
         Self { content: board }
     }
 
@@ -114,31 +326,96 @@ impl TxBoard {
 
     pub fn add_tx_req(&mut self, req: &RequestOrder) {
         if let Some(res) = self.content.get_mut(&req.get_card()) {
-            let card_board = res.get_bs_board(req.get_side());
-            let tag = Tag::new(req.get_trade_id(), req.get_uuid());
-            if let Some(cur_vol) = card_board.get_mut(&(req.get_order_px() as i32)) {
-                cur_vol.set_vol(cur_vol.get_vol() + req.get_vol());
-                cur_vol.push_trader(tag);
-            }
+            let cur_vol = res.get_level(req.get_side(), req.get_order_px());
+            let tag = Tag::new(req.get_trade_id(), req.get_uuid(), req.get_vol());
+            cur_vol.set_vol(cur_vol.get_vol() + req.get_vol());
+            cur_vol.push_trader(tag);
+        }
+    }
+
+    pub fn remove_tx_req(&mut self, req: &RequestOrder) -> Option<Tag> {
+        let card_board = self.content.get_mut(&req.get_card())?.get_bs_board(req.get_side());
+        let cur_vol = card_board.get_mut(&req.get_order_px())?;
+        let tag = cur_vol.remove_trader(&req.get_uuid())?;
+        cur_vol.set_vol(cur_vol.get_vol() - tag.vol);
+        Some(tag)
+    }
+
+    pub fn get_board(&self, card: &Card) -> Option<&CardBoard> {
+        self.content.get(card)
+    }
+
+    pub fn get_book(&self, card: &Card, depth: usize) -> Option<OrderBook> {
+        self.content.get(card).map(|card_board| card_board.get_book(*card, depth))
+    }
+
+    // orders of a trader resting on the boards
+    pub fn get_open_orders(&self, trader_id: &i32) -> usize {
+        self.content
+            .values()
+            .flat_map(|card_board| card_board.buy.values().chain(card_board.sell.values()))
+            .map(|volume| volume.get_traders().iter().filter(|tag| tag.id == *trader_id).count())
+            .sum()
+    }
+
+    // traders of the orders resting on the boards, once for each order
+    pub fn get_resting_traders(&self) -> Vec<i32> {
+        self.content
+            .values()
+            .flat_map(|card_board| card_board.buy.values().chain(card_board.sell.values()))
+            .flat_map(|volume| volume.get_traders().iter().map(|tag| tag.id))
+            .collect()
+    }
+
+    // total quantity and notional of the orders of a trader resting on a side of a card
+    pub fn get_open_exposure(&self, card: &Card, side: Side, trader_id: &i32) -> (i32, Price) {
+        let levels = match (self.content.get(card), side) {
+            (Some(card_board), Side::Buy) => &card_board.buy,
+            (Some(card_board), Side::Sell) => &card_board.sell,
+            (None, _) => return (0, Price::default()),
+        };
+        levels.iter().fold((0, Price::default()), |(vol, notional), (px, volume)| {
+            let own: i32 = volume.get_traders().iter().filter(|tag| tag.id == *trader_id).map(|tag| tag.vol).sum();
+            (vol + own, Price::from_cents(notional.get_cents() + px.times(own).get_cents()))
+        })
+    }
+
+    pub fn get_grid(&self, card: &Card) -> Option<&PriceGrid> {
+        self.content.get(card).map(|card_board| card_board.get_grid())
+    }
+
+    /**
+     * Check the price of a limit order against the grid of its card.
+     * Market orders are exempted since their prices are never used
+     **/
+    pub fn check_order_px(&self, req: &RequestOrder) -> Result<(), RejectReason> {
+        if req.get_order_type() == OrderType::Market {
+            return Ok(());
+        }
+        match self.get_grid(&req.get_card()) {
+            Some(grid) => grid.check(&req.get_order_px()),
+            None => Ok(()),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::data_type::{Card, RequestOrder, Side};
-    use crate::tx_board::{CardBoard, Tag, TxBoard, Volume};
+    use crate::data_type::{Card, OrderType, Price, RejectReason, RequestOrder, Side};
+    use crate::tx_board::{CardBoard, PriceGrid, Tag, TxBoard, Volume};
     use chrono::Utc;
+    use std::collections::HashMap;
     use std::sync::Arc;
     use std::sync::Mutex;
     use uuid::Uuid;
 
     #[test]
     fn given_uuid_and_id_when_tag_instatiated_then_could_read_fields() {
-        let (id, uuid) = (1, Uuid::new_v4());
-        let tag: Tag = Tag::new(id, uuid);
+        let (id, uuid, vol) = (1, Uuid::new_v4(), 3);
+        let tag: Tag = Tag::new(id, uuid, vol);
         assert_eq!(id, tag.clone().get_id());
         assert_eq!(uuid, tag.clone().get_uuid());
+        assert_eq!(vol, tag.clone().get_vol());
     }
 
     #[test]
@@ -150,8 +427,8 @@ mod tests {
 
     #[test]
     fn given_volume_configured_with_vol_and_tags_when_fields_accessed_then_all_are_verifiable() {
-        let tag1: Tag = Tag::new(1, Uuid::new_v4());
-        let tag2: Tag = Tag::new(2, Uuid::new_v4());
+        let tag1: Tag = Tag::new(1, Uuid::new_v4(), 1);
+        let tag2: Tag = Tag::new(2, Uuid::new_v4(), 1);
         let mut obj: Volume = Volume::new();
         obj.set_vol(2);
         assert_eq!(&2, obj.get_vol());
@@ -161,34 +438,118 @@ mod tests {
     }
 
     #[test]
-    fn given_cardboard_initiated_when_volume_accessed_by_key_then_field_vol_is_zero() {
-        let board = Arc::new(Mutex::new(CardBoard::new()));
+    fn given_volume_with_three_tags_when_middle_one_removed_then_others_keep_their_order() {
+        let (tag1, tag2, tag3) = (Tag::new(1, Uuid::new_v4(), 1), Tag::new(2, Uuid::new_v4(), 1), Tag::new(3, Uuid::new_v4(), 1));
+        let mut obj: Volume = Volume::new();
+        obj.push_trader(tag1.clone());
+        obj.push_trader(tag2.clone());
+        obj.push_trader(tag3.clone());
+
+        assert_eq!(Some(tag2.clone()), obj.remove_trader(&tag2.clone().get_uuid()));
+        assert_eq!(None, obj.find_trader(&tag2.clone().get_uuid()));
+        assert_eq!(None, obj.remove_trader(&tag2.get_uuid()));
+        assert_eq!(Some(tag1), obj.pop_trader());
+        assert_eq!(Some(tag3), obj.pop_trader());
+    }
+
+    #[test]
+    fn given_volume_with_two_tags_when_back_one_resized_then_position_kept_and_volume_adjusted() {
+        let (tag1, tag2) = (Tag::new(1, Uuid::new_v4(), 3), Tag::new(2, Uuid::new_v4(), 1));
+        let mut obj: Volume = Volume::new();
+        obj.set_vol(4);
+        obj.push_trader(tag1.clone());
+        obj.push_trader(tag2);
+
+        if let Some(res) = obj.resize_trader(&tag1.clone().get_uuid(), 2) {
+            assert_eq!(2, res.get_vol());
+        } else {
+            panic!("[ERROR] Test Failed: tag cannot be resized");
+        }
+        assert_eq!(&3, obj.get_vol());
+        assert_eq!(Some(tag1.get_uuid()), obj.get_front_trader().map(|tag| tag.clone().get_uuid()));
+        assert_eq!(None, obj.resize_trader(&Uuid::new_v4(), 2));
+    }
+
+    #[test]
+    fn given_volume_with_a_multi_unit_tag_when_front_filled_then_tag_popped_only_after_fully_filled() {
+        let tag: Tag = Tag::new(1, Uuid::new_v4(), 3);
+        let mut obj: Volume = Volume::new();
+        obj.set_vol(3);
+        obj.push_trader(tag.clone());
+
+        // fill more than the remaining quantity is refused
+        assert_eq!(None, obj.fill_front(4));
+
+        if let Some(res) = obj.fill_front(2) {
+            assert_eq!(1, res.get_vol());
+        } else {
+            panic!("[ERROR] Test Failed: front order cannot be partially filled");
+        }
+        assert_eq!(&1, obj.get_vol());
+        assert_eq!(1, obj.get_trader_nums());
+
+        if let Some(res) = obj.fill_front(1) {
+            assert_eq!(0, res.get_vol());
+        } else {
+            panic!("[ERROR] Test Failed: front order cannot be fully filled");
+        }
+        assert_eq!(&0, obj.get_vol());
+        assert_eq!(0, obj.get_trader_nums());
+        assert_eq!(None, obj.fill_front(1));
+    }
+
+    #[test]
+    fn given_cardboard_initiated_when_volume_accessed_by_key_then_created_on_demand_with_zero_vol() {
+        let board = Arc::new(Mutex::new(CardBoard::new(PriceGrid::default())));
         let (buy_board, sell_board) = (Arc::clone(&board), Arc::clone(&board));
         for px in 1..11 {
             if let Ok(mut res) = buy_board.lock() {
-                let card_buy_board = res.get_bs_board(Side::Buy);
-                if let Some(obj) = card_buy_board.get(&px) {
-                    assert_eq!(&0, obj.get_vol());
-                } else {
-                    panic!("[ERROR] Test Failed: Cannot Access Corresponding Buy Volume");
-                }
+                assert_eq!(None, res.get_bs_board(Side::Buy).get(&Price::from(px as f64)).map(|obj| *obj.get_vol()));
+                assert_eq!(&0, res.get_level(Side::Buy, Price::from(px as f64)).get_vol());
             } else {
                 panic!("[ERROR] Test Failed When Accessing Card Buy Board");
             }
 
             if let Ok(mut res) = sell_board.lock() {
-                let card_sell_board = res.get_bs_board(Side::Buy);
-                if let Some(obj) = card_sell_board.get(&px) {
-                    assert_eq!(&0, obj.get_vol());
-                } else {
-                    panic!("[ERROR] Test Failed: Cannot Access Corresponding Sell Volume");
-                }
+                assert_eq!(None, res.get_bs_board(Side::Sell).get(&Price::from(px as f64)).map(|obj| *obj.get_vol()));
+                assert_eq!(&0, res.get_level(Side::Sell, Price::from(px as f64)).get_vol());
             } else {
-                panic!("[ERROR] Test Failed When Accessing Card Buy Board");
+                panic!("[ERROR] Test Failed When Accessing Card Sell Board");
             }
         }
     }
 
+    #[test]
+    fn given_cardboard_with_decimal_levels_when_iterated_then_levels_ordered_by_price() {
+        let mut board = CardBoard::new(PriceGrid::default());
+        for px in [3.75, 1.20, 9.05, 3.70].iter() {
+            board.get_level(Side::Sell, Price::from(*px));
+        }
+        let pxs: Vec<Price> = board.get_bs_board(Side::Sell).keys().copied().collect();
+        assert_eq!(vec![Price::from(1.20), Price::from(3.70), Price::from(3.75), Price::from(9.05)], pxs);
+    }
+
+    #[test]
+    fn given_a_price_grid_when_prices_checked_then_off_tick_and_out_of_range_ones_rejected() {
+        let grid = PriceGrid::new(Price::from(0.05), Price::from(1.00), Price::from(10.00));
+        assert_eq!(Ok(()), grid.check(&Price::from(3.75)));
+        assert_eq!(Ok(()), grid.check(&Price::from(10.00)));
+        assert_eq!(Err(RejectReason::OffTick), grid.check(&Price::from(3.77)));
+        assert_eq!(Err(RejectReason::PxOutOfRange), grid.check(&Price::from(0.95)));
+        assert_eq!(Err(RejectReason::PxOutOfRange), grid.check(&Price::from(10.05)));
+
+        let mut grids = HashMap::new();
+        grids.insert(Card::Pikachu, grid);
+        let tx_board = TxBoard::with_grids(&grids);
+        let off_tick = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.77, 1, Card::Pikachu, 1);
+        assert_eq!(Err(RejectReason::OffTick), tx_board.check_order_px(&off_tick));
+        // market orders carry no meaningful price
+        assert_eq!(Ok(()), tx_board.check_order_px(&off_tick.with_order_type(OrderType::Market)));
+        // other cards keep the default one-cent grid
+        let default = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.77, 1, Card::Squirtle, 1);
+        assert_eq!(Ok(()), tx_board.check_order_px(&default));
+    }
+
     #[test]
     fn given_txboard_initiated_when_accessed_by_card_then_corresponding_cardboards_exist() {
         let mut tx_board = TxBoard::new();
@@ -238,7 +599,7 @@ mod tests {
         if let Some(board) = tx_board.get_board_content().get_mut(&req.get_card()) {
             if let Some(check) = board
                 .get_bs_board(req.get_side())
-                .get_mut(&(req.get_order_px() as i32))
+                .get_mut(&req.get_order_px())
             {
                 assert_eq!(&1, check.get_vol());
                 assert_eq!(1, check.get_trader_nums());
@@ -258,4 +619,64 @@ mod tests {
             panic!("[ERROR] Test Failed: Card board does not exist.");
         }
     }
+
+    #[test]
+    fn given_a_queued_req_when_removed_from_tx_board_then_volume_and_traders_decreased() {
+        let mut tx_board = TxBoard::new();
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 3.00, 1, Card::Pikachu, 7);
+        tx_board.add_tx_req(&req);
+
+        if let Some(tag) = tx_board.remove_tx_req(&req) {
+            assert_eq!(req.get_uuid(), tag.clone().get_uuid());
+            assert_eq!(req.get_trade_id(), tag.get_id());
+        } else {
+            panic!("[ERROR] Test Failed: queued request cannot be removed from tx_board");
+        }
+
+        if let Some(board) = tx_board.get_board_content().get_mut(&req.get_card()) {
+            if let Some(volume) = board.get_bs_board(req.get_side()).get_mut(&Price::from(3.00)) {
+                assert_eq!(&0, volume.get_vol());
+                assert_eq!(0, volume.get_trader_nums());
+            } else {
+                panic!("[ERROR] Test Failed: Volume does not exist.");
+            }
+        } else {
+            panic!("[ERROR] Test Failed: Card board does not exist.");
+        }
+
+        // removing it twice should not touch the board any more
+        assert_eq!(None, tx_board.remove_tx_req(&req));
+    }
+
+    #[test]
+    fn given_resting_orders_when_book_snapshotted_then_levels_aggregated_best_first_with_spread() {
+        let mut tx_board = TxBoard::new();
+        let orders = [
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.00, 3, Card::Bulbasaur, 1),
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.50, 1, Card::Bulbasaur, 2),
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 2.50, 2, Card::Bulbasaur, 3),
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 1.50, 1, Card::Bulbasaur, 4),
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 3.25, 4, Card::Bulbasaur, 5),
+            RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 3.00, 1, Card::Bulbasaur, 6),
+        ];
+        for req in orders.iter() {
+            tx_board.add_tx_req(req);
+        }
+        // an emptied level is left out
+        tx_board.add_tx_req(&RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 2.75, 1, Card::Bulbasaur, 7));
+        tx_board.get_board_content().get_mut(&Card::Bulbasaur).unwrap().get_level(Side::Sell, Price::from_cents(275)).set_vol(0);
+
+        let book = tx_board.get_book(&Card::Bulbasaur, 2).unwrap();
+        let bids: Vec<(i64, i32, usize)> = book.get_bids().iter().map(|level| (level.get_px().get_cents(), *level.get_vol(), *level.get_orders())).collect();
+        let asks: Vec<(i64, i32, usize)> = book.get_asks().iter().map(|level| (level.get_px().get_cents(), *level.get_vol(), *level.get_orders())).collect();
+        assert_eq!(vec![(250, 3, 2), (200, 3, 1)], bids);
+        assert_eq!(vec![(300, 1, 1), (325, 4, 1)], asks);
+        assert_eq!(&Some(Price::from_cents(250)), book.get_best_bid());
+        assert_eq!(&Some(Price::from_cents(300)), book.get_best_ask());
+        assert_eq!(&Some(Price::from_cents(50)), book.get_spread());
+
+        let empty = tx_board.get_book(&Card::Squirtle, 10).unwrap();
+        assert!(empty.get_bids().is_empty() && empty.get_asks().is_empty());
+        assert_eq!(&None, empty.get_spread());
+    }
 }
@@ -0,0 +1,168 @@
+use crate::data_type::{Price, RejectReason, RequestOrder};
+use crate::store::Store;
+use crate::tx_board::TxBoard;
+use chrono::{Duration, Utc};
+
+/**
+ * Limits an incoming order has to satisfy before being queued. The
+ * price range and tick come from the price grid of the card, so they
+ * are checked against tx_board rather than kept here
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validator {
+    max_vol: i32,
+    max_traders: i32,
+    max_tm_skew: Duration,
+    // orders of a trader resting on the boards or waiting in the queues
+    max_open_orders: usize,
+}
+
+impl Validator {
+    pub fn new(max_vol: i32, max_traders: i32, max_tm_skew_ms: i64) -> Self {
+        Self {
+            max_vol,
+            max_traders,
+            max_tm_skew: Duration::milliseconds(max_tm_skew_ms),
+            max_open_orders: 100,
+        }
+    }
+
+    pub fn with_max_open_orders(mut self, max_open_orders: usize) -> Self {
+        self.max_open_orders = max_open_orders;
+        self
+    }
+
+    pub fn get_max_vol(&self) -> &i32 {
+        &self.max_vol
+    }
+
+    pub fn get_max_traders(&self) -> &i32 {
+        &self.max_traders
+    }
+
+    pub fn get_max_tm_skew(&self) -> &Duration {
+        &self.max_tm_skew
+    }
+
+    pub fn get_max_open_orders(&self) -> &usize {
+        &self.max_open_orders
+    }
+
+    // prices are sent as decimal numbers, which could carry digits finer than a cent
+    pub fn check_px(px: &Price) -> Result<(), RejectReason> {
+        match px.is_whole_cents() {
+            true => Ok(()),
+            false => Err(RejectReason::InvalidPrice),
+        }
+    }
+
+    // an order, or an amendment of one, is at least a card and at most max_vol cards
+    pub fn check_vol(&self, vol: &i32) -> Result<(), RejectReason> {
+        match *vol > 0 && *vol <= self.max_vol {
+            true => Ok(()),
+            false => Err(RejectReason::InvalidVolume),
+        }
+    }
+
+    pub fn validate(&self, req: &RequestOrder, tx_board: &TxBoard) -> Result<(), RejectReason> {
+        Self::check_px(&req.get_order_px())?;
+        tx_board.check_order_px(req)?;
+
+        self.check_vol(&req.get_vol())?;
+
+        // trader ids are numbered from 0
+        if req.get_trade_id() < 0 || req.get_trade_id() >= self.max_traders {
+            return Err(RejectReason::InvalidTraderId);
+        }
+
+        let skew = Utc::now().signed_duration_since(req.get_tm());
+        if skew > self.max_tm_skew || -skew > self.max_tm_skew {
+            return Err(RejectReason::TimestampSkew);
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Looked up apart from the other checks, through a store of the pool
+     * rather than the one of the matching engine. Orders still waiting in
+     * the order queue are told apart by the queue itself
+     **/
+    pub fn check_uuid(req: &RequestOrder, db: &mut dyn Store) -> Result<(), RejectReason> {
+        match db.request_exist(&req.get_uuid()) {
+            true => Err(RejectReason::DuplicateUuid),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Default for Validator {
+    // 10K traders as stated in the trading scenario
+    fn default() -> Self {
+        Self::new(1000, 10000, 5000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_type::{AmendOrder, Card, RejectReason, RequestOrder, Side};
+    use crate::store;
+    use crate::tx_board::TxBoard;
+    use crate::validator::Validator;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    #[test]
+    fn given_a_well_formed_order_when_validated_then_accepted() {
+        let (validator, tx_board) = (Validator::default(), TxBoard::new());
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.25, 3, Card::Pikachu, 1);
+        assert_eq!(Ok(()), validator.validate(&req, &tx_board));
+    }
+
+    #[test]
+    fn given_malformed_orders_when_validated_then_rejected_with_corresponding_reasons() {
+        let (validator, tx_board) = (Validator::new(10, 100, 1000), TxBoard::new());
+        let cases = vec![
+            (RejectReason::PxOutOfRange, RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 0.50, 1, Card::Pikachu, 1)),
+            (RejectReason::PxOutOfRange, RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Sell, 42.00, 1, Card::Pikachu, 1)),
+            (RejectReason::InvalidVolume, RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.00, -3, Card::Pikachu, 1)),
+            (RejectReason::InvalidVolume, RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.00, 11, Card::Pikachu, 1)),
+            (RejectReason::InvalidTraderId, RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.00, 1, Card::Pikachu, -1)),
+            (RejectReason::InvalidTraderId, RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.00, 1, Card::Pikachu, 100)),
+            (RejectReason::TimestampSkew, RequestOrder::new(Uuid::new_v4(), Utc::now() - Duration::seconds(2), Side::Buy, 5.00, 1, Card::Pikachu, 1)),
+            (RejectReason::TimestampSkew, RequestOrder::new(Uuid::new_v4(), Utc::now() + Duration::seconds(2), Side::Buy, 5.00, 1, Card::Pikachu, 1)),
+        ];
+        for (reason, req) in cases {
+            assert_eq!(Err(reason), validator.validate(&req, &tx_board));
+        }
+    }
+
+    #[test]
+    fn given_an_order_stored_when_its_uuid_reused_then_rejected_as_duplicate() {
+        let (validator, tx_board, mut db) = (Validator::default(), TxBoard::new(), store::open());
+        let req = RequestOrder::new(Uuid::new_v4(), Utc::now(), Side::Buy, 5.00, 1, Card::Pikachu, 1);
+        assert_eq!(Ok(()), validator.validate(&req, &tx_board));
+        assert_eq!(Ok(()), Validator::check_uuid(&req, &mut *db));
+
+        db.insert_request_table(&req);
+        assert_eq!(Err(RejectReason::DuplicateUuid), Validator::check_uuid(&req, &mut *db));
+    }
+
+    #[test]
+    fn given_prices_finer_than_a_cent_when_orders_read_and_validated_then_rejected_as_invalid_price() {
+        let (validator, tx_board) = (Validator::default(), TxBoard::new());
+        for px in ["5.255", "5.0001", "5.0000001"] {
+            let body = format!(r#"{{"uuid":"{}","tm":"{}","side":"Buy","order_px":{},"vol":1,"card":"Pikachu"}}"#, Uuid::new_v4(), Utc::now().to_rfc3339(), px);
+            let req = serde_json::from_str::<RequestOrder>(&body).unwrap().with_trader_id(1);
+            assert_eq!(Err(RejectReason::InvalidPrice), validator.validate(&req, &tx_board));
+
+            let amend = serde_json::from_str::<AmendOrder>(&format!(r#"{{"order_px":{},"vol":1}}"#, px)).unwrap();
+            assert_eq!(Err(RejectReason::InvalidPrice), Validator::check_px(&amend.get_order_px()));
+        }
+
+        let body = format!(r#"{{"uuid":"{}","tm":"{}","side":"Buy","order_px":5.25,"vol":1,"card":"Pikachu"}}"#, Uuid::new_v4(), Utc::now().to_rfc3339());
+        let req = serde_json::from_str::<RequestOrder>(&body).unwrap().with_trader_id(1);
+        assert_eq!("5.25", req.get_order_px().to_string());
+        assert_eq!(Ok(()), validator.validate(&req, &tx_board));
+    }
+}
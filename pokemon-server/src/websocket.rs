@@ -0,0 +1,102 @@
+use crate::data_type::{ProcessStatus, Rsp};
+use crate::market_data::{Action, MarketData, Subscription, Topic};
+use async_std::channel;
+use async_std::task;
+use async_tungstenite::tungstenite::handshake::derive_accept_key;
+use async_tungstenite::tungstenite::protocol::Role;
+use async_tungstenite::tungstenite::Message;
+use async_tungstenite::WebSocketStream;
+use futures_util::{future, stream, SinkExt, StreamExt};
+use tide::http::upgrade::Connection;
+use tide::{Body, Request, Response, StatusCode};
+
+/**
+ * Upgrade a request to a WebSocket streaming the market data. The
+ * handshake is answered here and the connection is served by a task
+ * of its own once the upgrade completes. Orders of a trader are only
+ * streamed to a connection signed by that trader
+ **/
+pub async fn upgrade(req: Request<()>, feed: MarketData, identity: Option<i32>) -> tide::Result {
+    let is_websocket = req.header("Upgrade").is_some_and(|value| value.as_str().eq_ignore_ascii_case("websocket"));
+    let key = match (is_websocket, req.header("Sec-WebSocket-Key")) {
+        (true, Some(key)) => key.as_str().to_owned(),
+        _ => {
+            let mut res = Response::new(StatusCode::UpgradeRequired);
+            let rsp = Rsp::<String>::new(ProcessStatus::Failed, String::from("WebSocket upgrade expected"), String::from("{}"));
+            res.set_body(Body::from_json(&rsp)?);
+            return Ok(res)
+        }
+    };
+
+    let mut res = tide::http::Response::new(StatusCode::SwitchingProtocols);
+    res.insert_header("Upgrade", "websocket");
+    res.insert_header("Connection", "Upgrade");
+    res.insert_header("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes()));
+
+    let upgrade = res.recv_upgrade().await;
+    task::spawn(async move {
+        if let Some(connection) = upgrade.await {
+            let ws = WebSocketStream::from_raw_socket(connection, Role::Server, None).await;
+            serve(ws, feed, identity).await;
+        }
+    });
+    Ok(res.into())
+}
+
+// reply of a subscribe/unsubscribe message, data is the latest seq of the topic
+fn reply(feed: &MarketData, id: &u64, identity: &Option<i32>, text: &str) -> Rsp<u64> {
+    let subscription = match serde_json::from_str::<Subscription>(text) {
+        Ok(subscription) => subscription,
+        Err(e) => return Rsp::<u64>::new(ProcessStatus::Failed, e.to_string(), 0),
+    };
+    let topic: Topic = match subscription.get_topic() {
+        Some(topic) => topic,
+        None => return Rsp::<u64>::new(ProcessStatus::Failed, String::from("either card or trader_id is expected"), 0),
+    };
+    if let Topic::Trader(trader_id) = topic {
+        if *identity != Some(trader_id) {
+            return Rsp::<u64>::new(ProcessStatus::Failed, format!("not authorized to the orders of trader {}", trader_id), 0);
+        }
+    }
+
+    match subscription.get_action() {
+        Action::Subscribe => feed.subscribe(id, topic),
+        Action::Unsubscribe => feed.unsubscribe(id, &topic),
+    };
+    let msg = format!("{:?} {:?}", subscription.get_action(), topic);
+    Rsp::<u64>::new(ProcessStatus::Success, msg, feed.get_seq(&topic))
+}
+
+async fn serve(ws: WebSocketStream<Connection>, feed: MarketData, identity: Option<i32>) {
+    let (id, events) = feed.connect();
+    let (replies, replied) = channel::unbounded::<String>();
+    let (mut sink, mut source) = ws.split();
+
+    // events and replies share the outgoing half of the socket
+    let forwarder = task::spawn(async move {
+        let events = events.filter_map(|msg| future::ready(serde_json::to_string(&msg).ok()));
+        let mut outgoing = stream::select(events, replied);
+        while let Some(text) = outgoing.next().await {
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(message)) = source.next().await {
+        match message {
+            Message::Text(text) => {
+                if let Ok(text) = serde_json::to_string(&reply(&feed, &id, &identity, &text)) {
+                    let _ = replies.send(text).await;
+                }
+            },
+            Message::Close(_) => break,
+            _ => {},
+        }
+    }
+
+    // both channels are closed, so the forwarder stops as well
+    feed.disconnect(&id);
+    drop(replies);
+    forwarder.await;
+}
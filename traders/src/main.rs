@@ -25,10 +25,12 @@ fn main() {
     }
 
     let num: i32 = cfg.get_trader_nums();
+    let credentials = cfg.get_credentials();
     for i in 0..num {
         let property = cfg.clone();
+        let keys = credentials.get(&i).cloned();
         thread::spawn(move || {
-            let trader = Trader::new(i, property.clone());
+            let trader = Trader::new(i, property.clone(), keys);
             trader.send_request();
         });
     }
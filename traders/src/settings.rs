@@ -1,4 +1,6 @@
 use ini;
+use std::collections::HashMap;
+use std::fs;
 
 #[derive(Clone)]
 pub struct Settings {
@@ -48,6 +50,29 @@ impl Settings {
         num.parse::<i32>().unwrap()
     }    
 
+    /**
+     * Key pairs issued by `pokemon-server [config] register [trader_id...]`,
+     * read from the csv file given by keys with lines of trader_id,api_key,secret
+     **/
+    pub fn get_credentials(&self) -> HashMap<i32, (String, String)> {
+        let config = ini!(self.path.as_str());
+        let mut credentials = HashMap::new();
+        if let Some(path) = config["clients"].get("keys").and_then(|value| value.clone()) {
+            // traders without keys only reach the public endpoints
+            let keys = fs::read_to_string(path.trim()).unwrap_or_else(|e| {
+                println!("[WARN] keys {} unavailable: {}", path.trim(), e);
+                String::new()
+            });
+            for line in keys.lines().filter(|line| !line.trim().is_empty()) {
+                let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+                if let [id, api_key, secret] = fields[..] {
+                    credentials.insert(id.parse::<i32>().unwrap(), (api_key.to_owned(), secret.to_owned()));
+                }
+            }
+        }
+        credentials
+    }
+
     pub fn get_duration(&self) -> i32 {
         let mut duration = String::new();
         let config = ini!(self.path.as_str());
@@ -2,18 +2,40 @@ use crate::data_type::{Card, Side};
 use crate::settings::Settings;
 use chrono::prelude::*;
 use chrono::Local;
+use hmac::{Hmac, Mac};
 use rand::Rng;
+use sha2::Sha256;
 use std::{thread, time};
 use uuid::Uuid;
 
 pub struct Trader {
     id: i32,
     config: Settings,
+    // api_key and secret issued by the server
+    credentials: Option<(String, String)>,
 }
 
 impl Trader {
-    pub fn new(id: i32, config: Settings) -> Self {
-        Trader { id, config }
+    pub fn new(id: i32, config: Settings, credentials: Option<(String, String)>) -> Self {
+        Trader { id, config, credentials }
+    }
+
+    // a request signed by HMAC-SHA256 of {timestamp}\n{nonce}\n{METHOD}\n{path}\n{body}
+    fn request(&self, method: &str, path: &str, body: &str) -> ureq::Request {
+        let url = format!("http://{}{}", &self.config.get_connected_url(), path);
+        let req = ureq::request(method, url.as_str());
+        match &self.credentials {
+            Some((api_key, secret)) => {
+                let (tm, nonce) = (Utc::now().timestamp_millis(), Uuid::new_v4());
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+                mac.update(format!("{}\n{}\n{}\n{}\n{}", tm, nonce, method, path, body).as_bytes());
+                req.set("X-Api-Key", api_key)
+                    .set("X-Timestamp", &tm.to_string())
+                    .set("X-Nonce", &nonce.to_string())
+                    .set("X-Signature", &hex::encode(mac.finalize().into_bytes()))
+            },
+            None => req,
+        }
     }
 
     pub fn send_request(&self) {
@@ -65,8 +87,9 @@ impl Trader {
             1 => Side::Sell,
             _ => Side::Buy,
         };
-        let order_px = rand::thread_rng().gen_range(1..11) as f64;
-        let vol: i32 = 1;
+        // prices on the default 0.05 tick grid between 1.00 and 10.00
+        let order_px = (rand::thread_rng().gen_range(20..201) * 5) as f64 / 100.0;
+        let vol: i32 = rand::thread_rng().gen_range(1..4);
         let card = match rand::thread_rng().gen_range(0..4) {
             0 => Card::Pikachu,
             1 => Card::Bulbasaur,
@@ -74,18 +97,18 @@ impl Trader {
             3 => Card::Squirtle,
             _ => Card::Pikachu,
         };
-        let url = format!("http://{}/api/pokemon/card", &self.config.get_connected_url());
-        let rsp: String = ureq::post(url.as_str())
+        let body = ureq::json!({
+            "uuid": uuid,
+            "tm": tm,
+            "side": side,
+            "order_px": order_px,
+            "vol": vol,
+            "card": card,
+            "trader_id": &self.id
+        }).to_string();
+        let rsp: String = self.request("POST", "/api/pokemon/card", &body)
             .set("Content-type", "application/json")
-            .send_json(ureq::json!({
-                "uuid": uuid,
-                "tm": tm,
-                "side": side,
-                "order_px": order_px,
-                "vol": vol,
-                "card": card,
-                "trader_id": &self.id
-            }))?
+            .send_string(&body)?
             .into_string()?;
         println!("{}", &rsp);
         Ok(())
@@ -106,8 +129,8 @@ impl Trader {
     }
 
     fn get_order(&self) -> Result<(), ureq::Error> {
-        let url: String = format!("http://{}/api/pokemon/order/{}", &self.config.get_connected_url(), &self.id);
-        let rsp: String = ureq::get(&url).call()?.into_string()?;
+        let path: String = format!("/api/pokemon/order/{}", &self.id);
+        let rsp: String = self.request("GET", &path, "").call()?.into_string()?;
         println!("{}", &rsp);
         Ok(())
     }
@@ -115,8 +138,8 @@ impl Trader {
     fn get_trade_record(&self) -> Result<(), ureq::Error> {
         let tm = Local::now();
         let date = tm.format("%Y-%m-%d");
-        let url: String = format!("http://{}/api/pokemon/trade/history?id={}&date={}", &self.config.get_connected_url(), self.id, date);
-        let rsp: String = ureq::get(&url).call()?.into_string()?;
+        let path: String = format!("/api/pokemon/trade/history?id={}&date={}", self.id, date);
+        let rsp: String = self.request("GET", &path, "").call()?.into_string()?;
         println!("{}", &rsp);
         Ok(())        
     }
@@ -124,8 +147,8 @@ impl Trader {
     fn get_request_record(&self) -> Result<(), ureq::Error> {
         let tm = Local::now();
         let date = tm.format("%Y-%m-%d");
-        let url: String = format!("http://{}/api/pokemon/request/history?id={}&date={}", &self.config.get_connected_url(), self.id, date);
-        let rsp: String = ureq::get(&url).call()?.into_string()?;
+        let path: String = format!("/api/pokemon/request/history?id={}&date={}", self.id, date);
+        let rsp: String = self.request("GET", &path, "").call()?.into_string()?;
         println!("{}", &rsp);
         Ok(())        
     }